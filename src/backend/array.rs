@@ -0,0 +1,223 @@
+use super::{BackendError, BackendResult, Evaluate, Feature, FunctionBuilder, Operand, Score};
+use crate::mir::{MangledVar, MirExpression, MirExpressionKind, MirType};
+
+impl FunctionBuilder<'_> {
+    /// The length of the array `expr` evaluates to.
+    fn array_len(&self, expr: &MirExpression<MangledVar>) -> BackendResult<u32> {
+        match self.value_type(expr)? {
+            Some(MirType::Array(_, len)) => Ok(len),
+            _ => Err(BackendError::Unsupported(
+                "indices into values other than arrays",
+                expr.span,
+            )),
+        }
+    }
+
+    /// Writes an array literal to `target`, constant elements are part of the list it starts
+    /// from and others are written one at a time.
+    pub(super) fn array_literal(
+        &mut self,
+        target: &Score,
+        elements: Vec<MirExpression<MangledVar>>,
+    ) -> BackendResult<()> {
+        let mut values = vec![];
+        let mut computed = vec![];
+        for (index, element) in elements.into_iter().enumerate() {
+            match element.kind {
+                MirExpressionKind::Literal(literal) => {
+                    values.push(self.literal_value(literal, element.span)?)
+                }
+                kind => {
+                    values.push(0);
+                    computed.push((index, MirExpression::new(kind, element.span)));
+                }
+            }
+        }
+
+        let list = self.datapack.stored(target);
+        let values: Vec<String> = values.iter().map(i32::to_string).collect();
+        self.push(format!(
+            "data modify {} set value [{}]",
+            list,
+            values.join(", ")
+        ));
+
+        for (index, element) in computed {
+            let operand = element.evaluate(self)?;
+            let score = self.operand_score(operand);
+            self.push(format!(
+                "execute store result {}[{}] int 1 run scoreboard players get {}",
+                list, index, score
+            ));
+        }
+        Ok(())
+    }
+
+    /// Stores element `index` of the array `array` evaluates to in `target`.
+    pub(super) fn read_element(
+        &mut self,
+        array: MirExpression<MangledVar>,
+        index: MirExpression<MangledVar>,
+        target: &Score,
+    ) -> BackendResult<()> {
+        let len = self.array_len(&array)?;
+        let holder = self.value_score(array)?;
+
+        // Lookup trees check the index once per element, it must not change while they run.
+        let index = if index.reads(target) {
+            let temporary = self.temporary();
+            index.evaluate_into(self, &temporary)?;
+            Operand::Score(temporary)
+        } else {
+            index.evaluate(self)?
+        };
+
+        let list = self.datapack.stored(&holder);
+        self.element(&list, len, index, |element| {
+            format!(
+                "execute store result score {} run data get {}",
+                target, element
+            )
+        })
+    }
+
+    /// Writes `value` to element `index` of the array `array` holds.
+    pub(super) fn write_element(
+        &mut self,
+        array: MirExpression<MangledVar>,
+        index: MirExpression<MangledVar>,
+        value: MirExpression<MangledVar>,
+    ) -> BackendResult<()> {
+        let len = self.array_len(&array)?;
+        let holder = self.value_score(array)?;
+
+        // The index is evaluated first, the value must not change it afterwards.
+        let index = match index.evaluate(self)? {
+            Operand::Score(score) if value.has_side_effects() && !score.is_temporary() => {
+                let temporary = self.temporary();
+                self.copy(&temporary, &score);
+                Operand::Score(temporary)
+            }
+            index => index,
+        };
+
+        let list = self.datapack.stored(&holder);
+        match value.evaluate(self)? {
+            Operand::Constant(value) => self.element(&list, len, index, |element| {
+                format!("data modify {} set value {}", element, value)
+            }),
+            Operand::Score(score) => self.element(&list, len, index, |element| {
+                format!(
+                    "execute store result {} int 1 run scoreboard players get {}",
+                    element, score
+                )
+            }),
+        }
+    }
+
+    /// Runs the command `command` builds from the path to element `index` of `list`, an array of
+    /// length `len`. Indices only known at runtime are passed to a function macro where those
+    /// exist, older versions check the index against every possible value instead.
+    fn element(
+        &mut self,
+        list: &str,
+        len: u32,
+        index: Operand,
+        command: impl Fn(&str) -> String,
+    ) -> BackendResult<()> {
+        let index = match index {
+            Operand::Constant(index) => {
+                self.push(command(&format!("{}[{}]", list, index)));
+                return Ok(());
+            }
+            Operand::Score(index) => index,
+        };
+
+        if self.supports(Feature::FunctionMacros) {
+            let arguments = self.datapack.macro_arguments();
+            self.push(format!(
+                "execute store result {}.index int 1 run scoreboard players get {}",
+                arguments, index
+            ));
+            self.run_macro("index", &command(&format!("{}[$(index)]", list)));
+            return Ok(());
+        }
+
+        let mut elements = vec![];
+        for element in 0..len as i32 {
+            let generated = self.generate("index", true, |builder| {
+                builder.push(command(&format!("{}[{}]", list, element)));
+                Ok(())
+            })?;
+            elements.push((element, element, generated));
+        }
+        self.dispatch(&index, &elements, "index")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::{
+        tests::{lower, lower_for},
+        BackendError, Feature, MinecraftVersion,
+    };
+
+    #[test]
+    fn dynamic_indices_use_macros() -> eyre::Result<()> {
+        let datapack = lower(
+            "static function f(i: int): int {
+                let values: int[3] = [1, i, 3];
+                values[i] = values[0] + 1;
+                return values[i];
+            }",
+        )??;
+
+        let f = datapack.function("f").unwrap();
+        let list = "storage test:ss_global \"69508BA7208F6F71\"";
+        assert_eq!(f[0], format!("data modify {} set value [1, 0, 3]", list));
+        assert!(f[1].starts_with(&format!("execute store result {}[1] int 1 run", list)));
+        assert!(f[4].starts_with("execute store result storage test:ss_global macro.index"));
+        assert_eq!(
+            f[5],
+            "function test:f/index_0 with storage test:ss_global macro"
+        );
+
+        let index = datapack.function("f/index_0").unwrap();
+        assert_eq!(
+            index,
+            [format!(
+                "$execute store result {}[$(index)] int 1 run scoreboard players get #test.t0 ss_global",
+                list
+            )]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn old_versions_use_lookup_trees() -> eyre::Result<()> {
+        let datapack = lower_for(
+            "static function f(values: int[6], i: int): int {
+                return values[i];
+            }",
+            MinecraftVersion::new(20, 0),
+        )??;
+
+        let f = datapack.function("f").unwrap();
+        assert!(f[0].ends_with("matches ..2 run function test:f/index_6"));
+        assert!(f[1].ends_with("matches 3.. run function test:f/index_7"));
+
+        let low = datapack.function("f/index_6").unwrap();
+        assert_eq!(low.len(), 3);
+        assert!(low[2].contains("matches 2 store result score #return ss_global run data get"));
+        assert!(low[2].ends_with("[2]"));
+
+        assert!(matches!(
+            lower_for(
+                "static values: int[2] = [1, 2];",
+                MinecraftVersion::new(14, 4)
+            )?,
+            Err(BackendError::UnsupportedFeature(Feature::Storage, _, _))
+        ));
+        Ok(())
+    }
+}
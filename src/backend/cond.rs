@@ -0,0 +1,325 @@
+use super::{BackendResult, Evaluate, FunctionBuilder, Operand, Score};
+use crate::mir::{
+    MangledVar, MirArmBody, MirBinaryOp, MirExpression, MirExpressionKind, MirUnaryOp,
+};
+
+/// A condition checked by `execute` subcommands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Condition {
+    /// Known while compiling, no check is emitted.
+    Constant(bool),
+    /// Subcommands like `if score … matches 1` which all have to pass.
+    Checks(Vec<String>),
+}
+
+impl Condition {
+    pub fn check(check: String) -> Self {
+        Self::Checks(vec![check])
+    }
+
+    /// Holds when the boolean in `score` is `true`.
+    pub fn truthy(score: &Score) -> Self {
+        Self::check(format!("if score {} matches 1", score))
+    }
+
+    pub fn and(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::Constant(false), _) | (_, Self::Constant(false)) => Self::Constant(false),
+            (Self::Constant(true), other) | (other, Self::Constant(true)) => other,
+            (Self::Checks(mut checks), Self::Checks(other)) => {
+                checks.extend(other);
+                Self::Checks(checks)
+            }
+        }
+    }
+
+    /// The inverse condition, only a single check can be negated without a temporary.
+    pub fn inverse(self) -> Option<Self> {
+        match self {
+            Self::Constant(value) => Some(Self::Constant(!value)),
+            Self::Checks(checks) => match checks.as_slice() {
+                [check] => Some(Self::check(if let Some(rest) = check.strip_prefix("if ") {
+                    format!("unless {}", rest)
+                } else if let Some(rest) = check.strip_prefix("unless ") {
+                    format!("if {}", rest)
+                } else {
+                    return None;
+                })),
+                _ => None,
+            },
+        }
+    }
+
+    /// `command` guarded by the condition, or nothing if it can never run.
+    pub fn run(&self, command: &str) -> Option<String> {
+        match self {
+            Self::Constant(true) => Some(command.to_string()),
+            Self::Constant(false) => None,
+            // `execute … run execute …` is the same as chaining the subcommands.
+            Self::Checks(checks) => Some(match command.strip_prefix("execute ") {
+                Some(rest) => format!("execute {} {}", checks.join(" "), rest),
+                None => format!("execute {} run {}", checks.join(" "), command),
+            }),
+        }
+    }
+
+    /// Stores 1 in `target` if the condition holds and 0 otherwise.
+    pub fn store(&self, builder: &mut FunctionBuilder, target: &Score) {
+        builder.push(match self {
+            Self::Constant(value) => {
+                format!("scoreboard players set {} {}", target, *value as i32)
+            }
+            Self::Checks(checks) => {
+                format!(
+                    "execute store success score {} {}",
+                    target,
+                    checks.join(" ")
+                )
+            }
+        })
+    }
+}
+
+/// Compares `left` with `right`, comparisons with constants use `matches` ranges.
+pub(super) fn comparison(left: &Score, op: MirBinaryOp, right: &Operand) -> String {
+    match right {
+        Operand::Constant(value) => {
+            let (condition, range) = match op {
+                MirBinaryOp::Equals => ("if", format!("{}", value)),
+                MirBinaryOp::NotEquals => ("unless", format!("{}", value)),
+                MirBinaryOp::LessThan => ("unless", format!("{}..", value)),
+                MirBinaryOp::LessThanEquals => ("if", format!("..{}", value)),
+                MirBinaryOp::GreaterThan => ("unless", format!("..{}", value)),
+                MirBinaryOp::GreaterThanEquals => ("if", format!("{}..", value)),
+                _ => unreachable!("{:?} is not a comparison", op),
+            };
+            format!("{} score {} matches {}", condition, left, range)
+        }
+        Operand::Score(right) => {
+            let (condition, comparison) = match op {
+                MirBinaryOp::Equals => ("if", "="),
+                MirBinaryOp::NotEquals => ("unless", "="),
+                MirBinaryOp::LessThan => ("if", "<"),
+                MirBinaryOp::LessThanEquals => ("if", "<="),
+                MirBinaryOp::GreaterThan => ("if", ">"),
+                MirBinaryOp::GreaterThanEquals => ("if", ">="),
+                _ => unreachable!("{:?} is not a comparison", op),
+            };
+            format!("{} score {} {} {}", condition, left, comparison, right)
+        }
+    }
+}
+
+pub(super) fn is_comparison(op: MirBinaryOp) -> bool {
+    matches!(
+        op,
+        MirBinaryOp::Equals
+            | MirBinaryOp::NotEquals
+            | MirBinaryOp::LessThan
+            | MirBinaryOp::LessThanEquals
+            | MirBinaryOp::GreaterThan
+            | MirBinaryOp::GreaterThanEquals
+    )
+}
+
+/// The comparison with both sides swapped, `1 < x` is `x > 1`.
+fn mirror(op: MirBinaryOp) -> MirBinaryOp {
+    match op {
+        MirBinaryOp::LessThan => MirBinaryOp::GreaterThan,
+        MirBinaryOp::LessThanEquals => MirBinaryOp::GreaterThanEquals,
+        MirBinaryOp::GreaterThan => MirBinaryOp::LessThan,
+        MirBinaryOp::GreaterThanEquals => MirBinaryOp::LessThanEquals,
+        other => other,
+    }
+}
+
+fn compare(left: i32, op: MirBinaryOp, right: i32) -> bool {
+    match op {
+        MirBinaryOp::Equals => left == right,
+        MirBinaryOp::NotEquals => left != right,
+        MirBinaryOp::LessThan => left < right,
+        MirBinaryOp::LessThanEquals => left <= right,
+        MirBinaryOp::GreaterThan => left > right,
+        MirBinaryOp::GreaterThanEquals => left >= right,
+        _ => unreachable!("{:?} is not a comparison", op),
+    }
+}
+
+impl MirExpression<MangledVar> {
+    /// Whether evaluating the expression can run commands with effects beyond its value.
+    pub(super) fn has_side_effects(&self) -> bool {
+        match &self.kind {
+            MirExpressionKind::Literal(_)
+            | MirExpressionKind::Null
+            | MirExpressionKind::Variable(_)
+            | MirExpressionKind::Nbt(_) => false,
+            MirExpressionKind::Command(_)
+            | MirExpressionKind::Tellraw(_, _)
+            | MirExpressionKind::Call(_, _) => true,
+            MirExpressionKind::Struct(_, fields) => {
+                fields.iter().any(|(_, value)| value.has_side_effects())
+            }
+            MirExpressionKind::Variant(_, _, args) | MirExpressionKind::Array(args) => {
+                args.iter().any(Self::has_side_effects)
+            }
+            MirExpressionKind::Match(match_expr) => {
+                match_expr.scrutinee.has_side_effects()
+                    || match_expr.arms.iter().any(|arm| match &arm.body {
+                        MirArmBody::Expression(expr) => expr.has_side_effects(),
+                        MirArmBody::Block(_) => true,
+                    })
+            }
+            MirExpressionKind::Property(expr, _)
+            | MirExpressionKind::UnaryOp(_, expr)
+            | MirExpressionKind::Cast(expr, _) => expr.has_side_effects(),
+            MirExpressionKind::Index(left, right) | MirExpressionKind::BinaryOp(left, _, right) => {
+                left.has_side_effects() || right.has_side_effects()
+            }
+            MirExpressionKind::Ternary(condition, then, otherwise) => {
+                condition.has_side_effects()
+                    || then.has_side_effects()
+                    || otherwise.has_side_effects()
+            }
+        }
+    }
+
+    /// Emits everything needed to check the boolean expression.
+    pub fn evaluate_condition(self, builder: &mut FunctionBuilder) -> BackendResult<Condition> {
+        match self.kind {
+            MirExpressionKind::Literal(literal) => Ok(Condition::Constant(
+                builder.literal_value(literal, self.span)? != 0,
+            )),
+            MirExpressionKind::UnaryOp(MirUnaryOp::Not, expr) => {
+                let condition = expr.evaluate_condition(builder)?;
+                match condition.clone().inverse() {
+                    Some(inverse) => Ok(inverse),
+                    None => {
+                        // Several checks only pass together, store them to invert the result.
+                        let temporary = builder.temporary();
+                        condition.store(builder, &temporary);
+                        Ok(Condition::check(format!(
+                            "if score {} matches 0",
+                            temporary
+                        )))
+                    }
+                }
+            }
+            MirExpressionKind::BinaryOp(left, op, right)
+                if matches!(op, MirBinaryOp::Equals | MirBinaryOp::NotEquals)
+                    && builder.is_string(&left)? =>
+            {
+                let differs = builder.compare_strings(*left, *right)?;
+                Ok(Condition::check(format!(
+                    "if score {} matches {}",
+                    differs,
+                    (op == MirBinaryOp::NotEquals) as i32
+                )))
+            }
+            // Comparisons of `long`s check both halves, their result is evaluated like others.
+            MirExpressionKind::BinaryOp(left, op, right)
+                if is_comparison(op) && !builder.is_long(&left)? && !builder.is_long(&right)? =>
+            {
+                let is_literal =
+                    |expr: &MirExpression<_>| matches!(expr.kind, MirExpressionKind::Literal(_));
+                let (left, op, right) = if is_literal(&left) && !is_literal(&right) {
+                    (*right, mirror(op), *left)
+                } else {
+                    (*left, op, *right)
+                };
+
+                // A variable read directly could be changed by the right side before the check.
+                let left = if right.has_side_effects() {
+                    let temporary = builder.temporary();
+                    left.evaluate_into(builder, &temporary)?;
+                    Operand::Score(temporary)
+                } else {
+                    left.evaluate(builder)?
+                };
+                let right = right.evaluate(builder)?;
+
+                let left = match (left, &right) {
+                    (Operand::Constant(left), Operand::Constant(right)) => {
+                        return Ok(Condition::Constant(compare(left, op, *right)))
+                    }
+                    (left, _) => builder.operand_score(left),
+                };
+                Ok(Condition::check(comparison(&left, op, &right)))
+            }
+            MirExpressionKind::BinaryOp(left, MirBinaryOp::And, right)
+                if !right.has_side_effects() =>
+            {
+                let left = left.evaluate_condition(builder)?;
+                let right = right.evaluate_condition(builder)?;
+                Ok(left.and(right))
+            }
+            kind => match MirExpression::new(kind, self.span).evaluate(builder)? {
+                Operand::Constant(value) => Ok(Condition::Constant(value != 0)),
+                Operand::Score(score) => Ok(Condition::truthy(&score)),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::tests::lower;
+
+    #[test]
+    fn logical_operators_short_circuit_calls() -> eyre::Result<()> {
+        let datapack = lower(
+            "static x: int = 0;
+            static done: bool = false;
+            static function next(): int { x = x + 1; return x; }
+            static function f(a: int): void { done = a > 0 && next() > 1; }",
+        )??;
+
+        let f = datapack.function("f").unwrap();
+        assert!(f[1].starts_with("execute store success score #test.t0 ss_global unless score "));
+        assert_eq!(
+            f[2],
+            "execute if score #test.t0 ss_global matches 1 run function test:f/and_0"
+        );
+        // `next` could read `done`, so it is only assigned once the call is done.
+        assert!(f[3].ends_with(" = #test.t0 ss_global"));
+        assert_eq!(
+            datapack.function("f/and_0").unwrap()[0],
+            "function test:next"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn pure_conjunctions_chain_checks() -> eyre::Result<()> {
+        let datapack = lower(
+            "static x: int = 0;
+            static y: bool = true;
+            static function f(a: int): void { if (a != 2 && y) { x = 1; } }",
+        )??;
+
+        let f = datapack.function("f").unwrap();
+        assert_eq!(f.len(), 1);
+        assert!(f[0].starts_with("execute unless score "));
+        assert!(f[0].contains(" matches 2 if score "));
+        assert!(f[0].contains(" matches 1 run scoreboard players set "));
+        Ok(())
+    }
+
+    #[test]
+    fn booleans_are_scores() -> eyre::Result<()> {
+        let datapack = lower(
+            "static a: bool = true;
+            static b: bool = false;
+            static function f(): void { a = a && b; b = !a || b; while (b) { b = false; } }",
+        )??;
+
+        let f = datapack.function("f").unwrap();
+        // `&&` is the minimum of both sides and `||` their maximum.
+        assert!(f[0].starts_with("scoreboard players operation ") && f[0].contains(" < "));
+        assert!(f[3].starts_with("scoreboard players operation #test.t0 ss_global > "));
+        assert!(
+            f[5].starts_with("execute if score ")
+                && f[5].ends_with(" matches 1 run function test:f/loop_0")
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,506 @@
+use super::{
+    cond::comparison, display_path, storage::escape, BackendError, BackendResult, Condition,
+    FixedPoint, FunctionBuilder, Operand, Score,
+};
+use crate::{
+    hir::mir::MirPrimitive,
+    mir::{
+        AbsolutePath, CommandPart, MangledVar, MirArmBody, MirBinaryOp, MirExpression,
+        MirExpressionKind, MirType, MirUnaryOp,
+    },
+    token::LiteralType,
+    Span,
+};
+
+pub trait Evaluate: Sized {
+    /// Stores the value of the expression in `target`.
+    fn evaluate_into(self, builder: &mut FunctionBuilder, target: &Score) -> BackendResult<()>;
+
+    /// Evaluates the expression for its side effects only.
+    fn evaluate_discarded(self, builder: &mut FunctionBuilder) -> BackendResult<()> {
+        let temporary = builder.temporary();
+        self.evaluate_into(builder, &temporary)
+    }
+
+    /// Evaluates the expression into an operand, avoiding a copy for constants and variables.
+    fn evaluate(self, builder: &mut FunctionBuilder) -> BackendResult<Operand> {
+        let temporary = builder.temporary();
+        self.evaluate_into(builder, &temporary)?;
+        Ok(Operand::Score(temporary))
+    }
+}
+
+impl MirExpression<MangledVar> {
+    /// Whether evaluating the expression reads `score`.
+    pub(super) fn reads(&self, score: &Score) -> bool {
+        match &self.kind {
+            MirExpressionKind::Literal(_) | MirExpressionKind::Null | MirExpressionKind::Nbt(_) => {
+                false
+            }
+            MirExpressionKind::Command(parts) => parts.iter().any(|part| match part {
+                CommandPart::Text(_) => false,
+                CommandPart::Value(value) => value.reads(score),
+            }),
+            MirExpressionKind::Variable(variable) => Score::variable(*variable).overlaps(score),
+            MirExpressionKind::Call(_, args) => args.iter().any(|arg| arg.reads(score)),
+            MirExpressionKind::Struct(_, fields) => {
+                fields.iter().any(|(_, value)| value.reads(score))
+            }
+            MirExpressionKind::Variant(_, _, args)
+            | MirExpressionKind::Array(args)
+            | MirExpressionKind::Tellraw(_, args) => args.iter().any(|arg| arg.reads(score)),
+            // Arms copy the values they bind before reading anything else.
+            MirExpressionKind::Match(match_expr) => {
+                match_expr.scrutinee.reads(score)
+                    || match_expr.arms.iter().any(|arm| match &arm.body {
+                        MirArmBody::Expression(expr) => expr.reads(score),
+                        MirArmBody::Block(_) => true,
+                    })
+            }
+            MirExpressionKind::Property(expr, _)
+            | MirExpressionKind::UnaryOp(_, expr)
+            | MirExpressionKind::Cast(expr, _) => expr.reads(score),
+            MirExpressionKind::Index(left, right) | MirExpressionKind::BinaryOp(left, _, right) => {
+                left.reads(score) || right.reads(score)
+            }
+            MirExpressionKind::Ternary(condition, then, otherwise) => {
+                condition.reads(score) || then.reads(score) || otherwise.reads(score)
+            }
+        }
+    }
+}
+
+impl FunctionBuilder<'_> {
+    /// The value of a score holding the literal, `float`s and `double`s are scaled.
+    pub(super) fn literal_value(&mut self, literal: LiteralType, span: Span) -> BackendResult<i32> {
+        match literal {
+            LiteralType::Bool(value) => Ok(value as i32),
+            LiteralType::Byte(value) => Ok(value as i32),
+            LiteralType::Short(value) => Ok(value as i32),
+            LiteralType::Int(value) => Ok(value),
+            LiteralType::Long(_) => Err(BackendError::Unsupported("long values", span)),
+            LiteralType::Float(value) => {
+                let fixed = self.datapack.float;
+                self.datapack.fixed_literal(
+                    value as f64,
+                    value.to_string(),
+                    |stored| stored as f32 == value,
+                    fixed,
+                    span,
+                )
+            }
+            LiteralType::Double(value) => {
+                let fixed = self.datapack.double;
+                self.datapack.fixed_literal(
+                    value,
+                    value.to_string(),
+                    |stored| stored == value,
+                    fixed,
+                    span,
+                )
+            }
+            LiteralType::String(_) => Err(BackendError::Unsupported("string values", span)),
+        }
+    }
+
+    pub(super) fn operand_score(&mut self, operand: Operand) -> Score {
+        match operand {
+            Operand::Constant(value) => self.constant(value),
+            Operand::Score(score) => score,
+        }
+    }
+
+    pub(super) fn copy(&mut self, target: &Score, source: &Score) {
+        if target != source {
+            self.push(format!(
+                "scoreboard players operation {} = {}",
+                target, source
+            ))
+        }
+    }
+
+    /// Passes `args` to the function at `path` and calls it, returning whether it returns a value.
+    /// `span` is the span of the call.
+    fn call(
+        &mut self,
+        path: AbsolutePath,
+        args: Vec<MirExpression<MangledVar>>,
+        span: Span,
+    ) -> BackendResult<bool> {
+        let display_name = display_path(&path);
+        let (location, signature) = self.datapack.resolve(&path, span)?;
+        let (params, returns_value) = match signature {
+            Some(signature) => (
+                signature.args.clone(),
+                signature.return_type != MirType::Primitive(MirPrimitive::Void),
+            ),
+            None if args.is_empty() => (vec![], true),
+            None => return Err(BackendError::UnknownFunction(display_name, span)),
+        };
+
+        if params.len() != args.len() {
+            return Err(BackendError::ArgumentCount(
+                display_name,
+                params.len(),
+                args.len(),
+                span,
+            ));
+        }
+
+        let param_scores: Vec<Score> = params.iter().copied().map(Score::variable).collect();
+
+        // Every argument is evaluated before any parameter is written, arguments may read them.
+        let mut values = vec![];
+        for arg in args {
+            let value = if param_scores.iter().any(|param| arg.reads(param)) {
+                let temporary = self.temporary();
+                arg.evaluate_into(self, &temporary)?;
+                Operand::Score(temporary)
+            } else {
+                arg.evaluate(self)?
+            };
+            values.push(value);
+        }
+
+        // A call that may run this function again would overwrite its variables.
+        let saves_frame = self.datapack.reenters(&path, &self.function);
+        if saves_frame {
+            self.push_frame(span)?;
+        }
+
+        for ((param, value), variable) in param_scores.iter().zip(values).zip(params) {
+            match value {
+                Operand::Constant(value) => {
+                    self.push(format!("scoreboard players set {} {}", param, value))
+                }
+                Operand::Score(score) => {
+                    let layout = self.datapack.variable_layout(variable, span)?;
+                    self.copy_value(param, &score, &layout)
+                }
+            }
+        }
+
+        self.push(format!("function {}", location));
+        if saves_frame {
+            self.pop_frame();
+        }
+        Ok(returns_value)
+    }
+
+    /// Applies `op` to `target` and `operand`, values of the fixed-point type `fixed` or the
+    /// integer type `primitive`, and stores the result in `target`.
+    pub(super) fn arithmetic(
+        &mut self,
+        target: &Score,
+        op: MirBinaryOp,
+        operand: Operand,
+        fixed: Option<FixedPoint>,
+        primitive: Option<MirPrimitive>,
+    ) {
+        match (fixed, op) {
+            (Some(fixed), MirBinaryOp::Multiply) => self.fixed_multiply(target, operand, fixed),
+            (Some(fixed), MirBinaryOp::Divide) => self.fixed_divide(target, operand, fixed),
+            _ => {
+                self.binary_op(target, op, operand);
+                // The remainder is always closer to zero than the divisor.
+                if let (
+                    Some(primitive),
+                    MirBinaryOp::Add
+                    | MirBinaryOp::Subtract
+                    | MirBinaryOp::Multiply
+                    | MirBinaryOp::Divide,
+                ) = (primitive, op)
+                {
+                    self.wrap_integer(target, primitive);
+                }
+            }
+        }
+    }
+
+    pub(super) fn binary_op(&mut self, target: &Score, op: MirBinaryOp, operand: Operand) {
+        let operation = match op {
+            MirBinaryOp::Add | MirBinaryOp::Subtract => {
+                if let Operand::Constant(value) = operand {
+                    let (command, value) = match (op, value < 0) {
+                        (MirBinaryOp::Add, false) | (MirBinaryOp::Subtract, true) => {
+                            ("add", value.unsigned_abs())
+                        }
+                        _ => ("remove", value.unsigned_abs()),
+                    };
+
+                    // `add` and `remove` only accept values representable as a positive int.
+                    if value <= i32::MAX as u32 {
+                        self.push(format!(
+                            "scoreboard players {} {} {}",
+                            command, target, value
+                        ));
+                        return;
+                    }
+                }
+
+                if op == MirBinaryOp::Add {
+                    "+="
+                } else {
+                    "-="
+                }
+            }
+            MirBinaryOp::Multiply => "*=",
+            MirBinaryOp::Divide => "/=",
+            MirBinaryOp::Modulo => "%=",
+            MirBinaryOp::Coalesce => unreachable!("`??` is lowered by `coalesce`"),
+            MirBinaryOp::Equals
+            | MirBinaryOp::NotEquals
+            | MirBinaryOp::LessThan
+            | MirBinaryOp::LessThanEquals
+            | MirBinaryOp::GreaterThan
+            | MirBinaryOp::GreaterThanEquals => {
+                let check = comparison(target, op, &operand);
+                self.push(format!("execute store success score {} {}", target, check));
+                return;
+            }
+            // Booleans are 0 or 1, so `&&` is their minimum and `||` their maximum.
+            MirBinaryOp::And => "<",
+            MirBinaryOp::Or => ">",
+        };
+
+        let operand = self.operand_score(operand);
+        self.push(format!(
+            "scoreboard players operation {} {} {}",
+            target, operation, operand
+        ));
+    }
+}
+
+impl Evaluate for MirExpression<MangledVar> {
+    fn evaluate_into(self, builder: &mut FunctionBuilder, target: &Score) -> BackendResult<()> {
+        let layout = builder.value_layout(&self)?;
+        match self.kind {
+            MirExpressionKind::Literal(LiteralType::String(text)) => builder.push(format!(
+                "data modify {} set value \"{}\"",
+                builder.datapack.stored(target),
+                escape(&text)
+            )),
+            MirExpressionKind::Literal(LiteralType::Long(value)) => {
+                builder.long_literal(target, value)
+            }
+            MirExpressionKind::Literal(literal) => {
+                let value = builder.literal_value(literal, self.span)?;
+                builder.push(format!("scoreboard players set {} {}", target, value));
+            }
+            MirExpressionKind::Null => builder.push(format!("scoreboard players reset {}", target)),
+            MirExpressionKind::Variable(variable) => {
+                builder.copy_value(target, &Score::variable(variable), &layout)
+            }
+            MirExpressionKind::Command(parts) => builder.run_command(parts, |command| {
+                format!("execute store result score {} run {}", target, command)
+            })?,
+            MirExpressionKind::Tellraw(_, _) => {
+                return Err(BackendError::VoidValue("tellraw".to_string(), self.span))
+            }
+            MirExpressionKind::Call(path, args) => {
+                let name = display_path(&path);
+                if !builder.call(path, args, self.span)? {
+                    return Err(BackendError::VoidValue(name, self.span));
+                }
+
+                builder.copy_value(target, &Score::return_register(), &layout);
+            }
+            MirExpressionKind::Struct(path, fields) => {
+                // Fields are written one at a time, values reading the target would see it
+                // partly overwritten.
+                if fields.iter().any(|(_, value)| {
+                    value.reads(target) || (value.has_side_effects() && !target.is_temporary())
+                }) {
+                    let temporary = builder.temporary();
+                    MirExpression::new(MirExpressionKind::Struct(path, fields), self.span)
+                        .evaluate_into(builder, &temporary)?;
+                    builder.copy_value(target, &temporary, &layout);
+                    return Ok(());
+                }
+
+                for (name, value) in fields {
+                    value.evaluate_into(builder, &target.field(name.inner()))?;
+                }
+            }
+            MirExpressionKind::Variant(path, variant, args) => {
+                // Like struct fields, values are written one at a time.
+                if args.iter().any(|arg| {
+                    arg.reads(target) || (arg.has_side_effects() && !target.is_temporary())
+                }) {
+                    let temporary = builder.temporary();
+                    MirExpression::new(MirExpressionKind::Variant(path, variant, args), self.span)
+                        .evaluate_into(builder, &temporary)?;
+                    builder.copy_value(target, &temporary, &layout);
+                    return Ok(());
+                }
+
+                let (discriminant, _) = builder.datapack.variant(&path, &variant, self.span)?;
+                builder.push(format!(
+                    "scoreboard players set {} {}",
+                    target, discriminant
+                ));
+                let values = target.field(variant.inner());
+                for (index, arg) in args.into_iter().enumerate() {
+                    arg.evaluate_into(builder, &values.field(&index.to_string()))?;
+                }
+            }
+            MirExpressionKind::Match(match_expr) => {
+                builder.lower_match(*match_expr, Some(target))?
+            }
+            MirExpressionKind::UnaryOp(op, expr) => {
+                let is_long = builder.is_long(&expr)?;
+                let primitive = builder.primitive_of(&expr)?;
+                expr.evaluate_into(builder, target)?;
+
+                match op {
+                    MirUnaryOp::Not => builder.push(format!(
+                        "execute store success score {} if score {} matches 0",
+                        target, target
+                    )),
+                    MirUnaryOp::Negate if is_long => builder.long_negate(target),
+                    MirUnaryOp::Negate => {
+                        let negative_one = builder.constant(-1);
+                        builder.push(format!(
+                            "scoreboard players operation {} *= {}",
+                            target, negative_one
+                        ));
+                        if let Some(primitive) = primitive {
+                            builder.wrap_integer(target, primitive);
+                        }
+                    }
+                }
+            }
+            MirExpressionKind::BinaryOp(value, MirBinaryOp::Coalesce, fallback) => {
+                builder.coalesce(target, *value, *fallback, &layout)?
+            }
+            MirExpressionKind::Ternary(condition, then, otherwise) => {
+                builder.ternary(target, *condition, *then, *otherwise)?
+            }
+            MirExpressionKind::BinaryOp(left, op, right) if builder.is_string(&left)? => match op {
+                MirBinaryOp::Equals | MirBinaryOp::NotEquals => {
+                    builder.string_comparison(target, *left, op, *right)?
+                }
+                _ => builder.concat(
+                    target,
+                    MirExpression::new(MirExpressionKind::BinaryOp(left, op, right), self.span),
+                )?,
+            },
+            MirExpressionKind::BinaryOp(left, op, right)
+                if builder.is_long(&left)? || builder.is_long(&right)? =>
+            {
+                builder.long_binary_op(target, *left, op, *right)?
+            }
+            MirExpressionKind::BinaryOp(left, op, right) => {
+                // The left side is evaluated straight into `target`, so the right side must not
+                // read it afterwards, which any function it calls might do.
+                if right.reads(target) || (right.has_side_effects() && !target.is_temporary()) {
+                    let temporary = builder.temporary();
+                    MirExpression::new(MirExpressionKind::BinaryOp(left, op, right), self.span)
+                        .evaluate_into(builder, &temporary)?;
+                    builder.copy(target, &temporary);
+                    return Ok(());
+                }
+
+                let fixed = builder.fixed_point_of(&left)?;
+                let primitive = match builder.primitive_of(&left)? {
+                    Some(primitive) => Some(primitive),
+                    None => builder.primitive_of(&right)?,
+                };
+                left.evaluate_into(builder, target)?;
+
+                // The right side of `&&` and `||` only runs if it can still change the result.
+                if matches!(op, MirBinaryOp::And | MirBinaryOp::Or) && right.has_side_effects() {
+                    let (kind, remaining) = if op == MirBinaryOp::And {
+                        ("and", 1)
+                    } else {
+                        ("or", 0)
+                    };
+
+                    let condition =
+                        Condition::check(format!("if score {} matches {}", target, remaining));
+                    return builder.run_child(&condition, kind, |builder| {
+                        right.evaluate_into(builder, target)
+                    });
+                }
+
+                let operand = right.evaluate(builder)?;
+                builder.arithmetic(target, op, operand, fixed, primitive);
+            }
+            MirExpressionKind::Cast(value, MirPrimitive::String) => {
+                builder.concat(target, *value)?
+            }
+            MirExpressionKind::Cast(value, primitive)
+                if primitive == MirPrimitive::Long || builder.is_long(&value)? =>
+            {
+                builder.long_cast(target, *value, primitive)?
+            }
+            MirExpressionKind::Cast(value, primitive) => {
+                let scale = |fixed: Option<FixedPoint>| fixed.map_or(1, |fixed| fixed.scale());
+                let from = scale(builder.fixed_point_of(&value)?);
+                let to = scale(builder.datapack.fixed_point(&MirType::Primitive(primitive)));
+                let source = builder.primitive_of(&value)?;
+                value.evaluate_into(builder, target)?;
+                builder.rescale(target, from, to);
+                builder.wrap_conversion(target, source, primitive);
+            }
+            MirExpressionKind::Nbt(path) => builder.read_nbt(&path, target),
+            MirExpressionKind::Property(left, name) => {
+                let source = builder.field_score(*left, &name)?;
+                builder.copy_value(target, &source, &layout);
+            }
+            MirExpressionKind::Array(elements) => {
+                // Like struct fields, elements are written one at a time.
+                if elements.iter().any(|element| {
+                    element.reads(target) || (element.has_side_effects() && !target.is_temporary())
+                }) {
+                    let temporary = builder.temporary();
+                    MirExpression::new(MirExpressionKind::Array(elements), self.span)
+                        .evaluate_into(builder, &temporary)?;
+                    builder.copy_value(target, &temporary, &layout);
+                    return Ok(());
+                }
+
+                builder.array_literal(target, elements)?;
+            }
+            MirExpressionKind::Index(array, index) => {
+                builder.read_element(*array, *index, target)?
+            }
+        }
+
+        Ok(())
+    }
+
+    fn evaluate_discarded(self, builder: &mut FunctionBuilder) -> BackendResult<()> {
+        match self.kind {
+            MirExpressionKind::Command(parts) => {
+                builder.run_command(parts, |command| command.to_string())
+            }
+            MirExpressionKind::Tellraw(targets, parts) => {
+                builder.tellraw(&targets, parts, self.span)
+            }
+            MirExpressionKind::Call(path, args) => builder.call(path, args, self.span).map(|_| ()),
+            kind => {
+                let temporary = builder.temporary();
+                MirExpression::new(kind, self.span).evaluate_into(builder, &temporary)
+            }
+        }
+    }
+
+    fn evaluate(self, builder: &mut FunctionBuilder) -> BackendResult<Operand> {
+        match self.kind {
+            MirExpressionKind::Literal(literal)
+                if !matches!(literal, LiteralType::String(_) | LiteralType::Long(_)) =>
+            {
+                Ok(Operand::Constant(
+                    builder.literal_value(literal, self.span)?,
+                ))
+            }
+            MirExpressionKind::Variable(variable) => Ok(Operand::Score(Score::variable(variable))),
+            kind => {
+                let temporary = builder.temporary();
+                MirExpression::new(kind, self.span).evaluate_into(builder, &temporary)?;
+                Ok(Operand::Score(temporary))
+            }
+        }
+    }
+}
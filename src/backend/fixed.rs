@@ -0,0 +1,379 @@
+use super::{
+    BackendError, BackendResult, BackendWarning, Datapack, FunctionBuilder, Operand, Score,
+};
+use crate::{
+    hir::mir::MirPrimitive,
+    mir::{MangledVar, MirBinaryOp, MirExpression, MirType},
+    Span,
+};
+use std::fmt;
+
+/// Default scale of `float`s, which keep 2 decimal places.
+pub const DEFAULT_FLOAT_SCALE: u32 = 100;
+/// Default scale of `double`s, which keep 3 decimal places.
+pub const DEFAULT_DOUBLE_SCALE: u32 = 1000;
+/// Largest scale, multiplying keeps the product of two remainders of the scale in a score.
+pub const MAX_SCALE: u32 = 10_000;
+
+/// How values of `float` or `double` are stored: a score holds the value multiplied by the
+/// scale, a power of ten.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedPoint {
+    primitive: MirPrimitive,
+    scale: u32,
+}
+
+impl FixedPoint {
+    /// `None` if `scale` is not a power of ten up to [`MAX_SCALE`].
+    pub fn new(primitive: MirPrimitive, scale: u32) -> Option<Self> {
+        (scale <= MAX_SCALE && 10u32.pow(scale.max(1).ilog10()) == scale)
+            .then_some(Self { primitive, scale })
+    }
+
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    fn decimals(&self) -> u32 {
+        self.scale.ilog10()
+    }
+
+    /// The number a score holding `raw` stands for, with trailing zeros removed.
+    pub fn format(&self, raw: i32) -> String {
+        let scale = self.scale as i64;
+        let (sign, raw) = if raw < 0 {
+            ("-", -(raw as i64))
+        } else {
+            ("", raw as i64)
+        };
+        let fraction = format!("{:0width$}", raw % scale, width = self.decimals() as usize);
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.is_empty() {
+            format!("{}{}", sign, raw / scale)
+        } else {
+            format!("{}{}.{}", sign, raw / scale, fraction)
+        }
+    }
+}
+
+impl fmt::Display for FixedPoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "`{}` values have {} decimal places and range from {} to {}",
+            self.primitive,
+            self.decimals(),
+            self.format(i32::MIN),
+            self.format(i32::MAX)
+        )
+    }
+}
+
+impl Datapack {
+    /// Sets the scale of `float`s, `span` is where the project configuration sets it.
+    pub fn set_float_scale(&mut self, scale: u32, span: Span) -> BackendResult<()> {
+        self.float = FixedPoint::new(MirPrimitive::Float, scale)
+            .ok_or(BackendError::InvalidScale(MirPrimitive::Float, scale, span))?;
+        Ok(())
+    }
+
+    /// Like [`Datapack::set_float_scale`], for `double`s.
+    pub fn set_double_scale(&mut self, scale: u32, span: Span) -> BackendResult<()> {
+        self.double = FixedPoint::new(MirPrimitive::Double, scale).ok_or(
+            BackendError::InvalidScale(MirPrimitive::Double, scale, span),
+        )?;
+        Ok(())
+    }
+
+    /// How values of `ty` are stored, if it is a `float` or `double`.
+    pub(super) fn fixed_point(&self, ty: &MirType<MangledVar>) -> Option<FixedPoint> {
+        match ty {
+            MirType::Primitive(MirPrimitive::Float) => Some(self.float),
+            MirType::Primitive(MirPrimitive::Double) => Some(self.double),
+            _ => None,
+        }
+    }
+
+    /// The score holding the literal `value` of a type stored as `fixed`, warning if it has more
+    /// decimal places than the type keeps. Errors and warnings show the literal as `text`, the
+    /// value in its own type.
+    pub(super) fn fixed_literal(
+        &mut self,
+        value: f64,
+        text: String,
+        exact: impl Fn(f64) -> bool,
+        fixed: FixedPoint,
+        span: Span,
+    ) -> BackendResult<i32> {
+        let raw = (value * fixed.scale as f64).round();
+        if !(i32::MIN as f64..=i32::MAX as f64).contains(&raw) {
+            return Err(BackendError::FixedPointRange(text, fixed, span));
+        }
+
+        let raw = raw as i32;
+        if !exact(raw as f64 / fixed.scale as f64) {
+            self.warnings.push(BackendWarning::InexactLiteral(
+                text,
+                fixed.format(raw),
+                fixed,
+                span,
+            ));
+        }
+        Ok(raw)
+    }
+}
+
+impl FunctionBuilder<'_> {
+    /// How the value of `expr` is stored, if it is a `float` or `double`.
+    pub(super) fn fixed_point_of(
+        &self,
+        expr: &MirExpression<MangledVar>,
+    ) -> BackendResult<Option<FixedPoint>> {
+        Ok(self
+            .value_type(expr)?
+            .and_then(|ty| self.datapack.fixed_point(&ty)))
+    }
+
+    /// Converts the number in `target` from being stored with scale `from` to scale `to`,
+    /// rounding towards zero like Rust does.
+    pub(super) fn rescale(&mut self, target: &Score, from: u32, to: u32) {
+        if to > from {
+            self.binary_op(
+                target,
+                MirBinaryOp::Multiply,
+                Operand::Constant((to / from) as i32),
+            );
+        } else if to < from {
+            // Scores are divided rounding down, negative values are moved up to round up instead.
+            let factor = (from / to) as i32;
+            self.push(format!(
+                "execute if score {} matches ..-1 run scoreboard players add {} {}",
+                target,
+                target,
+                factor - 1
+            ));
+            self.binary_op(target, MirBinaryOp::Divide, Operand::Constant(factor));
+        }
+    }
+
+    /// Multiplies the fixed-point number in `target` by `operand`, without the product of both
+    /// scaled values overflowing a score where the result fits in one.
+    pub(super) fn fixed_multiply(&mut self, target: &Score, operand: Operand, fixed: FixedPoint) {
+        let scale = fixed.scale as i32;
+        if let Operand::Constant(value) = operand {
+            if value % scale == 0 {
+                self.binary_op(
+                    target,
+                    MirBinaryOp::Multiply,
+                    Operand::Constant(value / scale),
+                );
+                return;
+            }
+            if value > 0 && scale % value == 0 {
+                self.binary_op(
+                    target,
+                    MirBinaryOp::Divide,
+                    Operand::Constant(scale / value),
+                );
+                return;
+            }
+        }
+
+        // With `a = q1 * S + r1` and `b = q2 * S + r2`, the result `a * b / S` is
+        // `q1 * q2 * S + q1 * r2 + r1 * q2 + r1 * r2 / S`. Scores round down like `div_euclid`.
+        let (q2, r2) = match operand {
+            Operand::Constant(value) => (
+                Operand::Constant(value.div_euclid(scale)),
+                Operand::Constant(value.rem_euclid(scale)),
+            ),
+            Operand::Score(score) => {
+                let (q2, r2) = (self.temporary(), self.temporary());
+                self.copy(&q2, &score);
+                self.binary_op(&q2, MirBinaryOp::Divide, Operand::Constant(scale));
+                self.copy(&r2, &score);
+                self.binary_op(&r2, MirBinaryOp::Modulo, Operand::Constant(scale));
+                (Operand::Score(q2), Operand::Score(r2))
+            }
+        };
+
+        let (q1, sum) = (self.temporary(), self.temporary());
+        self.copy(&q1, target);
+        self.binary_op(&q1, MirBinaryOp::Divide, Operand::Constant(scale));
+        self.binary_op(target, MirBinaryOp::Modulo, Operand::Constant(scale));
+
+        self.copy(&sum, &q1);
+        self.binary_op(&sum, MirBinaryOp::Multiply, q2.clone());
+        self.binary_op(&sum, MirBinaryOp::Multiply, Operand::Constant(scale));
+        self.binary_op(&q1, MirBinaryOp::Multiply, r2.clone());
+        self.binary_op(&sum, MirBinaryOp::Add, Operand::Score(q1.clone()));
+        self.copy(&q1, target);
+        self.binary_op(&q1, MirBinaryOp::Multiply, q2);
+        self.binary_op(&sum, MirBinaryOp::Add, Operand::Score(q1));
+
+        self.binary_op(target, MirBinaryOp::Multiply, r2);
+        self.binary_op(target, MirBinaryOp::Divide, Operand::Constant(scale));
+        self.binary_op(target, MirBinaryOp::Add, Operand::Score(sum));
+    }
+
+    /// Divides the fixed-point number in `target` by `operand`, one decimal place at a time so
+    /// the dividend is never multiplied by the whole scale.
+    pub(super) fn fixed_divide(&mut self, target: &Score, operand: Operand, fixed: FixedPoint) {
+        let scale = fixed.scale as i32;
+        if let Operand::Constant(value) = operand {
+            if value != 0 && value % scale == 0 {
+                self.binary_op(
+                    target,
+                    MirBinaryOp::Divide,
+                    Operand::Constant(value / scale),
+                );
+                return;
+            }
+            if value > 0 && scale % value == 0 {
+                self.binary_op(
+                    target,
+                    MirBinaryOp::Multiply,
+                    Operand::Constant(scale / value),
+                );
+                return;
+            }
+        }
+
+        let divisor = Operand::Score(self.operand_score(operand));
+        let (remainder, digit) = (self.temporary(), self.temporary());
+        self.copy(&remainder, target);
+        self.binary_op(&remainder, MirBinaryOp::Modulo, divisor.clone());
+        self.binary_op(target, MirBinaryOp::Divide, divisor.clone());
+
+        for _ in 0..fixed.decimals() {
+            self.binary_op(target, MirBinaryOp::Multiply, Operand::Constant(10));
+            self.binary_op(&remainder, MirBinaryOp::Multiply, Operand::Constant(10));
+            self.copy(&digit, &remainder);
+            self.binary_op(&digit, MirBinaryOp::Divide, divisor.clone());
+            self.binary_op(&remainder, MirBinaryOp::Modulo, divisor.clone());
+            self.binary_op(target, MirBinaryOp::Add, Operand::Score(digit.clone()));
+        }
+    }
+
+    /// Reads the number at the NBT `path` into `target`, as a `double`.
+    pub(super) fn read_nbt(&mut self, path: &str, target: &Score) {
+        let scale = self.datapack.double.scale;
+        self.push(format!(
+            "execute store result score {} run data get {} {}",
+            target, path, scale
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FixedPoint;
+    use crate::{
+        backend::{tests::lower, BackendError, BackendWarning},
+        hir::mir::MirPrimitive,
+        source::FileId,
+        Span,
+    };
+
+    #[test]
+    fn scales_are_powers_of_ten() -> eyre::Result<()> {
+        let float = FixedPoint::new(MirPrimitive::Float, 100).unwrap();
+        assert_eq!(float.format(-150), "-1.5");
+        assert_eq!(float.format(7), "0.07");
+        assert_eq!(float.format(300), "3");
+        assert_eq!(
+            float.to_string(),
+            "`float` values have 2 decimal places and range from -21474836.48 to 21474836.47"
+        );
+
+        assert!(FixedPoint::new(MirPrimitive::Double, 1).is_some());
+        let mut datapack = lower("")??;
+        let span = Span::new(FileId::default(), 3, 2);
+        for scale in [0, 20, 100_000] {
+            assert!(matches!(
+                datapack.set_double_scale(scale, span),
+                Err(BackendError::InvalidScale(MirPrimitive::Double, _, error_span))
+                    if error_span == span
+            ));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn literals_are_scaled() -> eyre::Result<()> {
+        let mut datapack = lower("static x: float = -2.5; static y: double = 0.0625d;")??;
+
+        let init = datapack.function("__init").unwrap();
+        assert!(init[1].ends_with(" -250"));
+        assert!(init[2].ends_with(" 63"));
+        assert!(matches!(
+            datapack.take_warnings().as_slice(),
+            [BackendWarning::InexactLiteral(value, rounded, _, _)]
+                if value == "0.0625" && rounded == "0.063"
+        ));
+
+        let mut datapack = lower("static x: float = 1e-3f;")??;
+        assert!(matches!(
+            datapack.take_warnings().as_slice(),
+            [BackendWarning::InexactLiteral(value, rounded, _, _)]
+                if value == "0.001" && rounded == "0"
+        ));
+
+        assert!(matches!(
+            lower("static x: float = 30000000.0;")?,
+            Err(BackendError::FixedPointRange(_, _, _))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn products_and_quotients_are_rescaled() -> eyre::Result<()> {
+        let datapack = lower(
+            "static function f(a: float, b: float): float { return a * b; }
+            static function g(a: float, b: float): float { return a / b; }
+            static function h(a: float): float { return a * 3.0 / 0.25; }",
+        )??;
+
+        // The scaled product of both values would overflow, it is split at the scale instead.
+        let f = datapack.function("f").unwrap();
+        assert!(f[2].ends_with("/= #const.100 ss_global"));
+        assert!(f[4].ends_with("%= #const.100 ss_global"));
+        assert_eq!(f.len(), 19);
+
+        // Division goes one decimal place at a time.
+        let g = datapack.function("g").unwrap();
+        assert_eq!(
+            g.iter()
+                .filter(|command| command.contains("*= #const.10 "))
+                .count(),
+            4
+        );
+
+        let h = datapack.function("h").unwrap();
+        assert!(h[1].ends_with(" *= #const.3 ss_global"));
+        assert!(h[2].ends_with(" *= #const.4 ss_global"));
+        Ok(())
+    }
+
+    #[test]
+    fn conversions_and_entity_data() -> eyre::Result<()> {
+        let datapack = lower(
+            "static function f(a: float): int { return a as int; }
+            static function g(a: int): double { return a as double + $nbt(\"entity @s Pos[1]\"); }
+            static function h(a: double): void { $command(\"tp @s ~ ${a} ~\"); }",
+        )??;
+
+        let f = datapack.function("f").unwrap();
+        assert!(f[1].starts_with("execute if score #return ss_global matches ..-1 run"));
+        assert!(f[1].ends_with(" add #return ss_global 99"));
+        assert!(f[2].ends_with(" /= #const.100 ss_global"));
+
+        let g = datapack.function("g").unwrap();
+        assert!(g[1].ends_with(" *= #const.1000 ss_global"));
+        assert!(g[2].ends_with(" run data get entity @s Pos[1] 1000"));
+
+        let h = datapack.function("h").unwrap();
+        assert!(h[0]
+            .starts_with("execute store result storage test:ss_global macro.p0 double 0.001 run"));
+        Ok(())
+    }
+}
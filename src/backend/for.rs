@@ -0,0 +1,40 @@
+use super::{r#while::lower_loop, BackendResult, FunctionBuilder, Lower};
+use crate::mir::{MangledVar, MirFor};
+
+impl Lower for MirFor<MangledVar> {
+    fn lower(self, builder: &mut FunctionBuilder) -> BackendResult<()> {
+        self.init.lower(builder)?;
+        lower_loop(
+            builder,
+            self.label,
+            self.condition,
+            self.block,
+            Some(self.update),
+            self.is_spread,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::tests::lower;
+
+    #[test]
+    fn init_is_hoisted_and_update_appended() -> eyre::Result<()> {
+        let datapack = lower(
+            "static x: int = 0;
+            static function f(): void { for (let i: int = 0; i < 10; i = i + 1;) { x = x + i; } }",
+        )??;
+
+        let f = datapack.function("f").unwrap();
+        assert_eq!(f.len(), 2);
+        assert!(f[0].ends_with(" 0"));
+        assert!(f[1].ends_with(" matches 10.. run function test:f/loop_0"));
+
+        let body = datapack.function("f/loop_0").unwrap();
+        assert_eq!(body.len(), 3);
+        assert!(body[1].ends_with(" 1") && body[1].starts_with("scoreboard players add "));
+        assert_eq!(body[2], f[1]);
+        Ok(())
+    }
+}
@@ -0,0 +1,217 @@
+//! Functions keep their parameters, variables and temporaries in scores shared by every call, so
+//! a call that may run the calling function again would overwrite the values it still needs.
+//! Around such calls the caller saves its frame, every one of those scores which is set, in a
+//! new entry at the end of a list in storage, and restores it from there once the call returns.
+
+use super::{r#struct::Slot, BackendResult, Datapack, Feature, FunctionBuilder, Score};
+use crate::{
+    hir::mir::MirPrimitive,
+    mir::{AbsolutePath, MirType},
+    Span,
+};
+use std::collections::{BTreeSet, HashSet};
+
+/// The list in storage holding saved frames, the last entry is the frame of the latest call.
+pub(super) const FRAMES: &str = "frames";
+
+/// Function files saving and restoring the frame of a function, as children of the function.
+const PUSH_FRAME: &str = "push_frame";
+const POP_FRAME: &str = "pop_frame";
+
+impl Datapack {
+    /// Whether calling the function at `path` may run `caller` again before it returns.
+    pub(super) fn reenters(&self, path: &AbsolutePath, caller: &str) -> bool {
+        let (namespace, callee) = self.split_path(path);
+        if namespace != self.namespace {
+            return false;
+        }
+
+        let mut visited = HashSet::new();
+        let mut pending = vec![callee];
+        while let Some(function) = pending.pop() {
+            if function == caller {
+                return true;
+            }
+            if let Some(calls) = self.calls.get(&function) {
+                pending.extend(calls.iter().filter(|call| visited.insert(*call)).cloned());
+            }
+        }
+
+        false
+    }
+
+    /// Writes the functions saving and restoring the frame of every function which saves it.
+    /// Temporaries may hold values of any type, so every slot a value can have is saved for
+    /// them.
+    pub(super) fn write_frames(&mut self) -> BackendResult<()> {
+        if self.framed.is_empty() {
+            return Ok(());
+        }
+
+        let span = *self
+            .framed
+            .values()
+            .next()
+            .expect("some function is framed");
+        let mut temporary_slots = BTreeSet::from([Slot::Score(String::new())]);
+        let types = self.variables.values().chain(
+            self.signatures
+                .values()
+                .map(|signature| &signature.return_type),
+        );
+        for ty in types {
+            if *ty != MirType::Primitive(MirPrimitive::Void) {
+                let layout = self.layout(ty, span)?;
+                temporary_slots.extend(layout.iter().map(|slot| slot.unguarded().clone()));
+            }
+        }
+
+        for (function, span) in self.framed.clone() {
+            let mut frame = vec![(
+                Score::return_flag(&self.namespace, &function),
+                Slot::Score(String::new()),
+            )];
+            for variable in self.locals.get(&function).into_iter().flatten() {
+                let holder = Score::variable(*variable);
+                for slot in self.variable_layout(*variable, span)? {
+                    frame.push((holder.clone(), slot.unguarded().clone()));
+                }
+            }
+            let temporaries = self.function_temporaries.get(&function).cloned();
+            for index in temporaries.into_iter().flatten() {
+                let holder = Score::temporary(&self.namespace, index);
+                for slot in &temporary_slots {
+                    frame.push((holder.clone(), slot.clone()));
+                }
+            }
+
+            let push = self.saving_commands(&frame);
+            let pop = self.restoring_commands(&frame);
+            self.functions
+                .insert(format!("{}/{}", function, PUSH_FRAME), push);
+            self.functions
+                .insert(format!("{}/{}", function, POP_FRAME), pop);
+        }
+
+        Ok(())
+    }
+
+    /// The entry of the latest frame holding the value held by `holder`.
+    fn frame_entry(&self, holder: &Score) -> String {
+        format!("{} {}[-1].\"{}\"", self.storage(), FRAMES, holder.holder())
+    }
+
+    /// Commands appending a new entry holding the values of `frame` to the list of frames.
+    fn saving_commands(&self, frame: &[(Score, Slot)]) -> Vec<String> {
+        let mut commands = vec![format!(
+            "data modify {} {} append value {{}}",
+            self.storage(),
+            FRAMES
+        )];
+        for (holder, slot) in frame {
+            let score = holder.with_suffix(slot.suffix());
+            let entry = self.frame_entry(&score);
+            commands.push(match slot {
+                Slot::Stored(_, _) => {
+                    format!("data modify {} set from {}", entry, self.stored(&score))
+                }
+                _ => format!(
+                    "execute if score {} = {} store result {} int 1 run scoreboard players get {}",
+                    score, score, entry, score
+                ),
+            });
+        }
+        commands
+    }
+
+    /// Commands restoring the values of `frame` from the latest entry and removing it.
+    fn restoring_commands(&self, frame: &[(Score, Slot)]) -> Vec<String> {
+        let mut commands = vec![];
+        for (holder, slot) in frame {
+            let score = holder.with_suffix(slot.suffix());
+            let entry = self.frame_entry(&score);
+            match slot {
+                Slot::Stored(_, _) => {
+                    let stored = self.stored(&score);
+                    commands.push(format!("data remove {}", stored));
+                    commands.push(format!("data modify {} set from {}", stored, entry));
+                }
+                _ => {
+                    commands.push(format!("scoreboard players reset {}", score));
+                    commands.push(format!(
+                        "execute if data {} store result score {} run data get {}",
+                        entry, score, entry
+                    ));
+                }
+            }
+        }
+        commands.push(format!("data remove {} {}[-1]", self.storage(), FRAMES));
+        commands
+    }
+}
+
+impl FunctionBuilder<'_> {
+    /// Saves the frame of the function being built before a call at `span` that may run it
+    /// again.
+    pub(super) fn push_frame(&mut self, span: Span) -> BackendResult<()> {
+        self.require(Feature::Storage, span)?;
+        self.datapack
+            .framed
+            .entry(self.function.clone())
+            .or_insert(span);
+        let location = self
+            .datapack
+            .location(&format!("{}/{}", self.function, PUSH_FRAME));
+        self.push(format!("function {}", location));
+        Ok(())
+    }
+
+    /// Restores the frame saved by [`FunctionBuilder::push_frame`] once the call returned.
+    pub(super) fn pop_frame(&mut self) {
+        let location = self
+            .datapack
+            .location(&format!("{}/{}", self.function, POP_FRAME));
+        self.push(format!("function {}", location));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::tests::{call, lower};
+
+    #[test]
+    fn recursive_calls_keep_the_caller_frame() -> eyre::Result<()> {
+        let datapack = lower(
+            "static function fact(n: int): int {
+                if (n <= 1) { return 1; }
+                return n * fact(n - 1);
+            }
+            static function main(): int { return fact(5); }",
+        )??;
+
+        assert_eq!(call(&datapack, "fact", &[5]), 120);
+        assert_eq!(call(&datapack, "main", &[]), 120);
+        assert!(datapack.function("fact/push_frame").is_some());
+        assert!(datapack.function("main/push_frame").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn mutually_recursive_calls_keep_both_frames() -> eyre::Result<()> {
+        let datapack = lower(
+            "static function down(n: int): int {
+                if (n == 0) { return 0; }
+                let kept: int = n * 10;
+                return up(n - 1) + kept;
+            }
+            static function up(n: int): int {
+                if (n == 0) { return 0; }
+                return down(n - 1) + n;
+            }",
+        )??;
+
+        assert_eq!(call(&datapack, "down", &[4]), 40 + 3 + 20 + 1);
+        assert_eq!(call(&datapack, "up", &[3]), 3 + 20 + 1);
+        Ok(())
+    }
+}
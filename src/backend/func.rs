@@ -0,0 +1,55 @@
+use super::{BackendError, BackendResult, Evaluate, FunctionBuilder, Lower, Score};
+use crate::{
+    hir::mir::MirPrimitive,
+    mir::{MangledVar, MirFunction, MirStatement, MirType},
+    token::XID,
+    Span,
+};
+
+/// Converts the name of the function declared at `span` into the path of its `.mcfunction` file.
+pub(super) fn function_path(name: &XID, span: Span) -> BackendResult<String> {
+    let name = name.inner();
+    if name
+        .chars()
+        .all(|char| matches!(char, 'a'..='z' | '0'..='9' | '_' | '-' | '.'))
+    {
+        Ok(name.to_string())
+    } else {
+        Err(BackendError::InvalidFunctionName(name.to_string(), span))
+    }
+}
+
+impl Lower for MirFunction<MangledVar> {
+    fn lower(mut self, builder: &mut FunctionBuilder) -> BackendResult<()> {
+        let path = function_path(&self.name, self.span)?;
+        for (_, ty) in &self.args {
+            builder.datapack.layout(ty, self.span)?;
+        }
+        if self.return_type != MirType::Primitive(MirPrimitive::Void) {
+            builder.datapack.layout(&self.return_type, self.span)?;
+        }
+
+        let first_temporary = builder.datapack.temporary_counter;
+        let mut function = FunctionBuilder::new(builder.datapack, path.clone());
+
+        // A trailing return does not need to stop the function early.
+        let tail = if let Some(MirStatement::Return(_)) = self.block.last() {
+            self.block.pop()
+        } else {
+            None
+        };
+
+        self.block.lower(&mut function)?;
+        if let Some(MirStatement::Return(value)) = tail {
+            value.evaluate_into(&mut function, &Score::return_register())?;
+        }
+
+        function.finish();
+        let temporaries = first_temporary..builder.datapack.temporary_counter;
+        builder
+            .datapack
+            .function_temporaries
+            .insert(path, temporaries);
+        Ok(())
+    }
+}
@@ -0,0 +1,200 @@
+use super::{BackendResult, Condition, Evaluate, FunctionBuilder, Lower, Score};
+use crate::mir::{MangledVar, MirElseBlock, MirExpression, MirIf};
+
+impl Lower for MirIf<MangledVar> {
+    fn lower(self, builder: &mut FunctionBuilder) -> BackendResult<()> {
+        let condition = self.condition.evaluate_condition(builder)?;
+
+        match (condition, self.else_block) {
+            (Condition::Constant(true), _) => self.block.lower(builder),
+            (Condition::Constant(false), Some(else_block)) => else_block.lower(builder),
+            (Condition::Constant(false), None) => Ok(()),
+            (condition, None) => {
+                builder.run_child(&condition, "if", |builder| self.block.lower(builder))
+            }
+            (condition, Some(else_block)) => {
+                // The branch taken is stored first, the then branch may change what the
+                // condition reads.
+                let taken = builder.temporary();
+                condition.store(builder, &taken);
+
+                let then_condition = Condition::check(format!("if score {} matches 1", taken));
+                builder.run_child(&then_condition, "if", |builder| self.block.lower(builder))?;
+
+                // An `else if` is lowered inside the else function so its condition is only
+                // evaluated once the previous ones failed.
+                let else_condition = Condition::check(format!("if score {} matches 0", taken));
+                builder.run_child(&else_condition, "else", |builder| else_block.lower(builder))
+            }
+        }
+    }
+}
+
+impl Lower for MirElseBlock<MangledVar> {
+    fn lower(self, builder: &mut FunctionBuilder) -> BackendResult<()> {
+        match self {
+            Self::ElseIf(if_block) => if_block.lower(builder),
+            Self::Else(block) => block.lower(builder),
+        }
+    }
+}
+
+impl FunctionBuilder<'_> {
+    /// Evaluates `condition ? then : otherwise` into `target`, only the branch taken is evaluated.
+    pub(super) fn ternary(
+        &mut self,
+        target: &Score,
+        condition: MirExpression<MangledVar>,
+        then: MirExpression<MangledVar>,
+        otherwise: MirExpression<MangledVar>,
+    ) -> BackendResult<()> {
+        // Writing the then branch to the target may change what the condition reads.
+        let reread = condition.reads(target) || then.has_side_effects();
+        let condition = condition.evaluate_condition(self)?;
+
+        let (then_condition, else_condition) = match condition {
+            Condition::Constant(true) => return then.evaluate_into(self, target),
+            Condition::Constant(false) => return otherwise.evaluate_into(self, target),
+            condition => match condition.clone().inverse() {
+                Some(inverse) if !reread => (condition, inverse),
+                _ => {
+                    let taken = self.temporary();
+                    condition.store(self, &taken);
+                    (
+                        Condition::check(format!("if score {} matches 1", taken)),
+                        Condition::check(format!("if score {} matches 0", taken)),
+                    )
+                }
+            },
+        };
+
+        self.run_child(&then_condition, "if", |builder| {
+            then.evaluate_into(builder, target)
+        })?;
+        self.run_child(&else_condition, "else", |builder| {
+            otherwise.evaluate_into(builder, target)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::tests::{call, lower, lower_for};
+    use crate::backend::MinecraftVersion;
+
+    #[test]
+    fn constant_comparisons_use_ranges() -> eyre::Result<()> {
+        let datapack = lower(
+            "static x: int = 0;
+            static function f(a: int): void { if (a < 3) { x = 1; } if (5 <= a) { x = 2; } }",
+        )??;
+
+        let f = datapack.function("f").unwrap();
+        assert_eq!(f.len(), 2);
+        assert!(f[0].starts_with("execute unless score ") && f[0].contains(" matches 3.. run "));
+        assert!(f[1].starts_with("execute if score ") && f[1].contains(" matches 5.. run "));
+        Ok(())
+    }
+
+    #[test]
+    fn else_if_evaluates_conditions_once() -> eyre::Result<()> {
+        let datapack = lower(
+            "static x: int = 0;
+            static function next(): int { x = x + 1; return x; }
+            static function f(): void {
+                if (next() == 1) { x = 10; } else if (next() == 3) { x = 20; } else { x = 30; }
+            }",
+        )??;
+
+        let f = datapack.function("f").unwrap();
+        assert_eq!(f.len(), 5);
+        assert_eq!(f[0], "function test:next");
+        assert!(f[2].starts_with("execute store success score #test.t1 ss_global if score "));
+        assert!(f[3].starts_with("execute if score #test.t1 ss_global matches 1 run "));
+        assert_eq!(
+            f[4],
+            "execute if score #test.t1 ss_global matches 0 run function test:f/else_1"
+        );
+
+        let else_branch = datapack.function("f/else_1").unwrap();
+        assert_eq!(else_branch[0], "function test:next");
+        assert_eq!(
+            else_branch
+                .iter()
+                .filter(|command| command.contains("function test:next"))
+                .count(),
+            1
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn constant_conditions_are_inlined() -> eyre::Result<()> {
+        let datapack = lower(
+            "static x: int = 0;
+            static function f(): void { if (true) { x = 1; } else { x = 2; } if (false) { x = 3; } }",
+        )??;
+
+        let f = datapack.function("f").unwrap();
+        assert_eq!(f.len(), 1);
+        assert!(f[0].ends_with(" 1"));
+        assert_eq!(datapack.functions().count(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn return_inside_branch_stops_function() -> eyre::Result<()> {
+        let input = "static function f(a: int): int { if (a == 1) { a = 2; return 5; } return a; }";
+
+        let datapack = lower(input)??;
+        let f = datapack.function("f").unwrap();
+        assert_eq!(f[0], "scoreboard players set #test.f.returned ss_global 0");
+        assert!(f[1].ends_with(" matches 1 run function test:f/if_0"));
+        assert_eq!(
+            f[2],
+            "execute if score #test.f.returned ss_global matches 1 run return 0"
+        );
+
+        let branch = datapack.function("f/if_0").unwrap();
+        assert_eq!(
+            branch[2],
+            "return run scoreboard players set #test.f.returned ss_global 1"
+        );
+
+        let datapack = lower_for(input, MinecraftVersion::new(20, 1))??;
+        let branch = datapack.function("f/if_0").unwrap();
+        assert_eq!(
+            branch[2],
+            "scoreboard players set #test.f.returned ss_global 1"
+        );
+        assert_eq!(branch[3], "return 0");
+        Ok(())
+    }
+
+    #[test]
+    fn ternaries_only_evaluate_the_branch_taken() -> eyre::Result<()> {
+        let datapack = lower(
+            "static calls: int = 0;
+            static function next(): int { calls += 1; return calls; }
+            static function pick(a: int): int {
+                let x: int = a > 2 ? next() * 10 : -next();
+                return x * 10 + calls;
+            }",
+        )??;
+
+        assert_eq!(call(&datapack, "pick", &[3]), 101);
+        assert_eq!(call(&datapack, "pick", &[0]), -9);
+        Ok(())
+    }
+
+    #[test]
+    fn ternaries_can_read_their_target() -> eyre::Result<()> {
+        let datapack = lower(
+            "static function f(a: int): int { let x: int = a; x = x > 0 ? x - 5 : x + 5; return x; }",
+        )??;
+
+        assert_eq!(call(&datapack, "f", &[2]), -3);
+        assert_eq!(call(&datapack, "f", &[-2]), 3);
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+use super::{BackendResult, FunctionBuilder, Operand, Score};
+use crate::{
+    hir::mir::MirPrimitive,
+    mir::{MangledVar, MirBinaryOp, MirExpression, MirType},
+};
+
+/// The width of `byte`s and `short`s, other integers fill their scores.
+fn narrow_bits(primitive: MirPrimitive) -> Option<u32> {
+    match primitive {
+        MirPrimitive::Byte => Some(8),
+        MirPrimitive::Short => Some(16),
+        _ => None,
+    }
+}
+
+/// Whether every value of `from` is also a value of `to`, so conversions need no wrapping.
+fn fits(from: MirPrimitive, to: MirPrimitive) -> bool {
+    match (narrow_bits(from), narrow_bits(to)) {
+        (_, None) => true,
+        (Some(from), Some(to)) => from <= to,
+        (None, Some(_)) => from == MirPrimitive::Bool,
+    }
+}
+
+impl FunctionBuilder<'_> {
+    /// The primitive type of `expr`, if it is known.
+    pub(super) fn primitive_of(
+        &self,
+        expr: &MirExpression<MangledVar>,
+    ) -> BackendResult<Option<MirPrimitive>> {
+        Ok(match self.value_type(expr)? {
+            Some(MirType::Primitive(primitive)) => Some(primitive),
+            _ => None,
+        })
+    }
+
+    /// Wraps the integer in `target` around to the range of `primitive` if it is a `byte` or a
+    /// `short`, keeping its low bits and extending their sign like `as i8` and `as i16` do.
+    pub(super) fn wrap_integer(&mut self, target: &Score, primitive: MirPrimitive) {
+        let Some(bits) = narrow_bits(primitive) else {
+            return;
+        };
+
+        // Scores wrap around themselves, which keeps the low bits intact.
+        let half = 1 << (bits - 1);
+        self.binary_op(target, MirBinaryOp::Add, Operand::Constant(half));
+        self.binary_op(target, MirBinaryOp::Modulo, Operand::Constant(half * 2));
+        self.binary_op(target, MirBinaryOp::Subtract, Operand::Constant(half));
+    }
+
+    /// Wraps the integer in `target`, converted from `from` if it is known, to the range of
+    /// `primitive`, unless it already is in range.
+    pub(super) fn wrap_conversion(
+        &mut self,
+        target: &Score,
+        from: Option<MirPrimitive>,
+        primitive: MirPrimitive,
+    ) {
+        if !from.is_some_and(|from| fits(from, primitive)) {
+            self.wrap_integer(target, primitive);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::tests::{call, lower};
+
+    const VALUES: [i64; 9] = [0, 1, -1, 100, -100, 127, -128, 32767, -32768];
+
+    #[test]
+    fn arithmetic_wraps_like_rust() -> eyre::Result<()> {
+        let datapack = lower(
+            "static function add(a: byte, b: byte): byte { return a + b; }
+            static function mul(a: byte, b: byte): byte { return a * b; }
+            static function neg(a: byte): byte { return -a; }
+            static function sub(a: short, b: short): short { return a - b; }
+            static function square(a: short): short { return a * a; }
+            static function rem(a: short, b: short): short { return a % b; }",
+        )??;
+
+        for a in VALUES.map(|value| value as i8) {
+            for b in VALUES.map(|value| value as i8) {
+                let args = [a as i64, b as i64];
+                assert_eq!(call(&datapack, "add", &args), a.wrapping_add(b) as i64);
+                assert_eq!(call(&datapack, "mul", &args), a.wrapping_mul(b) as i64);
+            }
+            assert_eq!(call(&datapack, "neg", &[a as i64]), a.wrapping_neg() as i64);
+        }
+        for a in VALUES.map(|value| value as i16) {
+            for b in VALUES.map(|value| value as i16) {
+                let args = [a as i64, b as i64];
+                assert_eq!(call(&datapack, "sub", &args), a.wrapping_sub(b) as i64);
+                if b > 0 {
+                    assert_eq!(call(&datapack, "rem", &args), a.rem_euclid(b) as i64);
+                }
+            }
+            assert_eq!(
+                call(&datapack, "square", &[a as i64]),
+                a.wrapping_mul(a) as i64
+            );
+        }
+
+        // Remainders never leave the range.
+        let rem = datapack.function("rem").unwrap();
+        assert_eq!(rem.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn conversions_keep_low_bits() -> eyre::Result<()> {
+        let datapack = lower(
+            "static function to_byte(a: int): byte { return a as byte; }
+            static function to_short(a: long): short { return a as short; }
+            static function from_byte(a: byte): long { return a as long * 3l; }
+            static function from_float(a: float): byte { return a as byte; }
+            static function widen(a: byte): short { return a; }",
+        )??;
+
+        for value in [
+            0,
+            1,
+            -1,
+            200,
+            -129,
+            40_000,
+            i32::MAX as i64,
+            i32::MIN as i64,
+        ] {
+            assert_eq!(call(&datapack, "to_byte", &[value]), value as i8 as i64);
+        }
+        for value in [5_000_000_000, -70_000, i64::MIN, 0x1_8000] {
+            assert_eq!(call(&datapack, "to_short", &[value]), value as i16 as i64);
+        }
+        assert_eq!(call(&datapack, "from_byte", &[-128]), -384);
+        assert_eq!(call(&datapack, "widen", &[-128]), -128);
+        // Every `byte` is a `short`.
+        assert_eq!(datapack.function("widen").unwrap().len(), 1);
+        // `300.5` is stored as 30050.
+        assert_eq!(
+            call(&datapack, "from_float", &[30050]),
+            300.5f32 as i32 as i8 as i64
+        );
+        Ok(())
+    }
+}
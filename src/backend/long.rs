@@ -0,0 +1,518 @@
+use super::{
+    cond::comparison, BackendError, BackendResult, Evaluate, FixedPoint, FunctionBuilder, Operand,
+    Score,
+};
+use crate::{
+    hir::mir::MirPrimitive,
+    mir::{MangledVar, MirBinaryOp, MirExpression, MirExpressionKind, MirType},
+    token::LiteralType,
+    Span,
+};
+
+/// Scores split into 16 bit limbs to multiply them without losing the carry.
+const LIMB: i32 = 1 << 16;
+
+/// The high and low 32 bits of a `long`.
+fn halves(value: i64) -> (i32, i32) {
+    ((value >> 32) as i32, value as i32)
+}
+
+impl FunctionBuilder<'_> {
+    /// Whether `expr` evaluates to a `long`, which is split over a `.hi` and a `.lo` score.
+    ///
+    /// The low score holds the low 32 bits like an unsigned number would, so a `long` is
+    /// `hi * 2^32 + (lo as u32)`. Arithmetic wraps around like Rust's `wrapping_*` methods on
+    /// `i64` do.
+    pub(super) fn is_long(&self, expr: &MirExpression<MangledVar>) -> BackendResult<bool> {
+        Ok(self.value_type(expr)? == Some(MirType::Primitive(MirPrimitive::Long)))
+    }
+
+    pub(super) fn long_literal(&mut self, target: &Score, value: i64) {
+        let (high, low) = halves(value);
+        self.push(format!(
+            "scoreboard players set {} {}",
+            target.field("hi"),
+            high
+        ));
+        self.push(format!(
+            "scoreboard players set {} {}",
+            target.field("lo"),
+            low
+        ));
+    }
+
+    /// The high and low halves of the `long` `expr` evaluates to.
+    fn long_operand(
+        &mut self,
+        expr: MirExpression<MangledVar>,
+    ) -> BackendResult<(Operand, Operand)> {
+        match expr.kind {
+            MirExpressionKind::Literal(LiteralType::Long(value)) => {
+                let (high, low) = halves(value);
+                Ok((Operand::Constant(high), Operand::Constant(low)))
+            }
+            kind => {
+                let holder = self.value_score(MirExpression::new(kind, expr.span))?;
+                Ok((
+                    Operand::Score(holder.field("hi")),
+                    Operand::Score(holder.field("lo")),
+                ))
+            }
+        }
+    }
+
+    /// Stores the result of `left op right` in `target`, where both sides are `long`s.
+    pub(super) fn long_binary_op(
+        &mut self,
+        target: &Score,
+        left: MirExpression<MangledVar>,
+        op: MirBinaryOp,
+        right: MirExpression<MangledVar>,
+    ) -> BackendResult<()> {
+        let layout = self.value_layout(&left)?;
+        if matches!(
+            op,
+            MirBinaryOp::Equals
+                | MirBinaryOp::NotEquals
+                | MirBinaryOp::LessThan
+                | MirBinaryOp::LessThanEquals
+                | MirBinaryOp::GreaterThan
+                | MirBinaryOp::GreaterThanEquals
+        ) {
+            // A variable read directly could be changed by the right side before the check.
+            let left = if right.has_side_effects() {
+                let temporary = self.temporary();
+                left.evaluate_into(self, &temporary)?;
+                temporary
+            } else {
+                self.value_score(left)?
+            };
+            let left = (
+                Operand::Score(left.field("hi")),
+                Operand::Score(left.field("lo")),
+            );
+            let right = self.long_operand(right)?;
+            return self.long_comparison(target, left, op, right);
+        }
+
+        // Like other arithmetic, the left side is evaluated straight into `target`.
+        if right.reads(target) || (right.has_side_effects() && !target.is_temporary()) {
+            let temporary = self.temporary();
+            self.long_binary_op(&temporary, left, op, right)?;
+            self.copy_value(target, &temporary, &layout);
+            return Ok(());
+        }
+
+        let span = Span::from_start_end(left.span, right.span);
+        left.evaluate_into(self, target)?;
+        let right = self.long_operand(right)?;
+        match op {
+            MirBinaryOp::Add => self.long_add(target, right),
+            MirBinaryOp::Subtract => self.long_subtract(target, right),
+            MirBinaryOp::Multiply => self.long_multiply(target, right),
+            MirBinaryOp::Divide | MirBinaryOp::Modulo => {
+                return Err(BackendError::Unsupported("division of long values", span))
+            }
+            MirBinaryOp::And | MirBinaryOp::Or => {
+                unreachable!("type checking only allows booleans here")
+            }
+            _ => unreachable!("comparisons are handled above"),
+        }
+        Ok(())
+    }
+
+    /// Stores 1 in the returned score if `left` is below `right` when both are read as unsigned
+    /// numbers, where scores with the sign bit set are above all others.
+    fn unsigned_less(&mut self, left: &Score, right: &Score) -> Score {
+        let result = self.temporary();
+        self.push(format!(
+            "execute store success score {} if score {} < {}",
+            result, left, right
+        ));
+        self.push(format!(
+            "execute if score {} matches ..-1 if score {} matches 0.. run scoreboard players set {} 0",
+            left, right, result
+        ));
+        self.push(format!(
+            "execute if score {} matches 0.. if score {} matches ..-1 run scoreboard players set {} 1",
+            left, right, result
+        ));
+        result
+    }
+
+    fn long_add(&mut self, target: &Score, (high, low): (Operand, Operand)) {
+        let (target_high, target_low) = (target.field("hi"), target.field("lo"));
+
+        // The low halves carry if their sum wrapped around below either of them.
+        let original = self.temporary();
+        self.copy(&original, &target_low);
+        self.binary_op(&target_low, MirBinaryOp::Add, low);
+        let carry = self.unsigned_less(&target_low, &original);
+
+        self.binary_op(&target_high, MirBinaryOp::Add, high);
+        self.binary_op(&target_high, MirBinaryOp::Add, Operand::Score(carry));
+    }
+
+    fn long_subtract(&mut self, target: &Score, (high, low): (Operand, Operand)) {
+        let (target_high, target_low) = (target.field("hi"), target.field("lo"));
+
+        let low = self.operand_score(low);
+        let borrow = self.unsigned_less(&target_low, &low);
+        self.binary_op(&target_low, MirBinaryOp::Subtract, Operand::Score(low));
+
+        self.binary_op(&target_high, MirBinaryOp::Subtract, high);
+        self.binary_op(&target_high, MirBinaryOp::Subtract, Operand::Score(borrow));
+    }
+
+    /// Splits the 32 bits of `score` into two unsigned 16 bit limbs, low limb first.
+    fn limbs(&mut self, score: &Score) -> (Score, Score) {
+        let low = self.temporary();
+        self.copy(&low, score);
+        self.binary_op(&low, MirBinaryOp::Modulo, Operand::Constant(LIMB));
+
+        let high = self.temporary();
+        self.copy(&high, score);
+        self.shift_limb(&high);
+        (low, high)
+    }
+
+    /// Shifts the bits of `score` right by one limb, filling in zeros like `>>>` does.
+    fn shift_limb(&mut self, score: &Score) {
+        self.binary_op(score, MirBinaryOp::Divide, Operand::Constant(LIMB));
+        self.binary_op(score, MirBinaryOp::Modulo, Operand::Constant(LIMB));
+    }
+
+    fn long_multiply(&mut self, target: &Score, (high, low): (Operand, Operand)) {
+        let (target_high, target_low) = (target.field("hi"), target.field("lo"));
+
+        // Only the low 32 bits of the products of a high half with the other low half are kept.
+        let cross = self.temporary();
+        self.copy(&cross, &target_low);
+        self.binary_op(&cross, MirBinaryOp::Multiply, high);
+        self.binary_op(&target_high, MirBinaryOp::Multiply, low.clone());
+        self.binary_op(&target_high, MirBinaryOp::Add, Operand::Score(cross));
+
+        // The product of the low halves can take 64 bits, the ones above the low 32 are added
+        // to the high half. Products of 16 bit limbs still fit the 32 bits of a score.
+        let (a0, a1) = self.limbs(&target_low);
+        let (b0, b1) = match &low {
+            Operand::Constant(value) => {
+                let value = *value as u32;
+                (
+                    Operand::Constant((value & 0xFFFF) as i32),
+                    Operand::Constant((value >> 16) as i32),
+                )
+            }
+            Operand::Score(score) => {
+                let (b0, b1) = self.limbs(score);
+                (Operand::Score(b0), Operand::Score(b1))
+            }
+        };
+
+        let middle = self.temporary();
+        self.copy(&middle, &a0);
+        self.binary_op(&middle, MirBinaryOp::Multiply, b0.clone());
+        self.shift_limb(&middle);
+        for (a, b) in [(&a0, b1.clone()), (&a1, b0)] {
+            let product = self.temporary();
+            self.copy(&product, a);
+            self.binary_op(&product, MirBinaryOp::Multiply, b);
+
+            let lower = self.temporary();
+            self.copy(&lower, &product);
+            self.binary_op(&lower, MirBinaryOp::Modulo, Operand::Constant(LIMB));
+            self.binary_op(&middle, MirBinaryOp::Add, Operand::Score(lower));
+
+            self.shift_limb(&product);
+            self.binary_op(&target_high, MirBinaryOp::Add, Operand::Score(product));
+        }
+        self.binary_op(&middle, MirBinaryOp::Divide, Operand::Constant(LIMB));
+        self.binary_op(&target_high, MirBinaryOp::Add, Operand::Score(middle));
+
+        let product = self.temporary();
+        self.copy(&product, &a1);
+        self.binary_op(&product, MirBinaryOp::Multiply, b1);
+        self.binary_op(&target_high, MirBinaryOp::Add, Operand::Score(product));
+
+        self.binary_op(&target_low, MirBinaryOp::Multiply, low);
+    }
+
+    /// Stores whether the comparison of two `long`s holds in `target`.
+    fn long_comparison(
+        &mut self,
+        target: &Score,
+        left: (Operand, Operand),
+        op: MirBinaryOp,
+        right: (Operand, Operand),
+    ) -> BackendResult<()> {
+        let (left, right, or_equal) = match op {
+            MirBinaryOp::Equals | MirBinaryOp::NotEquals => {
+                let (left_high, left_low) =
+                    (self.operand_score(left.0), self.operand_score(left.1));
+                let checks = [
+                    comparison(&left_high, MirBinaryOp::Equals, &right.0),
+                    comparison(&left_low, MirBinaryOp::Equals, &right.1),
+                ];
+                self.push(format!(
+                    "execute store success score {} {}",
+                    target,
+                    checks.join(" ")
+                ));
+                if op == MirBinaryOp::NotEquals {
+                    self.push(format!(
+                        "execute store success score {} if score {} matches 0",
+                        target, target
+                    ));
+                }
+                return Ok(());
+            }
+            MirBinaryOp::LessThan => (left, right, false),
+            MirBinaryOp::LessThanEquals => (left, right, true),
+            MirBinaryOp::GreaterThan => (right, left, false),
+            MirBinaryOp::GreaterThanEquals => (right, left, true),
+            _ => unreachable!("{:?} is not a comparison", op),
+        };
+
+        // The high halves decide unless they are equal, then the low halves are compared
+        // without their sign.
+        let (left_high, left_low) = (self.operand_score(left.0), self.operand_score(left.1));
+        let right_low = self.operand_score(right.1);
+        let (lower, expected) = if or_equal {
+            (self.unsigned_less(&right_low, &left_low), 0)
+        } else {
+            (self.unsigned_less(&left_low, &right_low), 1)
+        };
+
+        self.push(format!(
+            "execute store success score {} {}",
+            target,
+            comparison(&left_high, MirBinaryOp::LessThan, &right.0)
+        ));
+        self.push(format!(
+            "execute {} if score {} matches {} run scoreboard players set {} 1",
+            comparison(&left_high, MirBinaryOp::Equals, &right.0),
+            lower,
+            expected,
+            target
+        ));
+        Ok(())
+    }
+
+    /// Negates the `long` in `target`, flipping the bits of the high half and carrying into it
+    /// if the low half is 0.
+    pub(super) fn long_negate(&mut self, target: &Score) {
+        let (high, low) = (target.field("hi"), target.field("lo"));
+        self.binary_op(&high, MirBinaryOp::Multiply, Operand::Constant(-1));
+        self.binary_op(&high, MirBinaryOp::Subtract, Operand::Constant(1));
+        self.push(format!(
+            "execute if score {} matches 0 run scoreboard players add {} 1",
+            low, high
+        ));
+        self.binary_op(&low, MirBinaryOp::Multiply, Operand::Constant(-1));
+    }
+
+    /// Converts `value` to or from a `long`. Other numbers are converted to an `int` first and
+    /// the sign extended, `long`s keep their low bits like `as` does in Rust.
+    pub(super) fn long_cast(
+        &mut self,
+        target: &Score,
+        value: MirExpression<MangledVar>,
+        primitive: MirPrimitive,
+    ) -> BackendResult<()> {
+        let scale = |fixed: Option<FixedPoint>| fixed.map_or(1, |fixed| fixed.scale());
+        match (self.is_long(&value)?, primitive) {
+            (true, MirPrimitive::Long) => value.evaluate_into(self, target),
+            (true, _) => {
+                let to = scale(self.datapack.fixed_point(&MirType::Primitive(primitive)));
+                let holder = self.value_score(value)?;
+                self.copy(target, &holder.field("lo"));
+                self.rescale(target, 1, to);
+                self.wrap_integer(target, primitive);
+                Ok(())
+            }
+            (false, _) => {
+                let from = scale(self.fixed_point_of(&value)?);
+                let (high, low) = (target.field("hi"), target.field("lo"));
+                value.evaluate_into(self, &low)?;
+                self.rescale(&low, from, 1);
+                self.push(format!("scoreboard players set {} 0", high));
+                self.push(format!(
+                    "execute if score {} matches ..-1 run scoreboard players set {} -1",
+                    low, high
+                ));
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::{
+        tests::{call, lower},
+        BackendError, INIT_FUNCTION,
+    };
+
+    /// Edge cases around the halves and limbs, followed by pseudo-random values.
+    fn values() -> Vec<i64> {
+        let mut values = vec![
+            0,
+            1,
+            -1,
+            i32::MAX as i64,
+            i32::MIN as i64,
+            u32::MAX as i64,
+            1 << 32,
+            -(1 << 32),
+            0xFFFF,
+            0x1_0000,
+            0x7FFF_FFFF_FFFF_0000,
+            i64::MAX,
+            i64::MIN,
+            -987_654_321_012,
+        ];
+        let mut state: u64 = 0x2545_F491_4F6C_DD1D;
+        for _ in 0..16 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            values.push(state as i64 >> (state % 48));
+        }
+        values
+    }
+
+    #[test]
+    fn arithmetic_matches_i64() -> eyre::Result<()> {
+        let datapack = lower(
+            "static function add(a: long, b: long): long { return a + b; }
+            static function sub(a: long, b: long): long { return a - b; }
+            static function mul(a: long, b: long): long { return a * b; }
+            static function neg(a: long): long { return -a; }
+            static function constants(a: long): long { return a * 5000000000l - 70000l; }",
+        )??;
+
+        let values = values();
+        for &a in &values {
+            for &b in &values {
+                assert_eq!(
+                    call(&datapack, "add", &[a, b]),
+                    a.wrapping_add(b),
+                    "{a} + {b}"
+                );
+                assert_eq!(
+                    call(&datapack, "sub", &[a, b]),
+                    a.wrapping_sub(b),
+                    "{a} - {b}"
+                );
+                assert_eq!(
+                    call(&datapack, "mul", &[a, b]),
+                    a.wrapping_mul(b),
+                    "{a} * {b}"
+                );
+            }
+            assert_eq!(call(&datapack, "neg", &[a]), a.wrapping_neg(), "-{a}");
+            assert_eq!(
+                call(&datapack, "constants", &[a]),
+                a.wrapping_mul(5_000_000_000).wrapping_sub(70_000),
+                "{a} * 5000000000 - 70000"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn comparisons_match_i64() -> eyre::Result<()> {
+        let datapack = lower(
+            "static function eq(a: long, b: long): bool { return a == b; }
+            static function ne(a: long, b: long): bool { return a != b; }
+            static function lt(a: long, b: long): bool { return a < b; }
+            static function le(a: long, b: long): bool { return a <= b; }
+            static function gt(a: long, b: long): bool { return a > b; }
+            static function ge(a: long, b: long): bool { return a >= b; }
+            static function max(a: long, b: long): long {
+                let result: long = b;
+                if (a > b) { result = a; }
+                return result;
+            }",
+        )??;
+
+        let values = values();
+        for &a in &values {
+            for &b in &values {
+                let checks = [
+                    ("eq", a == b),
+                    ("ne", a != b),
+                    ("lt", a < b),
+                    ("le", a <= b),
+                    ("gt", a > b),
+                    ("ge", a >= b),
+                ];
+                for (function, expected) in checks {
+                    assert_eq!(
+                        call(&datapack, function, &[a, b]),
+                        expected as i64,
+                        "{function}({a}, {b})"
+                    );
+                }
+                assert_eq!(call(&datapack, "max", &[a, b]), a.max(b));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn conversions_extend_and_truncate() -> eyre::Result<()> {
+        let datapack = lower(
+            "static function widen(a: int): long { return a as long; }
+            static function narrow(a: long): int { return a as int; }
+            static function halve(a: float): long { return a as long + 1l; }
+            static function implicit(a: int, b: short): long { return a * 3l + b; }",
+        )??;
+
+        for value in [0, 1, -1, i32::MAX, i32::MIN, 123_456] {
+            assert_eq!(call(&datapack, "widen", &[value as i64]), value as i64);
+        }
+        for value in values() {
+            assert_eq!(call(&datapack, "narrow", &[value]), value as i32 as i64);
+        }
+        // `-2.5` is stored as -250, which is truncated to -2 like `as i64` does.
+        assert_eq!(call(&datapack, "halve", &[-250]), -1);
+        assert_eq!(
+            call(&datapack, "implicit", &[i32::MAX as i64, -5]),
+            i32::MAX as i64 * 3 - 5
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn longs_are_split_into_halves() -> eyre::Result<()> {
+        let datapack = lower(
+            "static big: long = 5000000000l;
+            static function f(): void { big = -big; }",
+        )??;
+
+        let init = datapack.function(INIT_FUNCTION).unwrap();
+        let holder = init[init.len() - 1].split(' ').nth(3).unwrap();
+        assert!(holder.ends_with(".lo"));
+        assert_eq!(
+            init[init.len() - 2..],
+            [
+                format!(
+                    "scoreboard players set {}.hi ss_global 1",
+                    &holder[..holder.len() - 3]
+                ),
+                format!("scoreboard players set {} ss_global 705032704", holder),
+            ]
+        );
+
+        assert!(matches!(
+            lower("static function f(a: long): long { return a / 2l; }")?,
+            Err(BackendError::Unsupported("division of long values", _))
+        ));
+        assert!(matches!(
+            lower("static values: long[2] = [1l, 2l];")?,
+            Err(BackendError::Unsupported("arrays of longs", _))
+        ));
+        Ok(())
+    }
+}
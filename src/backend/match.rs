@@ -0,0 +1,346 @@
+use super::{
+    display_path, BackendError, BackendResult, Condition, Datapack, Evaluate, FunctionBuilder,
+    Generated, Lower, Score, Variant,
+};
+use crate::{
+    mir::{AbsolutePath, MangledVar, MirArmBody, MirMatch, MirPattern, MirType},
+    token::XID,
+    Span,
+};
+
+/// Dispatching to more ranges than this checks half of them in a child function each, so
+/// large enums take a logarithmic number of checks.
+const LINEAR_DISPATCH_LIMIT: usize = 4;
+
+impl Datapack {
+    /// The variants of the enum at `path`, which the code at `span` refers to.
+    fn variants(&self, path: &AbsolutePath, span: Span) -> BackendResult<&[Variant]> {
+        let name = display_path(path);
+        match self.enums.get(&name) {
+            Some(variants) => Ok(variants),
+            None => Err(BackendError::UnknownType(name, span)),
+        }
+    }
+
+    /// The discriminant of `variant` and the types of its values.
+    pub(super) fn variant(
+        &self,
+        path: &AbsolutePath,
+        variant: &XID,
+        span: Span,
+    ) -> BackendResult<(i32, &[MirType<MangledVar>])> {
+        self.variants(path, span)?
+            .iter()
+            .enumerate()
+            .find(|(_, (name, _))| name == variant)
+            .map(|(index, (_, payload))| (index as i32, payload.as_slice()))
+            .ok_or_else(|| {
+                BackendError::UnknownType(format!("{}::{}", display_path(path), variant), span)
+            })
+    }
+
+    /// Records the types of the values bound by each arm, and the variables inside the arms.
+    pub(super) fn collect_match_variables(&mut self, match_expr: &MirMatch<MangledVar>) {
+        self.collect_expression_variables(&match_expr.scrutinee);
+        for arm in &match_expr.arms {
+            if let MirPattern::Variant {
+                enum_path,
+                variant,
+                bindings,
+            } = &arm.pattern
+            {
+                if let Ok((_, payload)) = self.variant(enum_path, variant, arm.span) {
+                    let bound: Vec<_> = bindings
+                        .iter()
+                        .zip(payload)
+                        .filter_map(|(binding, ty)| binding.map(|binding| (binding, ty.clone())))
+                        .collect();
+                    for (binding, ty) in bound {
+                        self.collect_local(binding, ty);
+                    }
+                }
+            }
+
+            match &arm.body {
+                MirArmBody::Block(block) => self.collect_variables(block),
+                MirArmBody::Expression(expr) => self.collect_expression_variables(expr),
+            }
+        }
+    }
+}
+
+/// The `matches` range of discriminants from `start` to `end`.
+fn range(start: i32, end: i32) -> String {
+    if start == end {
+        start.to_string()
+    } else {
+        format!("{}..{}", start, end)
+    }
+}
+
+impl FunctionBuilder<'_> {
+    /// Lowers `match_expr`, storing the value of the arm that runs in `target` if there is one.
+    /// Every arm becomes its own function, run when the discriminant of the scrutinee falls in
+    /// one of the ranges the arm handles.
+    pub(super) fn lower_match(
+        &mut self,
+        match_expr: MirMatch<MangledVar>,
+        target: Option<&Score>,
+    ) -> BackendResult<()> {
+        let enum_path = match_expr.arms.iter().find_map(|arm| match &arm.pattern {
+            MirPattern::Variant { enum_path, .. } => Some(enum_path.clone()),
+            MirPattern::Wildcard => None,
+        });
+
+        // Only `_` arms, which match without looking at the value.
+        let Some(enum_path) = enum_path else {
+            match_expr.scrutinee.evaluate_discarded(self)?;
+            let Some(arm) = match_expr.arms.into_iter().next() else {
+                return Ok(());
+            };
+            return self.lower_arm_body(arm.body, target);
+        };
+
+        let variant_count = self
+            .datapack
+            .variants(&enum_path, match_expr.scrutinee.span)?
+            .len();
+        let holder = self.value_score(match_expr.scrutinee)?;
+        // Arms may change the scrutinee, the discriminant must not change before every check ran.
+        let discriminant = if holder.is_temporary() {
+            holder.clone()
+        } else {
+            let temporary = self.temporary();
+            self.copy(&temporary, &holder);
+            temporary
+        };
+
+        // The first arm matching a discriminant handles it.
+        let mut handlers: Vec<Option<usize>> = vec![None; variant_count];
+        let mut arms = vec![];
+        for arm in match_expr.arms {
+            let index = arms.len();
+            let handles_any = match &arm.pattern {
+                MirPattern::Wildcard => {
+                    let mut handles_any = false;
+                    for handler in handlers.iter_mut().filter(|handler| handler.is_none()) {
+                        *handler = Some(index);
+                        handles_any = true;
+                    }
+                    handles_any
+                }
+                MirPattern::Variant { variant, .. } => {
+                    let (discriminant, _) = self.datapack.variant(&enum_path, variant, arm.span)?;
+                    let handler = &mut handlers[discriminant as usize];
+                    let handles_any = handler.is_none();
+                    handler.get_or_insert(index);
+                    handles_any
+                }
+            };
+
+            if !handles_any {
+                arms.push(None);
+                continue;
+            }
+
+            let generated = self.generate("case", true, |builder| {
+                if let MirPattern::Variant {
+                    variant, bindings, ..
+                } = arm.pattern
+                {
+                    let (_, payload) = builder.datapack.variant(&enum_path, &variant, arm.span)?;
+                    let payload = payload.to_vec();
+                    let values = holder.field(variant.inner());
+                    for (index, (binding, ty)) in bindings.into_iter().zip(payload).enumerate() {
+                        if let Some(binding) = binding {
+                            let layout = builder.datapack.layout(&ty, arm.span)?;
+                            let value = values.field(&index.to_string());
+                            builder.copy_value(&Score::variable(binding), &value, &layout);
+                        }
+                    }
+                }
+
+                builder.lower_arm_body(arm.body, target)
+            })?;
+            arms.push(Some(generated));
+        }
+
+        // Neighbouring discriminants handled by the same arm share a range.
+        let mut ranges: Vec<(i32, i32, usize)> = vec![];
+        for (discriminant, handler) in handlers.into_iter().enumerate() {
+            let Some(handler) = handler else {
+                continue;
+            };
+            let discriminant = discriminant as i32;
+            match ranges.last_mut() {
+                Some((_, end, last)) if *last == handler && *end == discriminant - 1 => {
+                    *end = discriminant
+                }
+                _ => ranges.push((discriminant, discriminant, handler)),
+            }
+        }
+
+        let ranges: Vec<(i32, i32, Generated)> = ranges
+            .into_iter()
+            .filter_map(|(start, end, handler)| {
+                arms[handler]
+                    .clone()
+                    .map(|generated| (start, end, generated))
+            })
+            .collect();
+        self.dispatch(&discriminant, &ranges, "match")
+    }
+
+    /// Runs the generated function of the range `discriminant` is in, checking each range in
+    /// turn or splitting them in half into child functions of `kind` until few enough are left.
+    pub(super) fn dispatch(
+        &mut self,
+        discriminant: &Score,
+        ranges: &[(i32, i32, Generated)],
+        kind: &str,
+    ) -> BackendResult<()> {
+        if ranges.len() <= LINEAR_DISPATCH_LIMIT {
+            for (start, end, generated) in ranges {
+                let condition = Condition::check(format!(
+                    "if score {} matches {}",
+                    discriminant,
+                    range(*start, *end)
+                ));
+                self.run_generated(&condition, generated.clone());
+            }
+            return Ok(());
+        }
+
+        let (low, high) = ranges.split_at(ranges.len() / 2);
+        let split = high[0].0;
+        let condition =
+            Condition::check(format!("if score {} matches ..{}", discriminant, split - 1));
+        self.run_child(&condition, kind, |builder| {
+            builder.dispatch(discriminant, low, kind)
+        })?;
+        let condition = Condition::check(format!("if score {} matches {}..", discriminant, split));
+        self.run_child(&condition, kind, |builder| {
+            builder.dispatch(discriminant, high, kind)
+        })
+    }
+
+    fn lower_arm_body(
+        &mut self,
+        body: MirArmBody<MangledVar>,
+        target: Option<&Score>,
+    ) -> BackendResult<()> {
+        match (body, target) {
+            (MirArmBody::Block(block), _) => block.lower(self),
+            (MirArmBody::Expression(expr), Some(target)) => expr.evaluate_into(self, target),
+            (MirArmBody::Expression(expr), None) => expr.evaluate_discarded(self),
+        }
+    }
+}
+
+impl Lower for MirMatch<MangledVar> {
+    fn lower(self, builder: &mut FunctionBuilder) -> BackendResult<()> {
+        builder.lower_match(self, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::tests::{call, lower};
+
+    #[test]
+    fn arms_copy_their_bindings() -> eyre::Result<()> {
+        let datapack = lower(
+            "enum Shape { Empty, Square(int), Rect(int, int) }
+            static function area(shape: Shape): int {
+                return match (shape) {
+                    Shape::Square(side) => side * side,
+                    Shape::Rect(width, height) => width * height,
+                    Shape::Empty => 0,
+                };
+            }",
+        )??;
+
+        let area = datapack.function("area").unwrap();
+        let param = area[0].split(' ').nth(6).unwrap();
+        assert_eq!(
+            area[0],
+            format!("scoreboard players operation #test.t0 ss_global = {param} ss_global")
+        );
+        assert_eq!(
+            area[1],
+            "execute if score #test.t0 ss_global matches 0 run scoreboard players set #return ss_global 0"
+        );
+        assert_eq!(
+            area[2..],
+            [
+                "execute if score #test.t0 ss_global matches 1 run function test:area/case_0",
+                "execute if score #test.t0 ss_global matches 2 run function test:area/case_1",
+            ]
+        );
+
+        let rect = datapack.function("area/case_1").unwrap();
+        assert!(rect[0].ends_with(&format!("= {param}.Rect.0 ss_global")));
+        assert!(rect[1].ends_with(&format!("= {param}.Rect.1 ss_global")));
+        Ok(())
+    }
+
+    #[test]
+    fn copies_only_read_the_active_payload() -> eyre::Result<()> {
+        let datapack = lower(
+            "enum Shape { Empty, Square(int), Rect(int, int) }
+            static function area(shape: Shape): int {
+                return match (shape) {
+                    Shape::Square(side) => side * side,
+                    Shape::Rect(width, height) => width * height,
+                    Shape::Empty => 0,
+                };
+            }
+            static function copied(): int {
+                let shape: Shape = Shape::Square(3);
+                let copy: Shape = shape;
+                return area(copy);
+            }
+            static function passed(): int { return area(Shape::Rect(2, 5)); }",
+        )??;
+
+        assert_eq!(call(&datapack, "copied", &[]), 9);
+        assert_eq!(call(&datapack, "passed", &[]), 10);
+        Ok(())
+    }
+
+    #[test]
+    fn large_enums_are_dispatched_by_halves() -> eyre::Result<()> {
+        let datapack = lower(
+            "enum Digit { Zero, One, Two, Three, Four, Five, Six, Seven, Eight, Nine }
+            static digit: Digit = Digit::Three;
+            static odd: bool = false;
+            static function main(): void {
+                odd = match (digit) {
+                    Digit::One => true,
+                    Digit::Three => true,
+                    Digit::Five => true,
+                    Digit::Seven => true,
+                    Digit::Nine => true,
+                    _ => false,
+                };
+            }",
+        )??;
+
+        let main = datapack.function("main").unwrap();
+        assert_eq!(main.len(), 3);
+        assert_eq!(
+            main[1],
+            "execute if score #test.t0 ss_global matches ..4 run function test:main/match_6"
+        );
+        assert_eq!(
+            main[2],
+            "execute if score #test.t0 ss_global matches 5.. run function test:main/match_9"
+        );
+
+        let low = datapack.function("main/match_6").unwrap();
+        assert_eq!(low.len(), 2);
+        assert!(low[0].contains(" matches ..1 run function test:main/match_7"));
+        assert!(low[1].contains(" matches 2.. run function test:main/match_8"));
+        Ok(())
+    }
+}
@@ -0,0 +1,1067 @@
+use self::r#while::EnclosingLoop;
+pub use self::{
+    cond::Condition,
+    expr::Evaluate,
+    fixed::{FixedPoint, DEFAULT_DOUBLE_SCALE, DEFAULT_FLOAT_SCALE, MAX_SCALE},
+    score::{Operand, Score, OBJECTIVE},
+    version::{Feature, InvalidVersion, MinecraftVersion},
+};
+use crate::{
+    config::PackageInfo,
+    diagnostic::Diagnostic,
+    hir::mir::MirPrimitive,
+    mir::{AbsolutePath, AbsoluteVar, MangledVar, MirFunction, MirStatement, MirType},
+    token::XID,
+    Span,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs, io, mem,
+    ops::Range,
+    path::Path,
+};
+use thiserror::Error;
+
+mod array;
+mod cond;
+mod expr;
+mod fixed;
+mod r#for;
+mod frame;
+mod func;
+mod r#if;
+mod integer;
+mod long;
+mod r#match;
+mod optional;
+mod pack;
+mod score;
+mod stmt;
+mod storage;
+mod string;
+mod r#struct;
+mod version;
+mod r#while;
+
+/// Function holding all top level statements of a program.
+pub const INIT_FUNCTION: &str = "__init";
+/// Function run whenever the datapack is loaded.
+pub const LOAD_FUNCTION: &str = "main";
+/// Function run every tick.
+pub const TICK_FUNCTION: &str = "tick";
+/// Default value of the `maxCommandChainLength` gamerule.
+pub const DEFAULT_MAX_COMMAND_CHAIN_LENGTH: u32 = 65536;
+
+#[derive(Debug, Error)]
+pub enum BackendError {
+    #[error("{0} are not supported yet")]
+    Unsupported(&'static str, Span),
+    #[error("Cannot find function `{0}`")]
+    UnknownFunction(String, Span),
+    #[error("Function `{0}` is defined more than once")]
+    DuplicateFunction(String, Span),
+    #[error("Type `{0}` is defined more than once")]
+    DuplicateType(String, Span),
+    #[error("Cannot find type `{0}`")]
+    UnknownType(String, Span),
+    #[error("Type `{0}` contains itself, it cannot be stored in a fixed number of scores")]
+    RecursiveType(String, Span),
+    #[error(
+        "Invalid function name `{0}`, only lowercase letters, digits, `_`, `-` and `.` are allowed"
+    )]
+    InvalidFunctionName(String, Span),
+    #[error("Function `{0}` takes {1} arguments but {2} were supplied")]
+    ArgumentCount(String, usize, usize, Span),
+    #[error("Function `{0}` does not return a value")]
+    VoidValue(String, Span),
+    #[error("Function `{0}` is run by a function tag and cannot take arguments")]
+    EntrypointArguments(String, Span),
+    #[error("Using {0} requires Minecraft {} or newer, but the target is Minecraft {1}", .0.since())]
+    UnsupportedFeature(Feature, MinecraftVersion, Span),
+    #[error(
+        "Cannot return from or break out of a `spread` loop, it may still be running after its function ends"
+    )]
+    SpreadLoopReturn(Span),
+    #[error("Scale {1} of `{0}` must be a power of ten no larger than {MAX_SCALE}")]
+    InvalidScale(MirPrimitive, u32, Span),
+    #[error("`{0}` is out of range, {1}")]
+    FixedPointRange(String, FixedPoint, Span),
+}
+
+pub type BackendResult<T> = Result<T, BackendError>;
+
+impl From<BackendError> for Diagnostic {
+    fn from(err: BackendError) -> Self {
+        let diagnostic = Diagnostic::error(&err);
+        match err {
+            BackendError::Unsupported(_, span) => diagnostic.with_primary(span, "not supported"),
+            BackendError::UnknownFunction(_, span) | BackendError::UnknownType(_, span) => {
+                diagnostic.with_primary(span, "not found")
+            }
+            BackendError::DuplicateFunction(_, span) | BackendError::DuplicateType(_, span) => {
+                diagnostic.with_primary(span, "defined again here")
+            }
+            BackendError::RecursiveType(_, span) => diagnostic.with_primary(span, "recursive type"),
+            BackendError::InvalidFunctionName(_, span) => {
+                diagnostic.with_primary(span, "invalid name")
+            }
+            BackendError::ArgumentCount(_, expected, _, span) => {
+                diagnostic.with_primary(span, format!("expected {} arguments", expected))
+            }
+            BackendError::VoidValue(_, span) => diagnostic.with_primary(span, "has no value"),
+            BackendError::EntrypointArguments(_, span) => {
+                diagnostic.with_primary(span, "takes arguments")
+            }
+            BackendError::UnsupportedFeature(feature, _, span) => diagnostic
+                .with_primary(span, format!("requires Minecraft {}", feature.since()))
+                .with_help(
+                    "set `target.minecraft_version` in `stonescript.toml` to a newer version",
+                ),
+            BackendError::SpreadLoopReturn(span) => {
+                diagnostic.with_primary(span, "condition of the `spread` loop")
+            }
+            BackendError::InvalidScale(_, _, span) => {
+                diagnostic.with_primary(span, "invalid scale")
+            }
+            BackendError::FixedPointRange(_, _, span) => diagnostic
+                .with_primary(span, "does not fit in a score")
+                .with_help("a smaller scale in `stonescript.toml` allows larger values"),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum BackendWarning {
+    #[error("`{0}` is rounded to {1}, {2}")]
+    InexactLiteral(String, String, FixedPoint, Span),
+}
+
+impl From<BackendWarning> for Diagnostic {
+    fn from(warning: BackendWarning) -> Self {
+        let diagnostic = Diagnostic::warning(&warning);
+        match warning {
+            BackendWarning::InexactLiteral(_, _, _, span) => diagnostic
+                .with_primary(span, "has more decimal places than are kept")
+                .with_help("a larger scale in `stonescript.toml` keeps more decimal places"),
+        }
+    }
+}
+
+pub trait Lower {
+    fn lower(self, builder: &mut FunctionBuilder) -> BackendResult<()>;
+}
+
+impl<T: Lower> Lower for Vec<T> {
+    fn lower(self, builder: &mut FunctionBuilder) -> BackendResult<()> {
+        for item in self {
+            item.lower(builder)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn display_path(path: &AbsolutePath) -> String {
+    path.inner()
+        .iter()
+        .map(AbsoluteVar::inner)
+        .collect::<Vec<_>>()
+        .join("::")
+}
+
+/// A variant of an enum and the types of the values it holds.
+type Variant = (XID, Vec<MirType<MangledVar>>);
+
+#[derive(Debug, Clone)]
+struct Signature {
+    args: Vec<MangledVar>,
+    return_type: MirType<MangledVar>,
+}
+
+pub struct Datapack {
+    version: MinecraftVersion,
+    max_command_chain_length: u32,
+    float: FixedPoint,
+    double: FixedPoint,
+    namespace: String,
+    description: String,
+    functions: BTreeMap<String, Vec<String>>,
+    signatures: HashMap<String, Signature>,
+    /// Fields of every struct, in declaration order.
+    structs: HashMap<String, Vec<(XID, MirType<MangledVar>)>>,
+    /// Payload types of every variant of every enum, in declaration order.
+    enums: HashMap<String, Vec<Variant>>,
+    variables: HashMap<MangledVar, MirType<MangledVar>>,
+    /// Parameters and local variables of every function, see [`frame`].
+    locals: HashMap<String, Vec<MangledVar>>,
+    /// Functions of this datapack every function calls.
+    calls: HashMap<String, BTreeSet<String>>,
+    /// The function [`Datapack::collect_variables`] is in.
+    collecting: Option<String>,
+    /// Temporaries allocated while lowering every function.
+    function_temporaries: HashMap<String, Range<usize>>,
+    /// Functions which save their frame around calls, with the span of such a call.
+    framed: BTreeMap<String, Span>,
+    objectives: BTreeSet<&'static str>,
+    constants: BTreeSet<i32>,
+    temporary_counter: usize,
+    child_counters: HashMap<String, usize>,
+    load: Vec<String>,
+    tick: Vec<String>,
+    warnings: Vec<BackendWarning>,
+}
+
+impl Datapack {
+    pub fn new(package: &PackageInfo, version: MinecraftVersion) -> Self {
+        Self {
+            version,
+            max_command_chain_length: DEFAULT_MAX_COMMAND_CHAIN_LENGTH,
+            float: FixedPoint::new(MirPrimitive::Float, DEFAULT_FLOAT_SCALE)
+                .expect("the default scale is valid"),
+            double: FixedPoint::new(MirPrimitive::Double, DEFAULT_DOUBLE_SCALE)
+                .expect("the default scale is valid"),
+            namespace: package.namespace(),
+            description: package.description(),
+            functions: BTreeMap::new(),
+            signatures: HashMap::new(),
+            structs: HashMap::new(),
+            enums: HashMap::new(),
+            variables: HashMap::new(),
+            locals: HashMap::new(),
+            calls: HashMap::new(),
+            collecting: None,
+            function_temporaries: HashMap::new(),
+            framed: BTreeMap::new(),
+            objectives: BTreeSet::from([OBJECTIVE]),
+            constants: BTreeSet::new(),
+            temporary_counter: 0,
+            child_counters: HashMap::new(),
+            load: vec![],
+            tick: vec![],
+            warnings: vec![],
+        }
+    }
+
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    pub fn version(&self) -> MinecraftVersion {
+        self.version
+    }
+
+    pub fn set_max_command_chain_length(&mut self, length: u32) {
+        self.max_command_chain_length = length;
+    }
+
+    /// Warnings found while lowering, like literals losing precision.
+    pub fn take_warnings(&mut self) -> Vec<BackendWarning> {
+        mem::take(&mut self.warnings)
+    }
+
+    /// Fails if the target version does not support `feature`, which the code at `span` needs.
+    pub fn require(&self, feature: Feature, span: Span) -> BackendResult<()> {
+        if self.version.supports(feature) {
+            Ok(())
+        } else {
+            Err(BackendError::UnsupportedFeature(
+                feature,
+                self.version,
+                span,
+            ))
+        }
+    }
+
+    /// Lowers a whole program, every function becomes its own file and everything else ends up
+    /// in [`INIT_FUNCTION`], which runs on load before [`LOAD_FUNCTION`].
+    pub fn lower_root(&mut self, statements: Vec<MirStatement<MangledVar>>) -> BackendResult<()> {
+        self.collect_signatures(&statements)?;
+        self.collect_variables(&statements);
+        self.load.push(self.location(INIT_FUNCTION));
+
+        for statement in &statements {
+            if let MirStatement::Function(func) = statement {
+                let name = func.name.inner();
+                if name != LOAD_FUNCTION && name != TICK_FUNCTION {
+                    continue;
+                }
+
+                if !func.args.is_empty() {
+                    return Err(BackendError::EntrypointArguments(
+                        name.to_string(),
+                        func.span,
+                    ));
+                }
+
+                let location = self.location(name);
+                if name == LOAD_FUNCTION {
+                    self.load.push(location);
+                } else {
+                    self.tick.push(location);
+                }
+            }
+        }
+
+        let mut init = FunctionBuilder::new(self, INIT_FUNCTION.to_string());
+        statements.lower(&mut init)?;
+        let init_commands = init.into_commands();
+
+        let mut commands: Vec<String> = self
+            .objectives
+            .iter()
+            .map(|objective| format!("scoreboard objectives add {} dummy", objective))
+            .collect();
+        commands.extend(self.constants.iter().map(|value| {
+            format!(
+                "scoreboard players set {} {}",
+                Score::constant(*value),
+                value
+            )
+        }));
+        commands.extend(init_commands);
+        self.functions.insert(INIT_FUNCTION.to_string(), commands);
+        self.write_frames()?;
+
+        Ok(())
+    }
+
+    /// The resource location of the function at `path` in this datapack.
+    fn location(&self, path: &str) -> String {
+        format!("{}:{}", self.namespace, path)
+    }
+
+    /// Collects the signature of every function and the contents of every struct and enum.
+    fn collect_signatures(&mut self, statements: &[MirStatement<MangledVar>]) -> BackendResult<()> {
+        for statement in statements {
+            match statement {
+                MirStatement::Function(func) => {
+                    let name = func::function_path(&func.name, func.span)?;
+                    if name == INIT_FUNCTION || self.signatures.contains_key(&name) {
+                        return Err(BackendError::DuplicateFunction(name, func.span));
+                    }
+
+                    self.signatures.insert(name, Signature::from(func));
+                    self.collect_signatures(&func.block)?;
+                }
+                MirStatement::Struct(decl) => {
+                    let name = self.type_name(&decl.name, decl.span)?;
+                    self.structs.insert(name, decl.fields.clone());
+                }
+                MirStatement::Enum(decl) => {
+                    let name = self.type_name(&decl.name, decl.span)?;
+                    self.enums.insert(name, decl.variants.clone());
+                }
+                MirStatement::Block(block) | MirStatement::Unsafe(block) => {
+                    self.collect_signatures(block)?
+                }
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The name a type declared as `name` is known by, which no other type may have.
+    fn type_name(&self, name: &XID, span: Span) -> BackendResult<String> {
+        let name = name.to_string();
+        if self.structs.contains_key(&name) || self.enums.contains_key(&name) {
+            Err(BackendError::DuplicateType(name, span))
+        } else {
+            Ok(name)
+        }
+    }
+
+    /// Splits `path` into the namespace of the function it refers to and its path there.
+    fn split_path(&self, path: &AbsolutePath) -> (String, String) {
+        let segments: Vec<&str> = path.inner().iter().map(AbsoluteVar::inner).collect();
+
+        match segments.as_slice() {
+            [name] => (self.namespace.clone(), name.to_string()),
+            [namespace, rest @ ..] => (namespace.to_string(), rest.join("/")),
+            [] => panic!("Path should not be empty"),
+        }
+    }
+
+    /// Finds the function a call at `span` refers to, returning its resource location and, for
+    /// functions defined in this datapack, its signature.
+    fn resolve(
+        &self,
+        path: &AbsolutePath,
+        span: Span,
+    ) -> BackendResult<(String, Option<&Signature>)> {
+        let (namespace, name) = self.split_path(path);
+
+        if namespace == self.namespace {
+            let signature = self
+                .signatures
+                .get(&name)
+                .ok_or_else(|| BackendError::UnknownFunction(display_path(path), span))?;
+            Ok((self.location(&name), Some(signature)))
+        } else {
+            Ok((format!("{}:{}", namespace, name), None))
+        }
+    }
+
+    fn temporary(&mut self) -> Score {
+        let score = Score::temporary(&self.namespace, self.temporary_counter);
+        self.temporary_counter += 1;
+        score
+    }
+
+    fn constant(&mut self, value: i32) -> Score {
+        self.constants.insert(value);
+        Score::constant(value)
+    }
+
+    pub fn functions(&self) -> impl Iterator<Item = (&str, &[String])> {
+        self.functions
+            .iter()
+            .map(|(path, commands)| (path.as_str(), commands.as_slice()))
+    }
+
+    pub fn function(&self, path: &str) -> Option<&[String]> {
+        self.functions.get(path).map(Vec::as_slice)
+    }
+
+    /// Writes the datapack with `root` as its root directory.
+    pub fn write(&self, root: &Path) -> io::Result<()> {
+        self.write_pack_meta(root)?;
+        self.write_function_tags(root)?;
+
+        let function_dir = root
+            .join("data")
+            .join(&self.namespace)
+            .join(self.version.function_directory());
+
+        // Functions removed since the last build would still be in the pack.
+        if function_dir.exists() {
+            fs::remove_dir_all(&function_dir)?;
+        }
+
+        for (path, commands) in &self.functions {
+            let file = function_dir.join(format!("{}.mcfunction", path));
+            if let Some(parent) = file.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            let mut contents = commands.join("\n");
+            contents.push('\n');
+            fs::write(file, contents)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A function generated by [`FunctionBuilder::generate`].
+#[derive(Clone)]
+pub struct Generated {
+    /// Runs the function, or its only command if it was inlined.
+    command: Option<String>,
+    /// An inlined command stopping early is the `return` itself, it needs no check afterwards.
+    inlined: bool,
+    returns_early: bool,
+    /// Loop control scores of enclosing loops the function may have stopped for.
+    exits: Vec<Score>,
+}
+
+impl Generated {
+    pub fn returns_early(&self) -> bool {
+        self.returns_early
+    }
+}
+
+pub struct FunctionBuilder<'a> {
+    datapack: &'a mut Datapack,
+    path: String,
+    /// The function written in source this builder belongs to, differs from `path` for the
+    /// functions generated for branches.
+    function: String,
+    commands: Vec<String>,
+    returns_early: bool,
+    loops: Vec<EnclosingLoop>,
+    exits: Vec<Score>,
+}
+
+impl<'a> FunctionBuilder<'a> {
+    fn new(datapack: &'a mut Datapack, path: String) -> Self {
+        Self {
+            datapack,
+            function: path.clone(),
+            path,
+            commands: vec![],
+            returns_early: false,
+            loops: vec![],
+            exits: vec![],
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The resource location of the function being built.
+    pub fn location(&self) -> String {
+        self.datapack.location(&self.path)
+    }
+
+    /// Whether this builder holds a function generated for a branch rather than a source function.
+    pub fn is_child(&self) -> bool {
+        self.path != self.function
+    }
+
+    /// Set once a `return` inside a child function stops the source function.
+    pub fn return_flag(&self) -> Score {
+        Score::return_flag(&self.datapack.namespace, &self.function)
+    }
+
+    /// Creates a builder for a new function generated as part of the current source function.
+    fn child(&mut self, kind: &str) -> FunctionBuilder<'_> {
+        let counter = self
+            .datapack
+            .child_counters
+            .entry(self.function.clone())
+            .or_default();
+        let path = format!("{}/{}_{}", self.function, kind, counter);
+        *counter += 1;
+
+        FunctionBuilder {
+            datapack: &mut *self.datapack,
+            path,
+            function: self.function.clone(),
+            commands: vec![],
+            returns_early: false,
+            loops: self.loops.clone(),
+            exits: vec![],
+        }
+    }
+
+    /// Runs `body` in a child function when `condition` holds, bodies of a single command are
+    /// inlined instead.
+    pub fn run_child(
+        &mut self,
+        condition: &Condition,
+        kind: &str,
+        body: impl FnOnce(&mut FunctionBuilder) -> BackendResult<()>,
+    ) -> BackendResult<()> {
+        let child = self.generate(kind, true, body)?;
+        self.run_generated(condition, child);
+        Ok(())
+    }
+
+    /// Lowers `body` into a child function, which is only written if it cannot be inlined.
+    pub fn generate(
+        &mut self,
+        kind: &str,
+        inline: bool,
+        body: impl FnOnce(&mut FunctionBuilder) -> BackendResult<()>,
+    ) -> BackendResult<Generated> {
+        let mut child = self.child(kind);
+        body(&mut child)?;
+        let returns_early = child.returns_early;
+        let exits = mem::take(&mut child.exits);
+
+        let (command, inlined) = match child.commands.as_slice() {
+            [] if inline => (None, true),
+            [command] if inline => (Some(command.clone()), true),
+            _ => {
+                let location = child.location();
+                child.finish();
+                (Some(format!("function {}", location)), false)
+            }
+        };
+
+        Ok(Generated {
+            command,
+            inlined,
+            returns_early,
+            exits,
+        })
+    }
+
+    /// Runs a generated function when `condition` holds, stopping this function as well if a
+    /// `return`, `break` or `continue` inside stopped it.
+    pub fn run_generated(&mut self, condition: &Condition, generated: Generated) {
+        if let Some(command) = generated
+            .command
+            .and_then(|command| condition.run(&command))
+        {
+            self.push(command);
+        }
+
+        if generated.inlined {
+            self.returns_early |= generated.returns_early;
+            for exit in generated.exits {
+                if !self.exits.contains(&exit) {
+                    self.exits.push(exit);
+                }
+            }
+            return;
+        }
+
+        if generated.returns_early {
+            self.returns_early = true;
+            self.push(format!(
+                "execute if score {} matches 1 run return 0",
+                self.return_flag()
+            ));
+        }
+
+        for exit in generated.exits {
+            self.push(format!(
+                "execute unless score {} matches 0 run return 0",
+                exit
+            ));
+            if !self.exits.contains(&exit) {
+                self.exits.push(exit);
+            }
+        }
+    }
+
+    pub fn push(&mut self, command: impl Into<String>) {
+        self.commands.push(command.into())
+    }
+
+    pub fn temporary(&mut self) -> Score {
+        self.datapack.temporary()
+    }
+
+    pub fn constant(&mut self, value: i32) -> Score {
+        self.datapack.constant(value)
+    }
+
+    pub fn max_command_chain_length(&self) -> u32 {
+        self.datapack.max_command_chain_length
+    }
+
+    pub fn supports(&self, feature: Feature) -> bool {
+        self.datapack.version.supports(feature)
+    }
+
+    pub fn require(&self, feature: Feature, span: Span) -> BackendResult<()> {
+        self.datapack.require(feature, span)
+    }
+
+    fn into_commands(mut self) -> Vec<String> {
+        self.reset_return_flag();
+        self.commands
+    }
+
+    /// Source functions stopped by a child function start by clearing the flag of the last call.
+    fn reset_return_flag(&mut self) {
+        if self.returns_early && !self.is_child() {
+            let flag = self.return_flag();
+            self.commands
+                .insert(0, format!("scoreboard players set {} 0", flag));
+        }
+    }
+
+    fn finish(mut self) {
+        self.reset_return_flag();
+        self.datapack.functions.insert(self.path, self.commands);
+    }
+}
+
+impl From<&MirFunction<MangledVar>> for Signature {
+    fn from(value: &MirFunction<MangledVar>) -> Self {
+        Self {
+            args: value.args.iter().map(|(name, _)| *name).collect(),
+            return_type: value.return_type.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        frame, BackendError, Datapack, Diagnostic, Feature, MinecraftVersion, MirPrimitive,
+        MirType, Score, INIT_FUNCTION,
+    };
+    use crate::{
+        config::PackageInfo,
+        hir::{check_loop_control, Statement},
+        mir::{check_types, AbsoluteScope, MangleScope, ToMir, TypeError},
+        parse_str, TokenIter,
+    };
+    use semver::Version;
+    use std::{collections::HashMap, env, fs};
+
+    pub(super) fn lower(input: &str) -> eyre::Result<Result<Datapack, BackendError>> {
+        lower_for(input, MinecraftVersion::LATEST)
+    }
+
+    pub(super) fn lower_for(
+        input: &str,
+        version: MinecraftVersion,
+    ) -> eyre::Result<Result<Datapack, BackendError>> {
+        let tokens = parse_str(input)?;
+        let statements: Vec<Statement> = TokenIter::from(&tokens).parse()?;
+        if let Some(err) = check_loop_control(&statements).into_iter().next() {
+            return Err(err.into());
+        }
+        let mut mir = AbsoluteScope::root_to_absolute(statements.into_mir());
+        if let Some(err) = check_types(&mut mir).into_iter().next() {
+            return Err(err.into());
+        }
+        let mir = MangleScope::mangle_root("test", mir);
+
+        let mut datapack = Datapack::new(
+            &PackageInfo {
+                name: "test".to_string(),
+                version: Version::new(0, 1, 0),
+                description: None,
+            },
+            version,
+        );
+        Ok(datapack.lower_root(mir).map(|_| datapack))
+    }
+
+    /// Runs `function` like Minecraft would, as far as the scoreboard commands lowering emits
+    /// go. Scores are keyed by holder and objective, reading one which was never set panics
+    /// outside of conditions, which never hold for it. Saved frames are kept alongside them,
+    /// keyed by their path in storage, with their count under [`frame::FRAMES`].
+    pub(super) fn run(datapack: &Datapack, function: &str, scores: &mut HashMap<String, i32>) {
+        for command in datapack.function(function).unwrap() {
+            if run_command(datapack, command, scores).is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Calls `function` with `args` and returns its result, `int`s are passed and returned as
+    /// they are and `long`s split into halves.
+    pub(super) fn call(datapack: &Datapack, function: &str, args: &[i64]) -> i64 {
+        let mut scores = HashMap::new();
+        run(datapack, INIT_FUNCTION, &mut scores);
+        for (variable, value) in datapack.signatures[function].args.iter().zip(args) {
+            let param = Score::variable(*variable);
+            if datapack.variables[variable] == MirType::Primitive(MirPrimitive::Long) {
+                let (high, low) = ((*value >> 32) as i32, *value as i32);
+                scores.insert(param.field("hi").to_string(), high);
+                scores.insert(param.field("lo").to_string(), low);
+            } else {
+                scores.insert(param.to_string(), *value as i32);
+            }
+        }
+        run(datapack, function, &mut scores);
+
+        let result = Score::return_register();
+        match scores.get(&result.field("hi").to_string()) {
+            Some(high) => {
+                let low = scores[&result.field("lo").to_string()];
+                ((*high as i64) << 32) | low as u32 as i64
+            }
+            None => scores[&result.to_string()] as i64,
+        }
+    }
+
+    fn get(scores: &HashMap<String, i32>, score: String) -> i32 {
+        *scores
+            .get(&score)
+            .unwrap_or_else(|| panic!("{} is read before it is set", score))
+    }
+
+    /// Java's `Math.floorDiv`, which `/=` uses.
+    fn floor_div(left: i32, right: i32) -> i32 {
+        let quotient = left.wrapping_div(right);
+        if left.wrapping_rem(right) != 0 && (left < 0) != (right < 0) {
+            quotient - 1
+        } else {
+            quotient
+        }
+    }
+
+    fn in_range(range: &str, value: i32) -> bool {
+        match range.split_once("..") {
+            Some((min, max)) => {
+                (min.is_empty() || value >= min.parse().unwrap())
+                    && (max.is_empty() || value <= max.parse().unwrap())
+            }
+            None => value == range.parse::<i32>().unwrap(),
+        }
+    }
+
+    /// The function running a command stopped.
+    struct Returned;
+
+    /// The key of the entry at `path` in storage, where `frames[-1]` is the latest frame.
+    fn storage_key(scores: &HashMap<String, i32>, path: &str) -> String {
+        let latest = format!("{}[-1]", frame::FRAMES);
+        let frames = scores.get(frame::FRAMES).copied().unwrap_or(0);
+        path.replace(&latest, &format!("{}[{}]", frame::FRAMES, frames - 1))
+    }
+
+    /// Runs a single command, returning its result if it succeeded.
+    fn run_command(
+        datapack: &Datapack,
+        command: &str,
+        scores: &mut HashMap<String, i32>,
+    ) -> Result<Option<i32>, Returned> {
+        let words: Vec<&str> = command.split(' ').collect();
+        match words.as_slice() {
+            ["return", "run", rest @ ..] => {
+                run_command(datapack, &rest.join(" "), scores)?;
+                return Err(Returned);
+            }
+            ["return", _] => return Err(Returned),
+            ["scoreboard", "objectives", ..] => {}
+            ["scoreboard", "players", "set", holder, objective, value] => {
+                scores.insert(format!("{} {}", holder, objective), value.parse().unwrap());
+            }
+            ["scoreboard", "players", "get", holder, objective] => {
+                return Ok(Some(get(scores, format!("{} {}", holder, objective))));
+            }
+            ["scoreboard", "players", change @ ("add" | "remove"), holder, objective, value] => {
+                let value: i32 = value.parse().unwrap();
+                let score = scores
+                    .entry(format!("{} {}", holder, objective))
+                    .or_insert(0);
+                *score = if *change == "add" {
+                    score.wrapping_add(value)
+                } else {
+                    score.wrapping_sub(value)
+                };
+            }
+            ["scoreboard", "players", "operation", holder, objective, op, source, source_objective] =>
+            {
+                let value = get(scores, format!("{} {}", source, source_objective));
+                let score = scores
+                    .entry(format!("{} {}", holder, objective))
+                    .or_insert(0);
+                *score = match *op {
+                    "=" => value,
+                    "+=" => score.wrapping_add(value),
+                    "-=" => score.wrapping_sub(value),
+                    "*=" => score.wrapping_mul(value),
+                    "/=" if value == 0 => *score,
+                    "/=" => floor_div(*score, value),
+                    "%=" if value == 0 => *score,
+                    "%=" => score.wrapping_sub(floor_div(*score, value).wrapping_mul(value)),
+                    "<" => (*score).min(value),
+                    ">" => (*score).max(value),
+                    _ => panic!("unknown operation {}", op),
+                };
+            }
+            ["scoreboard", "players", "reset", holder, objective] => {
+                scores.remove(&format!("{} {}", holder, objective));
+            }
+            ["data", "modify", "storage", _, frame::FRAMES, "append", "value", "{}"] => {
+                *scores.entry(frame::FRAMES.to_string()).or_insert(0) += 1;
+            }
+            ["data", "remove", "storage", _, path] if path.starts_with(frame::FRAMES) => {
+                let latest = format!("{}.", storage_key(scores, path));
+                scores.retain(|key, _| !key.starts_with(&latest));
+                *scores.get_mut(frame::FRAMES).unwrap() -= 1;
+            }
+            ["data", "get", "storage", _, path] => {
+                return Ok(scores.get(&storage_key(scores, path)).copied());
+            }
+            ["function", function] => {
+                run(datapack, function.strip_prefix("test:").unwrap(), scores)
+            }
+            ["execute", subcommands @ ..] => {
+                let mut store_success = None;
+                let mut store_result = None;
+                let mut subcommands = subcommands;
+                let result = loop {
+                    match subcommands {
+                        ["store", "success", "score", holder, objective, rest @ ..] => {
+                            store_success = Some(format!("{} {}", holder, objective));
+                            subcommands = rest;
+                        }
+                        ["store", "result", "score", holder, objective, rest @ ..] => {
+                            store_result = Some(format!("{} {}", holder, objective));
+                            subcommands = rest;
+                        }
+                        ["store", "result", "storage", _, path, "int", "1", rest @ ..] => {
+                            store_result = Some(storage_key(scores, path));
+                            subcommands = rest;
+                        }
+                        [condition @ ("if" | "unless"), "data", "storage", _, path, rest @ ..] => {
+                            let holds = scores.contains_key(&storage_key(scores, path));
+                            if holds != (*condition == "if") {
+                                break None;
+                            }
+                            subcommands = rest;
+                        }
+                        [condition @ ("if" | "unless"), "score", holder, objective, "matches", range, rest @ ..] =>
+                        {
+                            let holds = scores
+                                .get(&format!("{} {}", holder, objective))
+                                .is_some_and(|value| in_range(range, *value));
+                            if holds != (*condition == "if") {
+                                break None;
+                            }
+                            subcommands = rest;
+                        }
+                        [condition @ ("if" | "unless"), "score", holder, objective, op, other, other_objective, rest @ ..] =>
+                        {
+                            let left = scores.get(&format!("{} {}", holder, objective));
+                            let right = scores.get(&format!("{} {}", other, other_objective));
+                            let holds = match (left, right, *op) {
+                                (Some(left), Some(right), "=") => left == right,
+                                (Some(left), Some(right), "<") => left < right,
+                                (Some(left), Some(right), "<=") => left <= right,
+                                (Some(left), Some(right), ">") => left > right,
+                                (Some(left), Some(right), ">=") => left >= right,
+                                (None, _, _) | (_, None, _) => false,
+                                _ => panic!("unknown comparison {}", op),
+                            };
+                            if holds != (*condition == "if") {
+                                break None;
+                            }
+                            subcommands = rest;
+                        }
+                        ["run", rest @ ..] => {
+                            break run_command(datapack, &rest.join(" "), scores)?
+                        }
+                        [] => break Some(1),
+                        _ => panic!("unsupported subcommands in {}", command),
+                    }
+                };
+                if let Some(store) = store_success {
+                    scores.insert(store, result.is_some() as i32);
+                }
+                if let (Some(store), Some(result)) = (store_result, result) {
+                    scores.insert(store, result);
+                }
+                return Ok(result);
+            }
+            _ => panic!("unsupported command {}", command),
+        }
+        Ok(Some(1))
+    }
+
+    #[test]
+    fn writing_removes_old_functions() -> eyre::Result<()> {
+        let root = env::temp_dir().join("stonescript-writing-removes-old-functions");
+        let function_dir = root.join("data").join("test").join("function");
+
+        lower("static function old(): void {}")??.write(&root)?;
+        assert!(function_dir.join("old.mcfunction").exists());
+
+        lower("static function new(): void {}")??.write(&root)?;
+        assert!(!function_dir.join("old.mcfunction").exists());
+        assert!(function_dir.join("new.mcfunction").exists());
+
+        fs::remove_dir_all(root)?;
+        Ok(())
+    }
+
+    #[test]
+    fn functions_and_statics() -> eyre::Result<()> {
+        let datapack = lower(
+            "static value: int = 3;
+            static function get(): int { return value * 2; }
+            static function set(): void { value = get(); }",
+        )??;
+
+        let paths: Vec<&str> = datapack.functions().map(|(path, _)| path).collect();
+        assert_eq!(paths, [INIT_FUNCTION, "get", "set"]);
+
+        let init = datapack.function(INIT_FUNCTION).unwrap();
+        assert_eq!(init[0], "scoreboard objectives add ss_global dummy");
+        assert_eq!(init[1], "scoreboard players set #const.2 ss_global 2");
+        assert!(init[2].starts_with("scoreboard players set ") && init[2].ends_with(" 3"));
+
+        let set = datapack.function("set").unwrap();
+        assert_eq!(set[0], "function test:get");
+        assert!(set[1].ends_with(&format!("= {}", Score::return_register())));
+        Ok(())
+    }
+
+    #[test]
+    fn arguments_are_copied_into_parameters() -> eyre::Result<()> {
+        let datapack = lower(
+            "static function add(a: int, b: int): int { return a + b; }
+            static function main(): void { add(1, add(2, 3)); }",
+        )??;
+
+        let main = datapack.function("main").unwrap();
+        assert_eq!(main.len(), 7);
+        assert_eq!(main[2], "function test:add");
+        assert_eq!(main[6], "function test:add");
+        assert_eq!(datapack.function("add").unwrap().len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn right_operand_reading_target() -> eyre::Result<()> {
+        let datapack = lower("static x: int = 1; static function main(): void { x = 10 / x; }")??;
+
+        let main = datapack.function("main").unwrap();
+        assert_eq!(main[0], "scoreboard players set #test.t0 ss_global 10");
+        assert!(main[1].starts_with("scoreboard players operation #test.t0 ss_global /= "));
+        assert!(main[2].ends_with("= #test.t0 ss_global"));
+        Ok(())
+    }
+
+    #[test]
+    fn entrypoints_are_tagged() -> eyre::Result<()> {
+        let datapack = lower(
+            "static function main(): void {}
+            static function tick(): void {}
+            static function other(): void {}",
+        )??;
+
+        assert_eq!(datapack.load_functions(), ["test:__init", "test:main"]);
+        assert_eq!(datapack.tick_functions(), ["test:tick"]);
+
+        assert!(matches!(
+            lower("static function tick(delta: int): void {}")?,
+            Err(BackendError::EntrypointArguments(name, _)) if name == "tick"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn errors_point_at_their_source() -> eyre::Result<()> {
+        let input = "static function f(): void {} static function f(a: int): void {}";
+        let Err(err) = lower(input)? else {
+            panic!("`f` is defined twice");
+        };
+        let primary = Diagnostic::from(err).primary.unwrap();
+        assert_eq!((primary.span.index, primary.span.width), (45, 1));
+
+        let input = "static function f(a: int): int { return a + 1; a = 2; return 1; }";
+        let Err(err) = lower_for(input, MinecraftVersion::new(19, 4))? else {
+            panic!("returning early needs Minecraft 1.20");
+        };
+        let primary = Diagnostic::from(err).primary.unwrap();
+        assert_eq!(&input[primary.span.index..][..primary.span.width], "a + 1");
+        assert_eq!(primary.message, "requires Minecraft 1.20");
+        Ok(())
+    }
+
+    #[test]
+    fn early_return_depends_on_version() -> eyre::Result<()> {
+        let input = "static function f(a: int): int { return a; a = 2; return 1; }";
+
+        let datapack = lower_for(input, MinecraftVersion::new(20, 4))??;
+        let f = datapack.function("f").unwrap();
+        assert!(f[0].starts_with("return run scoreboard players operation #return ss_global = "));
+
+        let datapack = lower_for(input, MinecraftVersion::new(20, 1))??;
+        let f = datapack.function("f").unwrap();
+        assert!(f[0].starts_with("scoreboard players operation #return ss_global = "));
+        assert_eq!(f[1], "return 0");
+
+        assert!(matches!(
+            lower_for(input, MinecraftVersion::new(19, 4))?,
+            Err(BackendError::UnsupportedFeature(Feature::Return, _, _))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_function() {
+        // Calls to missing functions are already rejected by the type checker.
+        let Err(error) = lower("static function main(): void { missing(1); }") else {
+            panic!("expected an error");
+        };
+        assert!(matches!(
+            error.downcast_ref::<TypeError>(),
+            Some(TypeError::UnknownFunction(name, _)) if name.to_string() == "missing"
+        ));
+    }
+}
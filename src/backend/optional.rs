@@ -0,0 +1,129 @@
+use super::{r#struct::Slot, BackendResult, Condition, Evaluate, FunctionBuilder, Score};
+use crate::mir::{MangledVar, MirExpression, MirExpressionKind};
+
+impl FunctionBuilder<'_> {
+    /// Copies an optional score, `target` is left unset if `source` is.
+    pub(super) fn copy_optional(&mut self, target: &Score, source: &Score) {
+        if target == source {
+            return;
+        }
+
+        self.push(format!("scoreboard players reset {}", target));
+        self.push(format!(
+            "execute if score {} = {} run scoreboard players operation {} = {}",
+            source, source, target, source
+        ));
+    }
+
+    /// Evaluates `value ?? fallback` into `target`, the fallback is only evaluated if the value
+    /// is unset.
+    pub(super) fn coalesce(
+        &mut self,
+        target: &Score,
+        value: MirExpression<MangledVar>,
+        fallback: MirExpression<MangledVar>,
+        layout: &[Slot],
+    ) -> BackendResult<()> {
+        // The value is written to the target before the fallback is evaluated.
+        if fallback.reads(target) || (fallback.has_side_effects() && !target.is_temporary()) {
+            let temporary = self.temporary();
+            self.coalesce(&temporary, value, fallback, layout)?;
+            self.copy_value(target, &temporary, layout);
+            return Ok(());
+        }
+
+        // Variables are checked in place, anything else is checked once it is in the target.
+        let checked = match value.kind {
+            MirExpressionKind::Variable(variable)
+                if !Score::variable(variable).overlaps(target) =>
+            {
+                let source = Score::variable(variable);
+                self.push(format!(
+                    "execute if score {} = {} run scoreboard players operation {} = {}",
+                    source, source, target, source
+                ));
+                source
+            }
+            kind => {
+                MirExpression::new(kind, value.span).evaluate_into(self, target)?;
+                target.clone()
+            }
+        };
+
+        let unset = Condition::check(format!("unless score {} = {}", checked, checked));
+        self.run_child(&unset, "unset", |builder| {
+            fallback.evaluate_into(builder, target)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::{
+        tests::{call, lower},
+        BackendError,
+    };
+
+    #[test]
+    fn fallbacks_replace_unset_values() -> eyre::Result<()> {
+        let datapack = lower(
+            "static x: int?;
+            static function get(value: int): int {
+                let before: int = x ?? -1;
+                x = value;
+                let after: int = x ?? -1;
+                x = null;
+                return before * 100 + after * 10 + (x ?? -1);
+            }",
+        )??;
+
+        assert_eq!(call(&datapack, "get", &[5]), -100 + 50 - 1);
+        Ok(())
+    }
+
+    #[test]
+    fn fallbacks_are_only_evaluated_when_needed() -> eyre::Result<()> {
+        let datapack = lower(
+            "static x: int?;
+            static calls: int = 0;
+            static function fallback(): int { calls += 1; return 7; }
+            static function get(value: int): int {
+                let first: int = x ?? fallback();
+                x = value;
+                let second: int = x ?? fallback();
+                return first * 100 + second * 10 + calls;
+            }",
+        )??;
+
+        assert_eq!(call(&datapack, "get", &[2]), 700 + 20 + 1);
+        Ok(())
+    }
+
+    #[test]
+    fn optional_copies_stay_unset() -> eyre::Result<()> {
+        let datapack = lower(
+            "static x: int?;
+            static y: int? = 3;
+            static function get(): int {
+                let before: int = y ?? 0;
+                y = x;
+                return before * 10 + (y ?? 0);
+            }",
+        )??;
+
+        assert_eq!(call(&datapack, "get", &[]), 30);
+        Ok(())
+    }
+
+    #[test]
+    fn optional_strings_are_reported_at_their_type() -> eyre::Result<()> {
+        let input = "static function f(): void { let s: string? = null; }";
+        let Err(BackendError::Unsupported(feature, span)) = lower(input)? else {
+            panic!("expected optional strings to be unsupported");
+        };
+
+        assert_eq!(feature, "optional `string`s");
+        assert_eq!(&input[span.index..span.index + span.width], "string?");
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+use super::Datapack;
+use serde_json::json;
+use std::{fs, io, path::Path};
+
+fn write_json(path: &Path, value: serde_json::Value) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut contents = serde_json::to_string_pretty(&value)?;
+    contents.push('\n');
+    fs::write(path, contents)
+}
+
+impl Datapack {
+    pub(super) fn write_pack_meta(&self, root: &Path) -> io::Result<()> {
+        write_json(
+            &root.join("pack.mcmeta"),
+            json!({
+                "pack": {
+                    "pack_format": self.version.pack_format(),
+                    "description": self.description,
+                }
+            }),
+        )
+    }
+
+    /// Writes the `minecraft:load` and `minecraft:tick` function tags.
+    pub(super) fn write_function_tags(&self, root: &Path) -> io::Result<()> {
+        let tag_dir = root
+            .join("data")
+            .join("minecraft")
+            .join("tags")
+            .join(self.version.function_directory());
+
+        for (tag, values) in [("load", &self.load), ("tick", &self.tick)] {
+            let path = tag_dir.join(format!("{}.json", tag));
+            if values.is_empty() {
+                if path.exists() {
+                    fs::remove_file(path)?;
+                }
+                continue;
+            }
+
+            write_json(&path, json!({ "values": values }))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn load_functions(&self) -> &[String] {
+        &self.load
+    }
+
+    pub fn tick_functions(&self) -> &[String] {
+        &self.tick
+    }
+}
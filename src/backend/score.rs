@@ -0,0 +1,101 @@
+use crate::mir::MangledVar;
+use std::fmt;
+
+/// Objective every variable, temporary and constant of a program is stored in.
+pub const OBJECTIVE: &str = "ss_global";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Score {
+    holder: String,
+    objective: &'static str,
+    temporary: bool,
+}
+
+impl Score {
+    fn new(holder: String) -> Self {
+        Self {
+            holder,
+            objective: OBJECTIVE,
+            temporary: false,
+        }
+    }
+
+    pub fn variable(variable: MangledVar) -> Self {
+        Self::new(variable.to_string())
+    }
+
+    pub fn constant(value: i32) -> Self {
+        Self::new(format!("#const.{}", value))
+    }
+
+    pub fn temporary(namespace: &str, index: usize) -> Self {
+        Self {
+            temporary: true,
+            ..Self::new(format!("#{}.t{}", namespace, index))
+        }
+    }
+
+    /// Holds the value of the last `return` until the caller copies it out.
+    pub fn return_register() -> Self {
+        Self::new("#return".to_string())
+    }
+
+    /// Set when a `return` inside a branch of `function` stops it, see
+    /// [`FunctionBuilder::return_flag`](super::FunctionBuilder::return_flag).
+    pub fn return_flag(namespace: &str, function: &str) -> Self {
+        Self::new(format!("#{}.{}.returned", namespace, function))
+    }
+
+    /// The score holding field `name` of the struct held by this score.
+    pub fn field(&self, name: &str) -> Self {
+        self.with_suffix(&format!(".{}", name))
+    }
+
+    /// The score holding the scalar a suffix from [`Datapack::layout`](super::Datapack::layout)
+    /// leads to in the value held by this score.
+    pub fn with_suffix(&self, suffix: &str) -> Self {
+        Self {
+            holder: format!("{}{}", self.holder, suffix),
+            ..self.clone()
+        }
+    }
+
+    /// Whether writing one of the scores can change the other, which is the case if either holds
+    /// a struct containing the other.
+    pub fn overlaps(&self, other: &Score) -> bool {
+        let contains = |outer: &Score, inner: &Score| {
+            inner
+                .holder
+                .strip_prefix(&outer.holder)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+        };
+        self.objective == other.objective && (contains(self, other) || contains(other, self))
+    }
+
+    /// Temporaries are only used by the expression they were created for, so nothing else can
+    /// read or write them in between.
+    pub fn is_temporary(&self) -> bool {
+        self.temporary
+    }
+
+    pub fn holder(&self) -> &str {
+        &self.holder
+    }
+
+    pub fn objective(&self) -> &str {
+        self.objective
+    }
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.holder, self.objective)
+    }
+}
+
+/// The right hand side of a scoreboard operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operand {
+    Constant(i32),
+    Score(Score),
+}
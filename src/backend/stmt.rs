@@ -0,0 +1,202 @@
+use super::{
+    r#struct::Slot, r#while::LoopExit, BackendError, BackendResult, Evaluate, Feature,
+    FunctionBuilder, Lower, Score,
+};
+use crate::{
+    hir::mir::MirPrimitive,
+    mir::{
+        MangledVar, MirAssignment, MirBinaryOp, MirDeclaration, MirExpression, MirExpressionKind,
+        MirStatement, MirType,
+    },
+    Span,
+};
+
+/// Ensures values of `ty`, needed by the code at `span`, can be stored in a single score.
+pub(super) fn check_score_type(ty: &MirType<MangledVar>, span: Span) -> BackendResult<()> {
+    match ty {
+        // `float`s and `double`s are fixed-point numbers.
+        MirType::Primitive(
+            MirPrimitive::Bool
+            | MirPrimitive::Byte
+            | MirPrimitive::Short
+            | MirPrimitive::Int
+            | MirPrimitive::Float
+            | MirPrimitive::Double,
+        ) => Ok(()),
+        MirType::Primitive(MirPrimitive::Void) => Err("void variables"),
+        MirType::Primitive(MirPrimitive::Long) => Err("long values"),
+        MirType::Primitive(MirPrimitive::String) => Err("string values"),
+        MirType::UserDefined(_) => Err("user defined types"),
+        MirType::Array(_, _) => Err("arrays of arrays"),
+        MirType::Optional(_) => Err("optional values"),
+    }
+    .map_err(|feature| BackendError::Unsupported(feature, span))
+}
+
+impl Lower for MirStatement<MangledVar> {
+    fn lower(self, builder: &mut FunctionBuilder) -> BackendResult<()> {
+        match self {
+            Self::Block(block) | Self::Unsafe(block) => block.lower(builder),
+            Self::Expression(expr) => expr.evaluate_discarded(builder),
+            Self::Return(value) => {
+                builder.require(Feature::Return, value.span)?;
+                let length = builder.commands.len();
+                value.evaluate_into(builder, &Score::return_register())?;
+
+                // Returning from a branch has to stop the source function as well.
+                if builder.is_child() {
+                    builder.returns_early = true;
+                    let flag = builder.return_flag();
+                    builder.push(format!("scoreboard players set {} 1", flag));
+                }
+
+                // Structs without fields take no commands to return.
+                if builder.supports(Feature::ReturnRun) && builder.commands.len() > length {
+                    let last = builder.commands.pop().expect("evaluation emits a command");
+                    builder.push(format!("return run {}", last));
+                } else {
+                    builder.push("return 0");
+                }
+                Ok(())
+            }
+            Self::Assignment(assign) => assign.lower(builder),
+            Self::Declaration(decl) => decl.lower(builder),
+            Self::Function(func) => func.lower(builder),
+            Self::Struct(_) | Self::Enum(_) => Ok(()),
+            Self::If(if_block) => if_block.lower(builder),
+            Self::Match(match_stmt) => match_stmt.lower(builder),
+            Self::While(while_loop) => while_loop.lower(builder),
+            Self::For(for_loop) => for_loop.lower(builder),
+            Self::Break(label, span) => builder.exit_loop(label, LoopExit::Break, span),
+            Self::Continue(label, span) => builder.exit_loop(label, LoopExit::Continue, span),
+            Self::Import(_) => Ok(()),
+        }
+    }
+}
+
+impl Lower for MirDeclaration<MangledVar> {
+    fn lower(self, builder: &mut FunctionBuilder) -> BackendResult<()> {
+        let layout = builder.datapack.layout(&self.ty, self.ty_span)?;
+
+        let score = Score::variable(self.name);
+        if let Some(value) = self.value {
+            value.evaluate_into(builder, &score)
+        } else {
+            // Makes sure the scores exist without overwriting previous values, payloads of every
+            // variant included.
+            for slot in layout {
+                let holder = score.with_suffix(slot.suffix());
+                match slot.unguarded() {
+                    Slot::Score(_) => builder.push(format!("scoreboard players add {} 0", holder)),
+                    // Optional values start out unset.
+                    Slot::Optional(_) => (),
+                    Slot::Stored(_, default) => builder.declare_stored(&holder, default),
+                    Slot::Payload(_, _, _) => unreachable!("`unguarded` strips payloads"),
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+impl FunctionBuilder<'_> {
+    /// Lowers `target op= value`. Single scores are updated in place, which takes one command
+    /// for constants, other values are assigned `target op value`.
+    fn compound_assign(
+        &mut self,
+        target: MirExpression<MangledVar>,
+        op: MirBinaryOp,
+        value: MirExpression<MangledVar>,
+    ) -> BackendResult<()> {
+        // The value is evaluated after the target is read, so it must not change it.
+        let in_place = !matches!(target.kind, MirExpressionKind::Index(_, _))
+            && !self.is_long(&target)?
+            && !self.is_string(&target)?
+            && !value.has_side_effects();
+        if !in_place {
+            if target.has_side_effects() {
+                return Err(BackendError::Unsupported(
+                    "compound assignments to elements at computed indices",
+                    target.span,
+                ));
+            }
+
+            let span = Span::from_start_end(target.span, value.span);
+            let value = MirExpression::new(
+                MirExpressionKind::BinaryOp(Box::new(target.clone()), op, Box::new(value)),
+                span,
+            );
+            return MirAssignment {
+                target,
+                op: None,
+                value,
+            }
+            .lower(self);
+        }
+
+        let fixed = self.fixed_point_of(&target)?;
+        let primitive = self.primitive_of(&target)?;
+        let target = self.place(target)?;
+        let operand = value.evaluate(self)?;
+        self.arithmetic(&target, op, operand, fixed, primitive);
+        Ok(())
+    }
+}
+
+impl Lower for MirAssignment<MangledVar> {
+    fn lower(self, builder: &mut FunctionBuilder) -> BackendResult<()> {
+        if let Some(op) = self.op {
+            return builder.compound_assign(self.target, op, self.value);
+        }
+
+        if let MirExpressionKind::Index(array, index) = self.target.kind {
+            return builder.write_element(*array, *index, self.value);
+        }
+
+        let target = builder.place(self.target)?;
+        self.value.evaluate_into(builder, &target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::tests::{call, lower};
+
+    #[test]
+    fn compound_assignments_update_in_place() -> eyre::Result<()> {
+        let datapack = lower(
+            "static function f(a: int, b: int): int { a += 5; a -= 3; a *= b; a %= 7; return a; }
+            static function g(a: byte, b: long, c: float): long {
+                a += 100;
+                b *= a;
+                c /= 2.0;
+                b -= c as long;
+                return b;
+            }",
+        )??;
+
+        let f = datapack.function("f").unwrap();
+        let a = f[0].split(' ').nth(3).unwrap();
+        assert_eq!(
+            f[..4],
+            [
+                format!("scoreboard players add {} ss_global 5", a),
+                format!("scoreboard players remove {} ss_global 3", a),
+                f[2].clone(),
+                format!(
+                    "scoreboard players operation {} ss_global %= #const.7 ss_global",
+                    a
+                ),
+            ]
+        );
+        assert!(f[2].contains(" *= "));
+        assert_eq!(call(&datapack, "f", &[4, 3]), 4);
+
+        // Bytes wrap after the update, longs are assigned `b * a` as a whole.
+        assert_eq!(
+            call(&datapack, "g", &[50, 1 << 33, -350]),
+            (1i64 << 33) * -106 + 1
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,62 @@
+use super::{Datapack, FunctionBuilder, Score};
+
+/// Storage every array and string of a program is stored in, under the namespace of the datapack.
+const STORAGE: &str = "ss_global";
+
+/// Escapes `text` so it can be put between double quotes in NBT.
+pub(super) fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+impl Datapack {
+    /// The resource location of the storage.
+    pub(super) fn storage_id(&self) -> String {
+        format!("{}:{}", self.namespace, STORAGE)
+    }
+
+    pub(super) fn storage(&self) -> String {
+        format!("storage {}", self.storage_id())
+    }
+
+    /// The entry holding the value held by `holder`. Arrays and strings are held like any other
+    /// value, but they are kept in storage under the name of the score instead of in it.
+    pub(super) fn stored(&self, holder: &Score) -> String {
+        format!("{} \"{}\"", self.storage(), holder.holder())
+    }
+
+    /// The compound function macros read their arguments from.
+    pub(super) fn macro_arguments(&self) -> String {
+        format!("{} macro", self.storage())
+    }
+}
+
+impl FunctionBuilder<'_> {
+    /// Copies the stored value held by `source` to `target`.
+    pub(super) fn copy_stored(&mut self, target: &Score, source: &Score) {
+        if target != source {
+            let (target, source) = (self.datapack.stored(target), self.datapack.stored(source));
+            self.push(format!("data modify {} set from {}", target, source));
+        }
+    }
+
+    /// Stores `default` for `holder`, unless it already holds a value.
+    pub(super) fn declare_stored(&mut self, holder: &Score, default: &str) {
+        let stored = self.datapack.stored(holder);
+        self.push(format!(
+            "execute unless data {} run data modify {} set value {}",
+            stored, stored, default
+        ));
+    }
+
+    /// Runs `line` as a function macro in a child function of `kind`, with the arguments written
+    /// to [`Datapack::macro_arguments`].
+    pub(super) fn run_macro(&mut self, kind: &str, line: &str) {
+        let arguments = self.datapack.macro_arguments();
+        let mut function = self.child(kind);
+        function.push(format!("${}", line));
+        let location = function.location();
+        function.finish();
+
+        self.push(format!("function {} with {}", location, arguments));
+    }
+}
@@ -0,0 +1,456 @@
+use super::{
+    storage::escape, BackendError, BackendResult, Evaluate, Feature, FixedPoint, FunctionBuilder,
+    Operand, Score,
+};
+use crate::{
+    hir::mir::MirPrimitive,
+    mir::{CommandPart, MangledVar, MirBinaryOp, MirExpression, MirExpressionKind, MirType},
+    token::LiteralType,
+    Span,
+};
+use serde_json::{json, Value};
+
+/// An evaluated part of a string concatenation, command or `tellraw` message.
+enum Piece {
+    /// Literal text.
+    Text(String),
+    /// A string kept in storage for the holder.
+    Stored(Score),
+    Scalar(Operand),
+    /// A `float` or `double` held by the score.
+    Fixed(Score, FixedPoint),
+}
+
+impl FunctionBuilder<'_> {
+    /// Whether `expr` evaluates to a string.
+    pub(super) fn is_string(&self, expr: &MirExpression<MangledVar>) -> BackendResult<bool> {
+        Ok(self.value_type(expr)? == Some(MirType::Primitive(MirPrimitive::String)))
+    }
+
+    /// Adds the strings `expr` concatenates to `parts`, with other values as they are.
+    fn flatten_concat(
+        &self,
+        expr: MirExpression<MangledVar>,
+        parts: &mut Vec<MirExpression<MangledVar>>,
+    ) -> BackendResult<()> {
+        let is_string = self.is_string(&expr)?;
+        match expr.kind {
+            MirExpressionKind::BinaryOp(left, MirBinaryOp::Add, right) if is_string => {
+                self.flatten_concat(*left, parts)?;
+                self.flatten_concat(*right, parts)
+            }
+            // Numbers are formatted like any other value in a concatenation.
+            MirExpressionKind::Cast(value, MirPrimitive::String) => {
+                self.flatten_concat(*value, parts)
+            }
+            kind => {
+                parts.push(MirExpression::new(kind, expr.span));
+                Ok(())
+            }
+        }
+    }
+
+    /// Evaluates `exprs` in order. Values of variables a later expression could change are
+    /// copied to a temporary first.
+    fn evaluate_pieces(
+        &mut self,
+        exprs: Vec<MirExpression<MangledVar>>,
+    ) -> BackendResult<Vec<Piece>> {
+        let side_effects: Vec<bool> = exprs.iter().map(|expr| expr.has_side_effects()).collect();
+
+        let mut pieces = vec![];
+        for (index, expr) in exprs.into_iter().enumerate() {
+            let changes_later = side_effects[index + 1..].iter().any(|effects| *effects);
+            let is_string = self.is_string(&expr)?;
+            let fixed = self.fixed_point_of(&expr)?;
+            let piece = match expr.kind {
+                MirExpressionKind::Literal(LiteralType::String(text)) => Piece::Text(text),
+                MirExpressionKind::Literal(LiteralType::Long(value)) => {
+                    Piece::Text(value.to_string())
+                }
+                MirExpressionKind::Literal(literal) => {
+                    let value = self.literal_value(literal, expr.span)?;
+                    match fixed {
+                        Some(fixed) => Piece::Text(fixed.format(value)),
+                        None => Piece::Scalar(Operand::Constant(value)),
+                    }
+                }
+                kind => {
+                    let expr = MirExpression::new(kind, expr.span);
+                    match self.value_type(&expr)? {
+                        Some(MirType::UserDefined(_) | MirType::Array(_, _)) => {
+                            return Err(BackendError::Unsupported(
+                                "structs, enums and arrays in text",
+                                expr.span,
+                            ))
+                        }
+                        Some(MirType::Primitive(MirPrimitive::Long)) => {
+                            return Err(BackendError::Unsupported("long values in text", expr.span))
+                        }
+                        _ => (),
+                    }
+
+                    let score = if changes_later {
+                        let temporary = self.temporary();
+                        expr.evaluate_into(self, &temporary)?;
+                        temporary
+                    } else {
+                        self.value_score(expr)?
+                    };
+
+                    match fixed {
+                        Some(fixed) => Piece::Fixed(score, fixed),
+                        None if is_string => Piece::Stored(score),
+                        None => Piece::Scalar(Operand::Score(score)),
+                    }
+                }
+            };
+            pieces.push(piece);
+        }
+        Ok(pieces)
+    }
+
+    /// The end of a `data modify … set` command copying the string `piece`.
+    fn string_source(&self, piece: &Piece) -> String {
+        match piece {
+            Piece::Text(text) => format!("value \"{}\"", escape(text)),
+            Piece::Stored(holder) => format!("from {}", self.datapack.stored(holder)),
+            Piece::Scalar(_) | Piece::Fixed(_, _) => {
+                unreachable!("type checking only allows strings here")
+            }
+        }
+    }
+
+    /// Writes the value of `piece` to the macro argument `name`, or returns it if it is known
+    /// while compiling.
+    fn macro_argument(&mut self, name: &str, piece: Piece) -> Option<String> {
+        let argument = format!("{}.{}", self.datapack.macro_arguments(), name);
+        match piece {
+            Piece::Text(text) => return Some(text),
+            Piece::Scalar(Operand::Constant(value)) => return Some(value.to_string()),
+            Piece::Stored(holder) => {
+                let source = self.string_source(&Piece::Stored(holder));
+                self.push(format!("data modify {} set {}", argument, source));
+            }
+            Piece::Scalar(Operand::Score(score)) => self.push(format!(
+                "execute store result {} int 1 run scoreboard players get {}",
+                argument, score
+            )),
+            // Macros insert numbers without their type suffix, like `1.5` for `1.5d`.
+            Piece::Fixed(score, fixed) => self.push(format!(
+                "execute store result {} double {} run scoreboard players get {}",
+                argument,
+                1.0 / fixed.scale() as f64,
+                score
+            )),
+        }
+        None
+    }
+
+    /// Compares the strings `left` and `right`, returning a score which is 1 if they differ and
+    /// 0 otherwise.
+    pub(super) fn compare_strings(
+        &mut self,
+        left: MirExpression<MangledVar>,
+        right: MirExpression<MangledVar>,
+    ) -> BackendResult<Score> {
+        let pieces = self.evaluate_pieces(vec![left, right])?;
+        let (left, right) = (
+            self.string_source(&pieces[0]),
+            self.string_source(&pieces[1]),
+        );
+
+        // Overwriting a copy of one side with the other only succeeds if that changes it.
+        let compared = format!("{} compare", self.datapack.storage());
+        let differs = self.temporary();
+        self.push(format!("data modify {} set {}", compared, left));
+        self.push(format!(
+            "execute store success score {} run data modify {} set {}",
+            differs, compared, right
+        ));
+        Ok(differs)
+    }
+
+    /// Stores the result of comparing the strings `left` and `right` with `op` in `target`.
+    pub(super) fn string_comparison(
+        &mut self,
+        target: &Score,
+        left: MirExpression<MangledVar>,
+        op: MirBinaryOp,
+        right: MirExpression<MangledVar>,
+    ) -> BackendResult<()> {
+        let differs = self.compare_strings(left, right)?;
+        if op == MirBinaryOp::Equals {
+            self.push(format!(
+                "execute store success score {} if score {} matches 0",
+                target, differs
+            ));
+        } else {
+            self.copy(target, &differs);
+        }
+        Ok(())
+    }
+
+    /// Stores the concatenation `expr` in `target`. Strings only known at runtime are inserted
+    /// by a function macro.
+    pub(super) fn concat(
+        &mut self,
+        target: &Score,
+        expr: MirExpression<MangledVar>,
+    ) -> BackendResult<()> {
+        let mut parts = vec![];
+        self.flatten_concat(expr, &mut parts)?;
+        if let Some(part) = parts.iter().find(|part| !is_literal(part)) {
+            self.require(Feature::FunctionMacros, part.span)?;
+        }
+
+        let mut template = String::new();
+        let mut arguments = 0;
+        for piece in self.evaluate_pieces(parts)? {
+            let name = format!("p{}", arguments);
+            match self.macro_argument(&name, piece) {
+                Some(text) => template.push_str(&escape(&text)),
+                None => {
+                    template.push_str(&format!("$({})", name));
+                    arguments += 1;
+                }
+            }
+        }
+
+        let command = format!(
+            "data modify {} set value \"{}\"",
+            self.datapack.stored(target),
+            template
+        );
+        if arguments == 0 {
+            self.push(command);
+        } else {
+            self.run_macro("concat", &command);
+        }
+        Ok(())
+    }
+
+    /// Runs the command made of `parts` wrapped by `wrap`. Values only known at runtime are
+    /// inserted by a function macro.
+    pub(super) fn run_command(
+        &mut self,
+        parts: Vec<CommandPart<MangledVar>>,
+        wrap: impl Fn(&str) -> String,
+    ) -> BackendResult<()> {
+        let mut texts = vec![];
+        let mut values = vec![];
+        for part in parts {
+            match part {
+                CommandPart::Text(text) => texts.push(Some(text)),
+                CommandPart::Value(value) => {
+                    texts.push(None);
+                    values.push(value);
+                }
+            }
+        }
+
+        if let Some(value) = values.iter().find(|value| !is_literal(value)) {
+            self.require(Feature::FunctionMacros, value.span)?;
+        }
+        let mut pieces = self.evaluate_pieces(values)?.into_iter();
+
+        let mut command = String::new();
+        let mut arguments = 0;
+        for text in texts {
+            let text = match text {
+                Some(text) => text,
+                None => {
+                    let piece = pieces.next().expect("every value is evaluated");
+                    let name = format!("p{}", arguments);
+                    match self.macro_argument(&name, piece) {
+                        Some(text) => text,
+                        None => {
+                            arguments += 1;
+                            format!("$({})", name)
+                        }
+                    }
+                }
+            };
+            command.push_str(&text);
+        }
+
+        if arguments == 0 {
+            self.push(wrap(&command));
+        } else {
+            self.run_macro("command", &wrap(&command));
+        }
+        Ok(())
+    }
+
+    /// Shows the text made of `parts` to `targets`, as a JSON text component. Fixed-point
+    /// numbers are inserted by a function macro, as scores only show the scaled value. `span`
+    /// is the span of the `$tellraw`.
+    pub(super) fn tellraw(
+        &mut self,
+        targets: &str,
+        parts: Vec<MirExpression<MangledVar>>,
+        span: Span,
+    ) -> BackendResult<()> {
+        let mut flattened = vec![];
+        for part in parts {
+            self.flatten_concat(part, &mut flattened)?;
+        }
+
+        let storage = self.datapack.storage_id();
+        let mut components = vec![json!("")];
+        let mut arguments = 0;
+        for piece in self.evaluate_pieces(flattened)? {
+            components.push(match piece {
+                Piece::Text(text) => Value::String(text),
+                Piece::Stored(holder) => json!({
+                    "storage": storage,
+                    "nbt": format!("\"{}\"", escape(holder.holder())),
+                }),
+                Piece::Scalar(Operand::Constant(value)) => Value::String(value.to_string()),
+                Piece::Scalar(Operand::Score(score)) => json!({
+                    "score": { "name": score.holder(), "objective": score.objective() },
+                }),
+                Piece::Fixed(score, fixed) => {
+                    self.require(Feature::FunctionMacros, span)?;
+                    let name = format!("p{}", arguments);
+                    arguments += 1;
+                    self.macro_argument(&name, Piece::Fixed(score, fixed));
+                    Value::String(format!("$({})", name))
+                }
+            });
+        }
+
+        let command = format!("tellraw {} {}", targets, Value::Array(components));
+        if arguments == 0 {
+            self.push(command);
+        } else {
+            self.run_macro("tellraw", &command);
+        }
+        Ok(())
+    }
+}
+
+fn is_literal(expr: &MirExpression<MangledVar>) -> bool {
+    matches!(expr.kind, MirExpressionKind::Literal(_))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::{
+        tests::{lower, lower_for},
+        BackendError, Feature, MinecraftVersion,
+    };
+
+    #[test]
+    fn strings_are_stored() -> eyre::Result<()> {
+        let datapack = lower(
+            "static function f(a: string): bool {
+                let b: string = \"say \\\"hi\\\"\";
+                return a == b;
+            }
+            static function main(): void { let same: bool = f(\"x\") != true; }",
+        )??;
+
+        let f = datapack.function("f").unwrap();
+        assert!(f[0].starts_with("data modify storage test:ss_global \""));
+        assert!(f[0].ends_with("\" set value \"say \\\"hi\\\"\""));
+        assert!(f[1].starts_with("data modify storage test:ss_global compare set from "));
+        assert!(f[2].starts_with(
+            "execute store success score #test.t0 ss_global run data modify storage test:ss_global compare set from "
+        ));
+        assert_eq!(
+            f[3],
+            "execute store success score #return ss_global if score #test.t0 ss_global matches 0"
+        );
+
+        let main = datapack.function("main").unwrap();
+        assert!(main[0].ends_with("set value \"x\""));
+        Ok(())
+    }
+
+    #[test]
+    fn concatenation_uses_macros() -> eyre::Result<()> {
+        let datapack = lower(
+            "static function f(name: string): string { return \"Hi \" + name + \"!\"; }
+            static function g(): string { return \"a\" + \"b\"; }",
+        )??;
+
+        let f = datapack.function("f").unwrap();
+        assert!(f[0].starts_with("data modify storage test:ss_global macro.p0 set from "));
+        assert_eq!(
+            f[1],
+            "function test:f/concat_0 with storage test:ss_global macro"
+        );
+        assert_eq!(
+            datapack.function("f/concat_0").unwrap(),
+            ["$data modify storage test:ss_global \"#return\" set value \"Hi $(p0)!\""]
+        );
+
+        assert_eq!(
+            datapack.function("g").unwrap(),
+            ["data modify storage test:ss_global \"#return\" set value \"ab\""]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn commands_interpolate_values() -> eyre::Result<()> {
+        let source = "static function f(name: string, count: int): void {
+            $command(\"give ${name} stone ${count}\");
+            $command(\"say {not a value} {name}\");
+        }";
+        let datapack = lower(source)??;
+
+        let f = datapack.function("f").unwrap();
+        assert!(f[0].starts_with("data modify storage test:ss_global macro.p0 set from "));
+        assert!(f[1].starts_with("execute store result storage test:ss_global macro.p1 int 1 run"));
+        assert_eq!(
+            f[2],
+            "function test:f/command_0 with storage test:ss_global macro"
+        );
+        assert_eq!(f[3], "say {not a value} {name}");
+        assert_eq!(
+            datapack.function("f/command_0").unwrap(),
+            ["$give $(p0) stone $(p1)"]
+        );
+
+        let err = lower_for(source, MinecraftVersion::new(20, 1))?;
+        assert!(matches!(
+            err,
+            Err(BackendError::UnsupportedFeature(
+                Feature::FunctionMacros,
+                _,
+                _
+            ))
+        ));
+        assert_eq!(
+            err.err().unwrap().to_string(),
+            "Using function macros requires Minecraft 1.20.2 or newer, but the target is \
+             Minecraft 1.20.1"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn tellraw_builds_json_text() -> eyre::Result<()> {
+        let datapack = lower(
+            "static function f(name: string, count: int): void {
+                $tellraw(\"@a\", \"Hi \" + name, \": \", count, 2);
+            }",
+        )??;
+
+        let f = datapack.function("f").unwrap();
+        assert_eq!(f.len(), 1);
+        let (command, json) = f[0].split_at(f[0].find('[').unwrap());
+        assert_eq!(command, "tellraw @a ");
+
+        let json: serde_json::Value = serde_json::from_str(json)?;
+        assert_eq!(json[1], "Hi ");
+        assert_eq!(json[2]["storage"], "test:ss_global");
+        assert!(json[2]["nbt"].as_str().unwrap().starts_with('"'));
+        assert_eq!(json[3], ": ");
+        assert_eq!(json[4]["score"]["objective"], "ss_global");
+        assert_eq!(json[5], "2");
+        Ok(())
+    }
+}
@@ -0,0 +1,600 @@
+use super::{
+    display_path, stmt::check_score_type, BackendError, BackendResult, Condition, Datapack,
+    Evaluate, Feature, FunctionBuilder, Score,
+};
+use crate::{
+    hir::mir::MirPrimitive,
+    mir::{
+        CommandPart, MangledVar, MirArmBody, MirBinaryOp, MirElseBlock, MirExpression,
+        MirExpressionKind, MirIf, MirStatement, MirType, MirUnaryOp,
+    },
+    token::{LiteralType, XID},
+    Span,
+};
+use std::slice;
+
+type Type = MirType<MangledVar>;
+
+/// Part of the layout of a value, see [`Datapack::layout`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) enum Slot {
+    /// A score, named by its suffix to the holder of the value.
+    Score(String),
+    /// A score which does not exist while the value is unset.
+    Optional(String),
+    /// An entry in storage named like the score with the suffix would be, and the NBT it starts
+    /// out as.
+    Stored(String, String),
+    /// Part of the payload of an enum variant, which only holds a value while the discriminant
+    /// in the score with the suffix is the one of the variant.
+    Payload(String, i32, Box<Slot>),
+}
+
+impl Slot {
+    pub(super) fn suffix(&self) -> &str {
+        match self {
+            Self::Score(suffix) | Self::Optional(suffix) | Self::Stored(suffix, _) => suffix,
+            Self::Payload(_, _, slot) => slot.suffix(),
+        }
+    }
+
+    /// The slot without the variants it is part of the payload of.
+    pub(super) fn unguarded(&self) -> &Self {
+        match self {
+            Self::Payload(_, _, slot) => slot.unguarded(),
+            slot => slot,
+        }
+    }
+
+    fn with_prefix(self, prefix: &str) -> Self {
+        match self {
+            Self::Score(suffix) => Self::Score(format!("{}{}", prefix, suffix)),
+            Self::Optional(suffix) => Self::Optional(format!("{}{}", prefix, suffix)),
+            Self::Stored(suffix, default) => Self::Stored(format!("{}{}", prefix, suffix), default),
+            Self::Payload(suffix, discriminant, slot) => Self::Payload(
+                format!("{}{}", prefix, suffix),
+                discriminant,
+                Box::new(slot.with_prefix(prefix)),
+            ),
+        }
+    }
+}
+
+impl Datapack {
+    /// Records the type of every variable in `statements`, struct values are copied field by
+    /// field so their layout has to be known wherever they are used. Also records the local
+    /// variables of every function and the functions it calls, for [`Datapack::write_frames`].
+    pub(super) fn collect_variables(&mut self, statements: &[MirStatement<MangledVar>]) {
+        for statement in statements {
+            match statement {
+                MirStatement::Declaration(decl) => {
+                    if decl.is_static {
+                        self.variables.insert(decl.name, decl.ty.clone());
+                    } else {
+                        self.collect_local(decl.name, decl.ty.clone());
+                    }
+                    if let Some(value) = &decl.value {
+                        self.collect_expression_variables(value);
+                    }
+                }
+                MirStatement::Expression(expr) | MirStatement::Return(expr) => {
+                    self.collect_expression_variables(expr)
+                }
+                MirStatement::Assignment(assign) => {
+                    self.collect_expression_variables(&assign.value)
+                }
+                MirStatement::Match(match_stmt) => self.collect_match_variables(match_stmt),
+                MirStatement::Function(func) => {
+                    let outer = self.collecting.replace(func.name.inner().to_string());
+                    for (arg, ty) in &func.args {
+                        self.collect_local(*arg, ty.clone());
+                    }
+                    self.collect_variables(&func.block);
+                    self.collecting = outer;
+                }
+                MirStatement::Block(block) | MirStatement::Unsafe(block) => {
+                    self.collect_variables(block)
+                }
+                MirStatement::If(if_block) => self.collect_if_variables(if_block),
+                MirStatement::While(while_loop) => self.collect_variables(&while_loop.block),
+                MirStatement::For(for_loop) => {
+                    self.collect_local(for_loop.init.name, for_loop.init.ty.clone());
+                    self.collect_variables(&for_loop.block);
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Collects the variables bound by `match` expressions in `expr`.
+    pub(super) fn collect_expression_variables(&mut self, expr: &MirExpression<MangledVar>) {
+        match &expr.kind {
+            MirExpressionKind::Match(match_expr) => self.collect_match_variables(match_expr),
+            MirExpressionKind::Call(path, args) => {
+                let (namespace, callee) = self.split_path(path);
+                if let Some(caller) = &self.collecting {
+                    if namespace == self.namespace {
+                        self.calls.entry(caller.clone()).or_default().insert(callee);
+                    }
+                }
+                for arg in args {
+                    self.collect_expression_variables(arg);
+                }
+            }
+            MirExpressionKind::Variant(_, _, args)
+            | MirExpressionKind::Array(args)
+            | MirExpressionKind::Tellraw(_, args) => {
+                for arg in args {
+                    self.collect_expression_variables(arg);
+                }
+            }
+            MirExpressionKind::Command(parts) => {
+                for part in parts {
+                    if let CommandPart::Value(value) = part {
+                        self.collect_expression_variables(value);
+                    }
+                }
+            }
+            MirExpressionKind::Struct(_, fields) => {
+                for (_, value) in fields {
+                    self.collect_expression_variables(value);
+                }
+            }
+            MirExpressionKind::Property(expr, _)
+            | MirExpressionKind::UnaryOp(_, expr)
+            | MirExpressionKind::Cast(expr, _) => self.collect_expression_variables(expr),
+            MirExpressionKind::Index(left, right) | MirExpressionKind::BinaryOp(left, _, right) => {
+                self.collect_expression_variables(left);
+                self.collect_expression_variables(right);
+            }
+            MirExpressionKind::Ternary(condition, then, otherwise) => {
+                self.collect_expression_variables(condition);
+                self.collect_expression_variables(then);
+                self.collect_expression_variables(otherwise);
+            }
+            MirExpressionKind::Literal(_)
+            | MirExpressionKind::Null
+            | MirExpressionKind::Variable(_)
+            | MirExpressionKind::Nbt(_) => (),
+        }
+    }
+
+    /// Records a variable which belongs to the function being collected, if there is one.
+    pub(super) fn collect_local(&mut self, variable: MangledVar, ty: Type) {
+        if let Some(function) = &self.collecting {
+            self.locals
+                .entry(function.clone())
+                .or_default()
+                .push(variable);
+        }
+        self.variables.insert(variable, ty);
+    }
+
+    fn collect_if_variables(&mut self, if_block: &MirIf<MangledVar>) {
+        self.collect_variables(&if_block.block);
+        match &if_block.else_block {
+            Some(MirElseBlock::ElseIf(else_if)) => self.collect_if_variables(else_if),
+            Some(MirElseBlock::Else(block)) => self.collect_variables(block),
+            None => (),
+        }
+    }
+
+    fn struct_fields(&self, ty: &Type, span: Span) -> BackendResult<Option<&[(XID, Type)]>> {
+        match ty {
+            MirType::UserDefined(path) => {
+                let name = display_path(path);
+                match self.structs.get(&name) {
+                    Some(fields) => Ok(Some(fields)),
+                    None if self.enums.contains_key(&name) => Ok(None),
+                    None => Err(BackendError::UnknownType(name, span)),
+                }
+            }
+            MirType::Primitive(_) | MirType::Array(_, _) | MirType::Optional(_) => Ok(None),
+        }
+    }
+
+    /// Lists the scores a value of type `ty` is stored in, as suffixes to the holder of the
+    /// score the value is stored in. Scalars only need the score itself, structs are flattened
+    /// so field `x` of a struct held by `holder` is held by `holder.x`. Enums keep their
+    /// discriminant in the score itself and value `i` of variant `V` in `holder.V.i`, which only
+    /// holds a value while the enum holds `V`. Arrays and
+    /// strings are kept in storage instead, under the name of the score they would be held by.
+    /// Types which cannot be stored are reported at `span`.
+    pub(super) fn layout(&self, ty: &Type, span: Span) -> BackendResult<Vec<Slot>> {
+        self.layout_inner(ty, span, &mut vec![])
+    }
+
+    fn layout_inner<'a>(
+        &'a self,
+        ty: &'a Type,
+        span: Span,
+        parents: &mut Vec<&'a Type>,
+    ) -> BackendResult<Vec<Slot>> {
+        if let MirType::Primitive(MirPrimitive::String) = ty {
+            self.require(Feature::Storage, span)?;
+            return Ok(vec![Slot::Stored(String::new(), "\"\"".to_string())]);
+        }
+
+        if let MirType::Array(element, len) = ty {
+            self.require(Feature::Storage, span)?;
+            return match **element {
+                MirType::Primitive(MirPrimitive::String) => {
+                    Err(BackendError::Unsupported("arrays of strings", span))
+                }
+                MirType::Primitive(MirPrimitive::Long) => {
+                    Err(BackendError::Unsupported("arrays of longs", span))
+                }
+                MirType::Primitive(_) => {
+                    check_score_type(element, span)?;
+                    let zeros = vec!["0"; *len as usize].join(", ");
+                    Ok(vec![Slot::Stored(String::new(), format!("[{}]", zeros))])
+                }
+                MirType::UserDefined(_) => Err(BackendError::Unsupported(
+                    "arrays of structs and enums",
+                    span,
+                )),
+                MirType::Array(_, _) => Err(BackendError::Unsupported("arrays of arrays", span)),
+                MirType::Optional(_) => {
+                    Err(BackendError::Unsupported("arrays of optional values", span))
+                }
+            };
+        }
+
+        // Unset values have no score at all.
+        if let MirType::Optional(inner) = ty {
+            return match **inner {
+                MirType::Primitive(MirPrimitive::String) => {
+                    Err(BackendError::Unsupported("optional `string`s", span))
+                }
+                MirType::Primitive(MirPrimitive::Long) => {
+                    Err(BackendError::Unsupported("optional `long`s", span))
+                }
+                MirType::Primitive(_) => {
+                    check_score_type(inner, span)?;
+                    Ok(vec![Slot::Optional(String::new())])
+                }
+                _ => Err(BackendError::Unsupported(
+                    "optional values other than numbers and booleans",
+                    span,
+                )),
+            };
+        }
+
+        // The high and low 32 bits of a `long`.
+        if let MirType::Primitive(MirPrimitive::Long) = ty {
+            return Ok(vec![
+                Slot::Score(".hi".to_string()),
+                Slot::Score(".lo".to_string()),
+            ]);
+        }
+
+        let MirType::UserDefined(path) = ty else {
+            check_score_type(ty, span)?;
+            return Ok(vec![Slot::Score(String::new())]);
+        };
+
+        let name = display_path(path);
+        // Members of variants are paired with their discriminant.
+        let (mut layout, members): (_, Vec<(String, Option<i32>, &Type)>) =
+            match (self.structs.get(&name), self.enums.get(&name)) {
+                (Some(fields), _) => (
+                    vec![],
+                    fields
+                        .iter()
+                        .map(|(field, field_type)| (format!(".{}", field), None, field_type))
+                        .collect(),
+                ),
+                (None, Some(variants)) => (
+                    vec![Slot::Score(String::new())],
+                    variants
+                        .iter()
+                        .enumerate()
+                        .flat_map(|(discriminant, (variant, payload))| {
+                            payload.iter().enumerate().map(move |(index, ty)| {
+                                let prefix = format!(".{}.{}", variant, index);
+                                (prefix, Some(discriminant as i32), ty)
+                            })
+                        })
+                        .collect(),
+                ),
+                (None, None) => return Err(BackendError::UnknownType(name, span)),
+            };
+
+        if parents.contains(&ty) {
+            return Err(BackendError::RecursiveType(name, span));
+        }
+
+        parents.push(ty);
+        for (prefix, discriminant, member_type) in members {
+            for slot in self.layout_inner(member_type, span, parents)? {
+                let slot = slot.with_prefix(&prefix);
+                layout.push(match discriminant {
+                    Some(discriminant) => {
+                        Slot::Payload(String::new(), discriminant, Box::new(slot))
+                    }
+                    None => slot,
+                });
+            }
+        }
+        parents.pop();
+
+        Ok(layout)
+    }
+
+    /// The layout of the variable used at `span`, variables declared elsewhere are assumed to be
+    /// scalars.
+    pub(super) fn variable_layout(
+        &self,
+        variable: MangledVar,
+        span: Span,
+    ) -> BackendResult<Vec<Slot>> {
+        match self.variables.get(&variable) {
+            Some(ty) => self.layout(ty, span),
+            None => Ok(vec![Slot::Score(String::new())]),
+        }
+    }
+}
+
+impl FunctionBuilder<'_> {
+    /// The type of `expr` if its layout or arithmetic depends on it, which is the case for
+    /// structs, enums, arrays, strings, integers narrower or wider than a score and fixed-point
+    /// numbers.
+    pub(super) fn value_type(
+        &self,
+        expr: &MirExpression<MangledVar>,
+    ) -> BackendResult<Option<Type>> {
+        Ok(match &expr.kind {
+            MirExpressionKind::Variable(variable) => self.datapack.variables.get(variable).cloned(),
+            MirExpressionKind::Call(path, _) => {
+                let (_, signature) = self.datapack.resolve(path, expr.span)?;
+                signature.map(|signature| signature.return_type.clone())
+            }
+            MirExpressionKind::Struct(path, _) | MirExpressionKind::Variant(path, _, _) => {
+                Some(MirType::UserDefined(path.clone()))
+            }
+            MirExpressionKind::Literal(LiteralType::String(_)) => {
+                Some(MirType::Primitive(MirPrimitive::String))
+            }
+            MirExpressionKind::Literal(LiteralType::Byte(_)) => {
+                Some(MirType::Primitive(MirPrimitive::Byte))
+            }
+            MirExpressionKind::Literal(LiteralType::Short(_)) => {
+                Some(MirType::Primitive(MirPrimitive::Short))
+            }
+            MirExpressionKind::Literal(LiteralType::Long(_)) => {
+                Some(MirType::Primitive(MirPrimitive::Long))
+            }
+            MirExpressionKind::Literal(LiteralType::Float(_)) => {
+                Some(MirType::Primitive(MirPrimitive::Float))
+            }
+            MirExpressionKind::Literal(LiteralType::Double(_)) | MirExpressionKind::Nbt(_) => {
+                Some(MirType::Primitive(MirPrimitive::Double))
+            }
+            MirExpressionKind::Cast(_, primitive) => Some(MirType::Primitive(*primitive)),
+            // Both sides of arithmetic have the type of the result.
+            MirExpressionKind::BinaryOp(
+                left,
+                MirBinaryOp::Add
+                | MirBinaryOp::Subtract
+                | MirBinaryOp::Multiply
+                | MirBinaryOp::Divide
+                | MirBinaryOp::Modulo,
+                right,
+            ) => match self.value_type(left)? {
+                Some(ty) => Some(ty),
+                None => self.value_type(right)?,
+            },
+            MirExpressionKind::UnaryOp(MirUnaryOp::Negate, expr) => self.value_type(expr)?,
+            // The right side of `??` replaces the left side only where it is unset.
+            MirExpressionKind::BinaryOp(left, MirBinaryOp::Coalesce, right) => {
+                match (self.value_type(right)?, self.value_type(left)?) {
+                    (Some(ty), _) => Some(ty),
+                    (None, Some(MirType::Optional(inner))) => Some(*inner),
+                    (None, ty) => ty,
+                }
+            }
+            MirExpressionKind::Ternary(_, then, otherwise) => match self.value_type(then)? {
+                Some(ty) => Some(ty),
+                None => self.value_type(otherwise)?,
+            },
+            MirExpressionKind::Index(array, _) => match self.value_type(array)? {
+                Some(MirType::Array(element, _)) => Some(*element),
+                _ => None,
+            },
+            // Elements are scalars, their type does not change the layout.
+            MirExpressionKind::Array(elements) => Some(MirType::Array(
+                Box::new(MirType::Primitive(MirPrimitive::Int)),
+                elements.len() as u32,
+            )),
+            // Every arm has the same type, arms running blocks have no value at all.
+            MirExpressionKind::Match(match_expr) => {
+                match match_expr.arms.iter().find_map(|arm| match &arm.body {
+                    MirArmBody::Expression(expr) => Some(expr),
+                    MirArmBody::Block(_) => None,
+                }) {
+                    Some(expr) => self.value_type(expr)?,
+                    None => None,
+                }
+            }
+            MirExpressionKind::Property(left, field) => match self.value_type(left)? {
+                Some(ty) => self
+                    .datapack
+                    .struct_fields(&ty, expr.span)?
+                    .and_then(|fields| fields.iter().find(|(name, _)| name == field))
+                    .map(|(_, field_type)| field_type.clone()),
+                None => None,
+            },
+            _ => None,
+        })
+    }
+
+    /// The layout of the value of `expr`.
+    pub(super) fn value_layout(
+        &self,
+        expr: &MirExpression<MangledVar>,
+    ) -> BackendResult<Vec<Slot>> {
+        match self.value_type(expr)? {
+            Some(ty) => self.datapack.layout(&ty, expr.span),
+            None => Ok(vec![Slot::Score(String::new())]),
+        }
+    }
+
+    /// Copies every score and stored entry of a value with the given layout. Payloads of enum
+    /// variants are only copied if the source holds the variant, others may have never been set.
+    pub(super) fn copy_value(&mut self, target: &Score, source: &Score, layout: &[Slot]) {
+        for slot in layout {
+            if let Slot::Payload(suffix, discriminant, slot) = slot {
+                let condition = Condition::check(format!(
+                    "if score {} matches {}",
+                    source.with_suffix(suffix),
+                    discriminant
+                ));
+                let start = self.commands.len();
+                self.copy_value(target, source, slice::from_ref(slot));
+                for command in &mut self.commands[start..] {
+                    *command = condition
+                        .run(command)
+                        .expect("the condition is not constant");
+                }
+                continue;
+            }
+
+            let (target, source) = (
+                target.with_suffix(slot.suffix()),
+                source.with_suffix(slot.suffix()),
+            );
+            match slot {
+                Slot::Score(_) => self.copy(&target, &source),
+                Slot::Optional(_) => self.copy_optional(&target, &source),
+                Slot::Stored(_, _) => self.copy_stored(&target, &source),
+                Slot::Payload(_, _, _) => unreachable!("payloads are copied above"),
+            }
+        }
+    }
+
+    /// The score holding the value of `expr`. Variables and their fields are used in place,
+    /// other values are stored in a temporary first.
+    pub(super) fn value_score(&mut self, expr: MirExpression<MangledVar>) -> BackendResult<Score> {
+        match expr.kind {
+            MirExpressionKind::Variable(variable) => Ok(Score::variable(variable)),
+            MirExpressionKind::Property(inner, field) => self.field_score(*inner, &field),
+            kind => {
+                let temporary = self.temporary();
+                MirExpression::new(kind, expr.span).evaluate_into(self, &temporary)?;
+                Ok(temporary)
+            }
+        }
+    }
+
+    /// The score holding field `name` of the struct `left` evaluates to.
+    pub(super) fn field_score(
+        &mut self,
+        left: MirExpression<MangledVar>,
+        name: &XID,
+    ) -> BackendResult<Score> {
+        if !matches!(self.value_type(&left)?, Some(MirType::UserDefined(_))) {
+            return Err(BackendError::Unsupported(
+                "fields of values other than structs",
+                left.span,
+            ));
+        }
+
+        Ok(self.value_score(left)?.field(name.inner()))
+    }
+
+    /// The score an assignment to `target` writes to.
+    pub(super) fn place(&mut self, target: MirExpression<MangledVar>) -> BackendResult<Score> {
+        match target.kind {
+            MirExpressionKind::Variable(variable) => Ok(Score::variable(variable)),
+            MirExpressionKind::Property(left, name) => self.field_score(*left, &name),
+            _ => Err(BackendError::Unsupported(
+                "assignments to values other than variables",
+                target.span,
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::{tests::lower, BackendError};
+
+    #[test]
+    fn fields_are_flattened_into_scores() -> eyre::Result<()> {
+        let datapack = lower(
+            "struct Point { x: int, y: int }
+            struct Line { from: Point, to: Point }
+            static line: Line = Line { from: Point { x: 1, y: 2 }, to: Point { x: 3, y: 4 } };
+            static function main(): void { line.to.x = line.from.y; line.from = line.to; }",
+        )??;
+
+        let init = datapack.function("__init").unwrap();
+        let suffixes: Vec<&str> = init[init.len() - 4..]
+            .iter()
+            .map(|command| command.split(' ').nth(3).unwrap())
+            .map(|holder| &holder[16..])
+            .collect();
+        assert_eq!(suffixes, [".from.x", ".from.y", ".to.x", ".to.y"]);
+
+        let main = datapack.function("main").unwrap();
+        assert_eq!(main.len(), 3);
+        assert!(main[0].contains(".to.x ss_global = ") && main[0].ends_with(".from.y ss_global"));
+        assert!(main[2].contains(".from.y ss_global = ") && main[2].ends_with(".to.y ss_global"));
+        Ok(())
+    }
+
+    #[test]
+    fn structs_are_passed_and_returned_by_copy() -> eyre::Result<()> {
+        let datapack = lower(
+            "struct Point { x: int, y: int }
+            static function swap(p: Point): Point { return Point { x: p.y, y: p.x }; }
+            static function main(): void {
+                let p: Point = Point { x: 1, y: 2 };
+                p = swap(p);
+                p = Point { x: p.y, y: p.x };
+            }",
+        )??;
+
+        let swap = datapack.function("swap").unwrap();
+        let param = swap[0].split(' ').nth(6).unwrap().trim_end_matches(".y");
+        assert_eq!(
+            swap[0],
+            format!(
+                "scoreboard players operation #return.x ss_global = {}.y ss_global",
+                param
+            )
+        );
+
+        let main = datapack.function("main").unwrap();
+        let local = main[0].split(' ').nth(3).unwrap().trim_end_matches(".x");
+        assert_ne!(local, param);
+        assert_eq!(
+            main[2..5],
+            [
+                format!("scoreboard players operation {param}.x ss_global = {local}.x ss_global"),
+                format!("scoreboard players operation {param}.y ss_global = {local}.y ss_global"),
+                "function test:swap".to_string(),
+            ]
+        );
+        assert!(main[6].ends_with("= #return.y ss_global"));
+
+        // Swapping fields in place goes through a temporary.
+        assert!(main[7].starts_with("scoreboard players operation #test.t0.x ss_global = "));
+        assert!(main[10].ends_with(".y ss_global = #test.t0.y ss_global"));
+        assert_eq!(main.len(), 11);
+        Ok(())
+    }
+
+    #[test]
+    fn recursive_structs_are_rejected() -> eyre::Result<()> {
+        assert!(matches!(
+            lower("struct Node { next: Node } static node: Node = Node { next: node };")?,
+            Err(BackendError::RecursiveType(name, _)) if name == "Node"
+        ));
+        assert!(matches!(
+            lower("struct Point { x: int } struct Point { y: int }")?,
+            Err(BackendError::DuplicateType(_, _))
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,202 @@
+use serde::{de::Error as _, Deserialize, Deserializer};
+use std::{fmt, str::FromStr};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum InvalidVersion {
+    #[error("Invalid Minecraft version {0:?}, expected a version like `1.20.4`")]
+    Malformed(String),
+    #[error("Minecraft {0} is not supported, supported versions are {1} to {2}")]
+    Unsupported(MinecraftVersion, MinecraftVersion, MinecraftVersion),
+}
+
+/// A Java Edition release, only `1.x.y` versions exist so the major version is implied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MinecraftVersion {
+    minor: u16,
+    patch: u16,
+}
+
+impl MinecraftVersion {
+    pub const OLDEST: Self = Self::new(13, 0);
+    pub const LATEST: Self = Self::new(21, 5);
+
+    pub const fn new(minor: u16, patch: u16) -> Self {
+        Self { minor, patch }
+    }
+
+    pub fn supports(&self, feature: Feature) -> bool {
+        *self >= feature.since()
+    }
+
+    pub fn pack_format(&self) -> u32 {
+        PACK_FORMATS
+            .iter()
+            .rev()
+            .find(|(version, _)| version <= self)
+            .map(|(_, format)| *format)
+            .expect("versions are validated on creation")
+    }
+
+    /// Name of the directories holding functions and function tags, it lost its plural in 1.21.
+    pub fn function_directory(&self) -> &'static str {
+        if *self >= Self::new(21, 0) {
+            "function"
+        } else {
+            "functions"
+        }
+    }
+}
+
+impl Default for MinecraftVersion {
+    fn default() -> Self {
+        Self::LATEST
+    }
+}
+
+/// The first release using each `pack_format`.
+const PACK_FORMATS: &[(MinecraftVersion, u32)] = &[
+    (MinecraftVersion::new(13, 0), 4),
+    (MinecraftVersion::new(15, 0), 5),
+    (MinecraftVersion::new(16, 2), 6),
+    (MinecraftVersion::new(17, 0), 7),
+    (MinecraftVersion::new(18, 0), 8),
+    (MinecraftVersion::new(18, 2), 9),
+    (MinecraftVersion::new(19, 0), 10),
+    (MinecraftVersion::new(19, 4), 12),
+    (MinecraftVersion::new(20, 0), 15),
+    (MinecraftVersion::new(20, 2), 18),
+    (MinecraftVersion::new(20, 3), 26),
+    (MinecraftVersion::new(20, 5), 41),
+    (MinecraftVersion::new(21, 0), 48),
+    (MinecraftVersion::new(21, 2), 57),
+    (MinecraftVersion::new(21, 4), 61),
+    (MinecraftVersion::new(21, 5), 71),
+];
+
+impl FromStr for MinecraftVersion {
+    type Err = InvalidVersion;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let malformed = || InvalidVersion::Malformed(s.to_string());
+
+        let mut parts = s.split('.');
+        if parts.next() != Some("1") {
+            return Err(malformed());
+        }
+
+        let minor = parts.next().ok_or_else(malformed)?;
+        let patch = parts.next().unwrap_or("0");
+        if parts.next().is_some() {
+            return Err(malformed());
+        }
+
+        let version = Self::new(
+            minor.parse().map_err(|_| malformed())?,
+            patch.parse().map_err(|_| malformed())?,
+        );
+
+        if version < Self::OLDEST || version > Self::LATEST {
+            return Err(InvalidVersion::Unsupported(
+                version,
+                Self::OLDEST,
+                Self::LATEST,
+            ));
+        }
+
+        Ok(version)
+    }
+}
+
+impl fmt::Display for MinecraftVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.patch == 0 {
+            write!(f, "1.{}", self.minor)
+        } else {
+            write!(f, "1.{}.{}", self.minor, self.patch)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for MinecraftVersion {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value: String = Deserialize::deserialize(deserializer)?;
+        value.parse().map_err(D::Error::custom)
+    }
+}
+
+/// Commands that only exist in newer versions of the game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    Schedule,
+    Storage,
+    Return,
+    ReturnRun,
+    FunctionMacros,
+    ExecuteIfFunction,
+}
+
+impl Feature {
+    /// The first release supporting the feature.
+    pub fn since(&self) -> MinecraftVersion {
+        match self {
+            Self::Schedule => MinecraftVersion::new(14, 0),
+            Self::Storage => MinecraftVersion::new(15, 0),
+            Self::Return => MinecraftVersion::new(20, 0),
+            Self::FunctionMacros => MinecraftVersion::new(20, 2),
+            // `return run` exists since 1.20.2, but only returns when its command fails since 1.20.3.
+            Self::ReturnRun | Self::ExecuteIfFunction => MinecraftVersion::new(20, 3),
+        }
+    }
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Schedule => "`schedule`",
+            Self::Storage => "command storage",
+            Self::Return => "`return`",
+            Self::ReturnRun => "`return run`",
+            Self::FunctionMacros => "function macros",
+            Self::ExecuteIfFunction => "`execute if function`",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Feature, InvalidVersion, MinecraftVersion};
+
+    #[test]
+    fn parse_versions() {
+        let version: MinecraftVersion = "1.20.4".parse().unwrap();
+        assert_eq!(version, MinecraftVersion::new(20, 4));
+        assert_eq!(version.to_string(), "1.20.4");
+        assert_eq!(version.pack_format(), 26);
+        assert_eq!(version.function_directory(), "functions");
+
+        let version: MinecraftVersion = "1.21".parse().unwrap();
+        assert_eq!(version.pack_format(), 48);
+        assert_eq!(version.function_directory(), "function");
+
+        assert!(matches!(
+            "1.12.2".parse::<MinecraftVersion>(),
+            Err(InvalidVersion::Unsupported(..))
+        ));
+        assert!(matches!(
+            "21.4".parse::<MinecraftVersion>(),
+            Err(InvalidVersion::Malformed(..))
+        ));
+    }
+
+    #[test]
+    fn features() {
+        let version = MinecraftVersion::new(20, 2);
+        assert!(version.supports(Feature::Return));
+        assert!(version.supports(Feature::FunctionMacros));
+        assert!(!version.supports(Feature::ExecuteIfFunction));
+    }
+}
@@ -0,0 +1,369 @@
+use super::{BackendError, BackendResult, Condition, Feature, FunctionBuilder, Lower, Score};
+use crate::{
+    mir::{MangledVar, MirArmBody, MirElseBlock, MirExpression, MirIf, MirStatement, MirWhile},
+    token::XID,
+    Span,
+};
+
+/// A loop around the statements being lowered.
+#[derive(Debug, Clone)]
+pub(super) struct EnclosingLoop {
+    label: Option<XID>,
+    /// Set by `break` and `continue` targeting the loop, only exists if the body uses them.
+    control: Option<Score>,
+}
+
+/// Values of the loop control score, 0 means the body ran to the end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum LoopExit {
+    Break = 1,
+    Continue = 2,
+}
+
+/// Whether `statements` contain a `break` or `continue` targeting the loop labelled `label`,
+/// `innermost` tells whether it is also the innermost loop around them.
+fn uses_loop_control(
+    statements: &[MirStatement<MangledVar>],
+    label: Option<&XID>,
+    innermost: bool,
+) -> bool {
+    statements.iter().any(|statement| match statement {
+        MirStatement::Break(target, _) | MirStatement::Continue(target, _) => match target {
+            Some(target) => Some(target) == label,
+            None => innermost,
+        },
+        MirStatement::Block(block) | MirStatement::Unsafe(block) => {
+            uses_loop_control(block, label, innermost)
+        }
+        MirStatement::If(if_block) => if_uses_loop_control(if_block, label, innermost),
+        MirStatement::Match(match_stmt) => match_stmt.arms.iter().any(|arm| match &arm.body {
+            MirArmBody::Block(block) => uses_loop_control(block, label, innermost),
+            MirArmBody::Expression(_) => false,
+        }),
+        // Nested loops with the same label shadow this one.
+        MirStatement::While(inner) => {
+            (label.is_none() || inner.label.as_ref() != label)
+                && uses_loop_control(&inner.block, label, false)
+        }
+        MirStatement::For(inner) => {
+            (label.is_none() || inner.label.as_ref() != label)
+                && uses_loop_control(&inner.block, label, false)
+        }
+        _ => false,
+    })
+}
+
+fn if_uses_loop_control(
+    if_block: &MirIf<MangledVar>,
+    label: Option<&XID>,
+    innermost: bool,
+) -> bool {
+    uses_loop_control(&if_block.block, label, innermost)
+        || match &if_block.else_block {
+            Some(MirElseBlock::ElseIf(else_if)) => if_uses_loop_control(else_if, label, innermost),
+            Some(MirElseBlock::Else(block)) => uses_loop_control(block, label, innermost),
+            None => false,
+        }
+}
+
+/// Lowers a loop running `body` and `update` while `condition` holds. The loop function calls
+/// itself for the next iteration, `spread` loops stop after a number of iterations and resume
+/// next tick.
+pub(super) fn lower_loop(
+    builder: &mut FunctionBuilder,
+    label: Option<XID>,
+    condition: MirExpression<MangledVar>,
+    body: Vec<MirStatement<MangledVar>>,
+    update: Option<MirStatement<MangledVar>>,
+    is_spread: bool,
+) -> BackendResult<()> {
+    let control = uses_loop_control(&body, label.as_ref(), true).then(|| builder.temporary());
+    let enclosing = EnclosingLoop { label, control };
+
+    if is_spread {
+        builder.require(Feature::Schedule, condition.span)?;
+        let entry = builder.generate("spread", false, |entry| {
+            lower_spread_entry(entry, condition, enclosing, body, update)
+        })?;
+        builder.run_generated(&Condition::Constant(true), entry);
+        return Ok(());
+    }
+
+    let tail_condition = condition.clone();
+    let condition = condition.evaluate_condition(builder)?;
+    if condition == Condition::Constant(false) {
+        return Ok(());
+    }
+
+    let function = builder.generate("loop", false, |builder| {
+        lower_iteration(builder, enclosing, body, update)?;
+        let location = builder.location();
+        let condition = tail_condition.evaluate_condition(builder)?;
+        if let Some(command) = condition.run(&format!("function {}", location)) {
+            builder.push(command);
+        }
+        Ok(())
+    })?;
+    builder.run_generated(&condition, function);
+    Ok(())
+}
+
+/// Lowers a single iteration, the body gets a function of its own if `break` or `continue`
+/// have to stop it early.
+fn lower_iteration(
+    builder: &mut FunctionBuilder,
+    enclosing: EnclosingLoop,
+    body: Vec<MirStatement<MangledVar>>,
+    update: Option<MirStatement<MangledVar>>,
+) -> BackendResult<()> {
+    match enclosing.control.clone() {
+        Some(control) => {
+            builder.push(format!("scoreboard players set {} 0", control));
+            let mut function = builder.generate("body", false, |builder| {
+                builder.loops.push(enclosing);
+                body.lower(builder)
+            })?;
+            function.exits.retain(|exit| exit != &control);
+            builder.run_generated(&Condition::Constant(true), function);
+            builder.push(format!(
+                "execute if score {} matches {} run return 0",
+                control,
+                LoopExit::Break as i32
+            ));
+        }
+        None => {
+            builder.loops.push(enclosing);
+            body.lower(builder)?;
+            builder.loops.pop();
+        }
+    }
+
+    if let Some(update) = update {
+        update.lower(builder)?;
+    }
+    Ok(())
+}
+
+/// The entry of a `spread` loop resets the iteration budget of the current tick, it is run by
+/// the enclosing function and scheduled once the budget runs out.
+fn lower_spread_entry(
+    entry: &mut FunctionBuilder,
+    condition: MirExpression<MangledVar>,
+    enclosing: EnclosingLoop,
+    body: Vec<MirStatement<MangledVar>>,
+    update: Option<MirStatement<MangledVar>>,
+) -> BackendResult<()> {
+    let entry_location = entry.location();
+    let budget = entry.temporary();
+    let tail_condition = condition.clone();
+
+    let mut length = 0;
+    let function = entry.generate("loop", false, |builder| {
+        lower_iteration(builder, enclosing, body, update)?;
+        let location = builder.location();
+
+        // Both checks below must see the same result, the loop call changes the budget.
+        let next = builder.temporary();
+        tail_condition
+            .evaluate_condition(builder)?
+            .store(builder, &next);
+        builder.push(format!("scoreboard players remove {} 1", budget));
+        builder.push(format!(
+            "execute if score {} matches 1 if score {} matches ..0 run schedule function {} 1t",
+            next, budget, entry_location
+        ));
+        builder.push(format!(
+            "execute if score {} matches 1 if score {} matches 1.. run function {}",
+            next, budget, location
+        ));
+
+        length = builder.commands.len();
+        Ok(())
+    })?;
+
+    if function.returns_early() || !function.exits.is_empty() {
+        return Err(BackendError::SpreadLoopReturn(condition.span));
+    }
+
+    // Only the commands of the loop function itself are counted, functions called by the body
+    // add to the chain as well.
+    let iterations = (entry.max_command_chain_length() / length as u32).max(1);
+    entry.push(format!("scoreboard players set {} {}", budget, iterations));
+
+    let condition = condition.evaluate_condition(entry)?;
+    entry.run_generated(&condition, function);
+    Ok(())
+}
+
+impl FunctionBuilder<'_> {
+    /// Stops the body of the loop labelled `label`, or the innermost loop, for the `break` or
+    /// `continue` at `span`.
+    pub(super) fn exit_loop(
+        &mut self,
+        label: Option<XID>,
+        exit: LoopExit,
+        span: Span,
+    ) -> BackendResult<()> {
+        self.require(Feature::Return, span)?;
+
+        let control = self
+            .loops
+            .iter()
+            .rev()
+            .find(|enclosing| label.is_none() || enclosing.label == label)
+            .and_then(|enclosing| enclosing.control.clone())
+            .expect("loop control statements are checked before lowering");
+
+        let set = format!("scoreboard players set {} {}", control, exit as i32);
+        if self.supports(Feature::ReturnRun) {
+            self.push(format!("return run {}", set));
+        } else {
+            self.push(set);
+            self.push("return 0");
+        }
+
+        if !self.exits.contains(&control) {
+            self.exits.push(control);
+        }
+        Ok(())
+    }
+}
+
+impl Lower for MirWhile<MangledVar> {
+    fn lower(self, builder: &mut FunctionBuilder) -> BackendResult<()> {
+        lower_loop(
+            builder,
+            self.label,
+            self.condition,
+            self.block,
+            None,
+            self.is_spread,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::{
+        tests::{lower, lower_for},
+        BackendError, Feature, MinecraftVersion,
+    };
+
+    #[test]
+    fn loop_calls_itself() -> eyre::Result<()> {
+        let datapack = lower(
+            "static x: int = 1;
+            static function f(): void { while (x < 1000) { x = x * 2; } while (false) { x = 1; } }",
+        )??;
+
+        let f = datapack.function("f").unwrap();
+        assert_eq!(f.len(), 1);
+        assert!(f[0].ends_with(" matches 1000.. run function test:f/loop_0"));
+
+        let body = datapack.function("f/loop_0").unwrap();
+        assert_eq!(body.len(), 2);
+        assert_eq!(body[1], f[0]);
+        assert_eq!(datapack.functions().count(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn spread_loop_schedules_itself() -> eyre::Result<()> {
+        let datapack = lower(
+            "static x: int = 1;
+            static function f(): void { spread while (x != 0) { x = x / 2; } }",
+        )??;
+
+        assert_eq!(
+            datapack.function("f").unwrap(),
+            ["function test:f/spread_0"]
+        );
+
+        let entry = datapack.function("f/spread_0").unwrap();
+        assert_eq!(entry[0], "scoreboard players set #test.t0 ss_global 13107");
+        assert!(entry[1].ends_with(" matches 0 run function test:f/loop_1"));
+
+        let body = datapack.function("f/loop_1").unwrap();
+        assert_eq!(body[2], "scoreboard players remove #test.t0 ss_global 1");
+        assert!(body[3].ends_with(" matches ..0 run schedule function test:f/spread_0 1t"));
+        assert!(body[4].ends_with(" matches 1.. run function test:f/loop_1"));
+        Ok(())
+    }
+
+    #[test]
+    fn spread_loop_restrictions() -> eyre::Result<()> {
+        assert!(matches!(
+            lower("static function f(): int { spread while (true) { return 1; } return 0; }")?,
+            Err(BackendError::SpreadLoopReturn(_))
+        ));
+        assert!(matches!(
+            lower_for(
+                "static function f(): void { spread while (true) {} }",
+                MinecraftVersion::new(13, 2)
+            )?,
+            Err(BackendError::UnsupportedFeature(Feature::Schedule, _, _))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn break_and_continue_stop_the_body() -> eyre::Result<()> {
+        let datapack = lower(
+            "static x: int = 0;
+            static function f(): void {
+                while (x < 10) { x = x + 1; if (x == 3) { continue; } if (x == 5) { break; } x = x * 2; }
+            }",
+        )??;
+
+        let body = datapack.function("f/loop_0").unwrap();
+        assert_eq!(body[0], "scoreboard players set #test.t0 ss_global 0");
+        assert_eq!(body[1], "function test:f/body_1");
+        assert_eq!(
+            body[2],
+            "execute if score #test.t0 ss_global matches 1 run return 0"
+        );
+        assert!(body[3].ends_with(" matches 10.. run function test:f/loop_0"));
+
+        let inner = datapack.function("f/body_1").unwrap();
+        assert!(inner[1].ends_with(" run return run scoreboard players set #test.t0 ss_global 2"));
+        assert!(inner[2].ends_with(" run return run scoreboard players set #test.t0 ss_global 1"));
+        Ok(())
+    }
+
+    #[test]
+    fn labelled_break_leaves_inner_loops() -> eyre::Result<()> {
+        let datapack = lower(
+            "static x: int = 0;
+            static function f(): void {
+                outer: while (true) { while (x != 0) { x = x / 2; if (x == 2) { break outer; } } x = 3; }
+            }",
+        )??;
+
+        let inner = datapack.function("f/loop_2").unwrap();
+        assert_eq!(inner.len(), 3);
+        assert!(inner[1].ends_with(" run return run scoreboard players set #test.t0 ss_global 1"));
+
+        let outer_body = datapack.function("f/body_1").unwrap();
+        assert_eq!(
+            outer_body[1],
+            "execute unless score #test.t0 ss_global matches 0 run return 0"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn loop_control_restrictions() -> eyre::Result<()> {
+        assert!(matches!(
+            lower_for(
+                "static function f(): void { while (true) { break; } }",
+                MinecraftVersion::new(19, 4)
+            )?,
+            Err(BackendError::UnsupportedFeature(Feature::Return, _, _))
+        ));
+        assert!(matches!(
+            lower("static function f(): void { a: while (true) { spread while (true) { break a; } } }")?,
+            Err(BackendError::SpreadLoopReturn(_))
+        ));
+        Ok(())
+    }
+}
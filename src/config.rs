@@ -1,10 +1,11 @@
+use crate::backend::MinecraftVersion;
 use semver::{Version, VersionReq};
 use serde::{
     de::{Error, MapAccess, Visitor},
     Deserialize, Deserializer,
 };
 use std::{collections::HashMap, fmt};
-use toml::Table;
+use toml::{Spanned, Table};
 
 fn deserialize_version<'de, D>(deserializer: D) -> Result<Version, D::Error>
 where
@@ -19,6 +20,27 @@ pub struct PackageInfo {
     pub name: String,
     #[serde(deserialize_with = "deserialize_version")]
     pub version: Version,
+    pub description: Option<String>,
+}
+
+impl PackageInfo {
+    /// The datapack namespace of the package, characters not allowed in namespaces become `_`.
+    pub fn namespace(&self) -> String {
+        self.name
+            .to_lowercase()
+            .chars()
+            .map(|char| match char {
+                'a'..='z' | '0'..='9' | '_' | '-' | '.' => char,
+                _ => '_',
+            })
+            .collect()
+    }
+
+    pub fn description(&self) -> String {
+        self.description
+            .clone()
+            .unwrap_or_else(|| format!("{} {}", self.name, self.version))
+    }
 }
 
 #[derive(Default, Debug)]
@@ -75,8 +97,24 @@ impl<'de> Deserialize<'de> for Dependency {
     }
 }
 
+#[derive(Deserialize, Debug, Default)]
+pub struct TargetConfig {
+    /// Version of Minecraft to generate the datapack for, defaults to the latest supported.
+    pub minecraft_version: Option<MinecraftVersion>,
+    /// Value of the `maxCommandChainLength` gamerule on the target world, used to decide how
+    /// many iterations of a `spread` loop run per tick.
+    pub max_command_chain_length: Option<u32>,
+    /// `float`s are stored in scores multiplied by this power of ten, which sets how many
+    /// decimal places they keep. Larger scales leave less range.
+    pub float_scale: Option<Spanned<u32>>,
+    /// Like `float_scale`, for `double`s.
+    pub double_scale: Option<Spanned<u32>>,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct ProjectConfig {
     pub package: PackageInfo,
+    #[serde(default)]
+    pub target: TargetConfig,
     pub dependencies: HashMap<String, Dependency>,
 }
@@ -0,0 +1,327 @@
+use crate::{
+    source::{FileId, Location, SourceMap},
+    Span,
+};
+use std::fmt::{self, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Warning => "warning",
+            Self::Error => "error",
+        })
+    }
+}
+
+/// A span in the source with a short explanation next to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpanLabel {
+    pub span: Span,
+    pub message: String,
+}
+
+/// A message about the program being compiled, pointing at the code it is about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    /// Where the problem is, if it can be pinned to a place in the source.
+    pub primary: Option<SpanLabel>,
+    /// Other places that explain the problem.
+    pub secondary: Vec<SpanLabel>,
+    pub notes: Vec<String>,
+    pub help: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: impl ToString) -> Self {
+        Self {
+            severity,
+            message: message.to_string(),
+            primary: None,
+            secondary: vec![],
+            notes: vec![],
+            help: vec![],
+        }
+    }
+
+    pub fn error(message: impl ToString) -> Self {
+        Self::new(Severity::Error, message)
+    }
+
+    pub fn warning(message: impl ToString) -> Self {
+        Self::new(Severity::Warning, message)
+    }
+
+    pub fn with_primary(mut self, span: Span, message: impl ToString) -> Self {
+        self.primary = Some(SpanLabel {
+            span,
+            message: message.to_string(),
+        });
+        self
+    }
+
+    pub fn with_secondary(mut self, span: Span, message: impl ToString) -> Self {
+        self.secondary.push(SpanLabel {
+            span,
+            message: message.to_string(),
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl ToString) -> Self {
+        self.notes.push(note.to_string());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl ToString) -> Self {
+        self.help.push(help.to_string());
+        self
+    }
+
+    /// Renders the diagnostic like rustc does, quoting the lines it points at.
+    pub fn render(&self, source_map: &SourceMap) -> String {
+        let mut output = format!("{}: {}\n", self.severity, self.message);
+
+        // Primary labels are underlined with `^`, secondary ones with `-`.
+        let labels: Vec<(&SpanLabel, char)> = self
+            .primary
+            .iter()
+            .map(|label| (label, '^'))
+            .chain(self.secondary.iter().map(|label| (label, '-')))
+            .collect();
+
+        // Labels are grouped by file, starting with the file of the primary label.
+        let mut files: Vec<FileId> = vec![];
+        for (label, _) in &labels {
+            if !files.contains(&label.span.file) {
+                files.push(label.span.file);
+            }
+        }
+
+        let gutter = labels
+            .iter()
+            .filter_map(|(label, _)| source_map.location(label.span))
+            .map(|(_, location)| location.line.to_string().len())
+            .max()
+            .unwrap_or(1);
+        let empty = " ".repeat(gutter);
+
+        for (index, id) in files.into_iter().enumerate() {
+            let Some(file) = source_map.file(id) else {
+                continue;
+            };
+
+            let mut lines: Vec<(Location, usize, char, &str)> = labels
+                .iter()
+                .filter(|(label, _)| label.span.file == id)
+                .map(|(label, underline)| {
+                    let location = file.location(label.span.index);
+                    // The end of the line can be pointed at, for input missing there.
+                    let line_width = file.line(location.line).chars().count() + 1;
+                    let width = label.span.width.clamp(1, line_width - location.column + 1);
+                    (location, width, *underline, label.message.as_str())
+                })
+                .collect();
+
+            let arrow = if index == 0 && self.primary.is_some() {
+                "-->"
+            } else {
+                ":::"
+            };
+            writeln!(
+                output,
+                "{}{} {}:{}",
+                empty,
+                arrow,
+                file.path().display(),
+                lines[0].0
+            )
+            .unwrap();
+            writeln!(output, "{} |", empty).unwrap();
+
+            lines.sort_by_key(|(location, ..)| (location.line, location.column));
+            let mut previous_line = None;
+            for (location, width, underline, message) in lines {
+                let text = file.line(location.line);
+                if previous_line != Some(location.line) {
+                    writeln!(output, "{:>gutter$} | {}", location.line, text).unwrap();
+                    previous_line = Some(location.line);
+                }
+
+                // Tabs are kept so the underline lines up however wide they are displayed.
+                let padding: String = text
+                    .chars()
+                    .take(location.column - 1)
+                    .map(|char| if char == '\t' { '\t' } else { ' ' })
+                    .collect();
+                let underline = underline.to_string().repeat(width);
+                let line = format!("{} | {}{} {}", empty, padding, underline, message);
+                writeln!(output, "{}", line.trim_end()).unwrap();
+            }
+        }
+
+        if !self.notes.is_empty() || !self.help.is_empty() {
+            if !labels.is_empty() {
+                writeln!(output, "{} |", empty).unwrap();
+            }
+            for note in &self.notes {
+                writeln!(output, "{} = note: {}", empty, note).unwrap();
+            }
+            for help in &self.help {
+                writeln!(output, "{} = help: {}", empty, help).unwrap();
+            }
+        }
+
+        output
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.severity, self.message)
+    }
+}
+
+/// Every diagnostic reported while compiling.
+#[derive(Debug, Clone, Default)]
+pub struct Diagnostics {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, diagnostic: impl Into<Diagnostic>) {
+        self.diagnostics.push(diagnostic.into())
+    }
+
+    pub fn extend<T: Into<Diagnostic>>(&mut self, diagnostics: impl IntoIterator<Item = T>) {
+        self.diagnostics
+            .extend(diagnostics.into_iter().map(Into::into))
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+            .count()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.error_count() > 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.diagnostics.len()
+    }
+
+    /// Renders every diagnostic, separated by empty lines.
+    pub fn render(&self, source_map: &SourceMap) -> String {
+        self.diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.render(source_map))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Diagnostic, Diagnostics};
+    use crate::{SourceMap, Span};
+
+    #[test]
+    fn renders_snippets_with_carets() {
+        let mut source_map = SourceMap::new();
+        let (file, _) = source_map.load(
+            "src/main.ss",
+            "static function f(): void {\n\tlet scoped: float = \"hello\";\n}".to_string(),
+            &mut Diagnostics::new(),
+        );
+        let diagnostic = Diagnostic::error("Mismatched types")
+            .with_primary(Span::new(file, 49, 7), "expected `float`, found `string`")
+            .with_secondary(Span::new(file, 41, 5), "expected due to this")
+            .with_help("use a float literal like `0.0`");
+
+        assert_eq!(
+            diagnostic.render(&source_map),
+            "error: Mismatched types
+ --> src/main.ss:2:22
+  |
+2 | \tlet scoped: float = \"hello\";
+  | \t            ----- expected due to this
+  | \t                    ^^^^^^^ expected `float`, found `string`
+  |
+  = help: use a float literal like `0.0`
+"
+        );
+    }
+
+    #[test]
+    fn end_of_file_is_pointed_at() {
+        let mut source_map = SourceMap::new();
+        let (file, _) = source_map.load(
+            "main.ss",
+            "let x: int = 1".to_string(),
+            &mut Diagnostics::new(),
+        );
+        let diagnostic =
+            Diagnostic::error("Unexpected end of file").with_primary(Span::new(file, 14, 0), "");
+
+        assert_eq!(
+            diagnostic.render(&source_map),
+            "error: Unexpected end of file
+ --> main.ss:1:15
+  |
+1 | let x: int = 1
+  |               ^
+"
+        );
+    }
+
+    #[test]
+    fn labels_in_other_files() {
+        let mut source_map = SourceMap::new();
+        let mut diagnostics = Diagnostics::new();
+        let (main, _) = source_map.load("main.ss", "f(1);".to_string(), &mut diagnostics);
+        let (lib, _) = source_map.load(
+            "lib.ss",
+            "\n\nstatic function f(): void {}".to_string(),
+            &mut diagnostics,
+        );
+        let diagnostic = Diagnostic::error("Function `f` takes 0 arguments but 1 were supplied")
+            .with_primary(Span::new(main, 0, 4), "expected 0 arguments")
+            .with_secondary(Span::new(lib, 18, 1), "defined here");
+
+        assert_eq!(
+            diagnostic.render(&source_map),
+            "error: Function `f` takes 0 arguments but 1 were supplied
+ --> main.ss:1:1
+  |
+1 | f(1);
+  | ^^^^ expected 0 arguments
+ ::: lib.ss:3:17
+  |
+3 | static function f(): void {}
+  |                 - defined here
+"
+        );
+    }
+}
@@ -1,14 +1,68 @@
-use super::{Expression, Path};
+use super::Expression;
 use crate::{
     ast_item,
-    token::{Assign, Semicolon},
-    Span, Spanned,
+    token::{
+        Assign, MinusAssign, PercentAssign, PlusAssign, Punct, PunctToken, Semicolon, SlashAssign,
+        StarAssign,
+    },
+    Parse, Span, Spanned, SyntaxError, SyntaxResult, TokenIter, TokenTree,
 };
 
+ast_item!(
+    pub enum AssignOp {
+        Assign(Assign),
+        Add(PlusAssign),
+        Subtract(MinusAssign),
+        Multiply(StarAssign),
+        Divide(SlashAssign),
+        Modulo(PercentAssign),
+    }
+);
+
+impl AssignOp {
+    pub fn is_punct(punct: &Punct) -> bool {
+        matches!(
+            punct.inner(),
+            PunctToken::Assign
+                | PunctToken::PlusAssign
+                | PunctToken::MinusAssign
+                | PunctToken::StarAssign
+                | PunctToken::SlashAssign
+                | PunctToken::PercentAssign
+        )
+    }
+}
+
+impl Parse for AssignOp {
+    fn parse(token_iter: &mut TokenIter) -> SyntaxResult<Self> {
+        let TokenTree::Punct(punct) = token_iter.expect_peek()? else {
+            return Err(SyntaxError::UnexpectedToken(
+                token_iter.expect_consume()?,
+                "an assignment operator",
+            ));
+        };
+
+        Ok(match punct.inner() {
+            PunctToken::Assign => Self::Assign(token_iter.parse()?),
+            PunctToken::PlusAssign => Self::Add(token_iter.parse()?),
+            PunctToken::MinusAssign => Self::Subtract(token_iter.parse()?),
+            PunctToken::StarAssign => Self::Multiply(token_iter.parse()?),
+            PunctToken::SlashAssign => Self::Divide(token_iter.parse()?),
+            PunctToken::PercentAssign => Self::Modulo(token_iter.parse()?),
+            _ => {
+                return Err(SyntaxError::UnexpectedToken(
+                    token_iter.expect_consume()?,
+                    "an assignment operator",
+                ))
+            }
+        })
+    }
+}
+
 ast_item!(
     pub struct Assignment {
-        variable: Path,
-        assign: Assign,
+        target: Expression,
+        assign: AssignOp,
         value: Expression,
         semicolon: Semicolon,
     }
@@ -16,6 +70,6 @@ ast_item!(
 
 impl Spanned for Assignment {
     fn span(&self) -> Span {
-        Span::from_start_end(self.variable.span(), self.semicolon.span())
+        Span::from_start_end(self.target.span(), self.semicolon.span())
     }
 }
@@ -0,0 +1,235 @@
+use super::{ArmBody, Block, ElseBlock, IfBlock, Statement};
+use crate::{
+    ast_item,
+    diagnostic::Diagnostic,
+    token::{Break, Colon, Continue, Ident, Semicolon},
+    Parse, Span, Spanned, SyntaxResult, TokenIter, TokenTree,
+};
+use std::mem;
+use thiserror::Error;
+
+ast_item!(
+    pub struct Label {
+        ident: Ident,
+        colon: Colon,
+    }
+);
+
+impl Spanned for Label {
+    fn span(&self) -> Span {
+        Span::from_start_end(self.ident.span(), self.colon.span())
+    }
+}
+
+impl Parse for Option<Label> {
+    fn parse(token_iter: &mut TokenIter) -> SyntaxResult<Self> {
+        if let Some(TokenTree::Ident(_)) = token_iter.peek() {
+            if let Some(TokenTree::Punct(punct)) = token_iter.peek_ahead(1) {
+                if Colon::is_punct(punct) {
+                    return Ok(Some(token_iter.parse()?));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+ast_item!(
+    pub struct BreakStatement {
+        break_token: Break,
+        label: Option<Ident>,
+        semicolon: Semicolon,
+    }
+);
+
+impl Spanned for BreakStatement {
+    fn span(&self) -> Span {
+        Span::from_start_end(self.break_token.span(), self.semicolon.span())
+    }
+}
+
+ast_item!(
+    pub struct ContinueStatement {
+        continue_token: Continue,
+        label: Option<Ident>,
+        semicolon: Semicolon,
+    }
+);
+
+impl Spanned for ContinueStatement {
+    fn span(&self) -> Span {
+        Span::from_start_end(self.continue_token.span(), self.semicolon.span())
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum LoopControlError {
+    #[error("`{0}` outside of a loop")]
+    OutsideLoop(&'static str, Span),
+    #[error("Use of undeclared loop label `{0}`")]
+    UndeclaredLabel(String, Span),
+}
+
+impl From<LoopControlError> for Diagnostic {
+    fn from(err: LoopControlError) -> Self {
+        let diagnostic = Diagnostic::error(&err);
+        match err {
+            LoopControlError::OutsideLoop(keyword, span) => {
+                diagnostic.with_primary(span, format!("cannot `{}` outside of a loop", keyword))
+            }
+            LoopControlError::UndeclaredLabel(_, span) => diagnostic
+                .with_primary(span, "undeclared label")
+                .with_help("labels are declared in front of a loop, like `outer: while (true) {}`"),
+        }
+    }
+}
+
+/// Ensures every `break` and `continue` is inside a loop it can refer to.
+pub fn check_loop_control(statements: &[Statement]) -> Vec<LoopControlError> {
+    let mut checker = LoopControl {
+        loops: vec![],
+        errors: vec![],
+    };
+    checker.check_block(statements);
+    checker.errors
+}
+
+struct LoopControl {
+    /// Labels of the loops around the current statement, innermost last.
+    loops: Vec<Option<String>>,
+    errors: Vec<LoopControlError>,
+}
+
+impl LoopControl {
+    fn check_block(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            self.check_statement(statement);
+        }
+    }
+
+    fn check_loop(&mut self, label: &Option<Label>, block: &Block) {
+        self.loops
+            .push(label.as_ref().map(|label| label.ident.inner().to_string()));
+        self.check_block(block.contents());
+        self.loops.pop();
+    }
+
+    fn check_if(&mut self, if_block: &IfBlock) {
+        self.check_block(if_block.block.contents());
+        match &if_block.else_block {
+            Some((_, ElseBlock::ElseIf(else_if))) => self.check_if(else_if),
+            Some((_, ElseBlock::Else(block))) => self.check_block(block.contents()),
+            None => (),
+        }
+    }
+
+    fn check_target(&mut self, keyword: &'static str, label: &Option<Ident>, span: Span) {
+        match label {
+            _ if self.loops.is_empty() => self
+                .errors
+                .push(LoopControlError::OutsideLoop(keyword, span)),
+            Some(label)
+                if !self
+                    .loops
+                    .iter()
+                    .flatten()
+                    .any(|name| name == label.inner()) =>
+            {
+                self.errors.push(LoopControlError::UndeclaredLabel(
+                    label.inner().to_string(),
+                    label.span(),
+                ))
+            }
+            _ => (),
+        }
+    }
+
+    fn check_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Block(block) | Statement::Unsafe((_, block)) => {
+                self.check_block(block.contents())
+            }
+            // Loops do not continue into functions declared inside them.
+            Statement::Function(func) => {
+                let loops = mem::take(&mut self.loops);
+                self.check_block(func.block.contents());
+                self.loops = loops;
+            }
+            Statement::If(if_block) => self.check_if(if_block),
+            Statement::Match(match_stmt) => {
+                for arm in match_stmt.arms.contents() {
+                    if let ArmBody::Block(block) = &arm.body {
+                        self.check_block(block.contents());
+                    }
+                }
+            }
+            Statement::While(while_loop) => self.check_loop(&while_loop.label, &while_loop.block),
+            Statement::For(for_loop) => self.check_loop(&for_loop.label, &for_loop.block),
+            Statement::Break(break_stmt) => {
+                self.check_target("break", &break_stmt.label, break_stmt.span())
+            }
+            Statement::Continue(continue_stmt) => {
+                self.check_target("continue", &continue_stmt.label, continue_stmt.span())
+            }
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_loop_control, LoopControlError};
+    use crate::{hir::Statement, parse_str, TokenIter};
+
+    fn check(input: &str) -> eyre::Result<Vec<LoopControlError>> {
+        let tokens = parse_str(input)?;
+        let statements: Vec<Statement> = TokenIter::from(&tokens).parse()?;
+        Ok(check_loop_control(&statements))
+    }
+
+    #[test]
+    fn labelled_loops() -> eyre::Result<()> {
+        assert!(check(
+            "outer: while (true) {
+                inner: spread for (let i: int = 0; i < 3; i = i + 1;) {
+                    if (i == 1) { continue outer; } else { break inner; }
+                }
+                break;
+            }",
+        )?
+        .is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn outside_of_loop() -> eyre::Result<()> {
+        assert!(matches!(
+            check("static function f(): void { break; }")?.as_slice(),
+            [LoopControlError::OutsideLoop("break", _)]
+        ));
+        assert!(matches!(
+            check("while (true) { static function f(): void { continue; } }")?.as_slice(),
+            [LoopControlError::OutsideLoop("continue", _)]
+        ));
+        assert!(matches!(
+            check("a: while (true) {} while (true) { break a; }")?.as_slice(),
+            [LoopControlError::UndeclaredLabel(label, _)] if label == "a"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn every_error_is_reported() -> eyre::Result<()> {
+        let errors = check("break; while (true) { continue b; } continue;")?;
+        assert!(matches!(
+            errors.as_slice(),
+            [
+                LoopControlError::OutsideLoop("break", _),
+                LoopControlError::UndeclaredLabel(_, _),
+                LoopControlError::OutsideLoop("continue", _),
+            ]
+        ));
+        Ok(())
+    }
+}
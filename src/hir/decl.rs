@@ -23,13 +23,13 @@ impl Parse for DeclStart {
                 } else {
                     Err(SyntaxError::UnexpectedToken(
                         token_iter.expect_consume()?,
-                        "static or let",
+                        "`static` or `let`",
                     ))
                 }
             }
             _ => Err(SyntaxError::UnexpectedToken(
                 token_iter.expect_consume()?,
-                "static or let",
+                "`static` or `let`",
             )),
         }
     }
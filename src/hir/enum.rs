@@ -0,0 +1,36 @@
+use super::{Punctuated, Type};
+use crate::{
+    ast_item,
+    token::{Brace, Comma, Enum, Ident, Parenthesis},
+    Span, Spanned,
+};
+
+ast_item!(
+    pub struct EnumVariant {
+        name: Ident,
+        payload: Option<Parenthesis<Punctuated<Type, Comma>>>,
+    }
+);
+
+impl Spanned for EnumVariant {
+    fn span(&self) -> Span {
+        match &self.payload {
+            Some(payload) => Span::from_start_end(self.name.span(), payload.span()),
+            None => self.name.span(),
+        }
+    }
+}
+
+ast_item!(
+    pub struct EnumDecl {
+        enum_token: Enum,
+        ident: Ident,
+        variants: Brace<Punctuated<EnumVariant, Comma>>,
+    }
+);
+
+impl Spanned for EnumDecl {
+    fn span(&self) -> Span {
+        Span::from_start_end(self.enum_token.span(), self.variants.span())
+    }
+}
@@ -1,10 +1,11 @@
-use super::{Path, Punctuated, ToTokens};
+use super::{FieldValue, MacroCall, MatchExpr, Path, Primitive, Punctuated, ToTokens};
 use crate::{
     ast_item,
     token::{
-        And, Bracket, Comma, Delimiter, Dot, Equals, GreaterThan, GreaterThanEquals, Ident,
-        LessThan, LessThanEquals, Literal, MacroPrefix, Minus, Not, NotEquals, Or, Parenthesis,
-        Percent, Plus, Punct, PunctToken, Slash, Star, ToTokenTree,
+        And, As, Brace, Bracket, Colon, Comma, Delimiter, Dot, Equals, GreaterThan,
+        GreaterThanEquals, Ident, LessThan, LessThanEquals, Literal, MacroPrefix, Match, Minus,
+        Not, NotEquals, Null, NullishCoalescing, Or, Parenthesis, Percent, Plus, Punct, PunctToken,
+        Slash, Star, Ternary, ToTokenTree,
     },
     Parse, Span, Spanned, SyntaxError, SyntaxResult, TokenIter, TokenStream, TokenTree,
 };
@@ -64,11 +65,11 @@ macro_rules! define_unary_op {
         );
 
         impl UnaryOp {
-            fn parse_left(punct: Punct) -> SyntaxResult<Option<UnaryOp>> {
-                Ok(match punct.inner() {
+            fn parse_left(punct: Punct) -> Option<UnaryOp> {
+                match punct.inner() {
                     $(PunctToken::$inner => unary_op_parse_left!(punct $pos $name),)+
                     _ => None,
-                })
+                }
             }
 
             fn parse_right(punct: Punct) -> Option<UnaryOp> {
@@ -86,7 +87,7 @@ macro_rules! define_unary_op {
                 }
             }
 
-            fn to_tokens_with_expr(self, expr: Expression, stream: &mut Vec<TokenTree>) {
+            fn write_into_stream_with_expr(self, expr: Expression, stream: &mut Vec<TokenTree>) {
                 match self {
                     $(
                         Self::$name(inner) => unary_op_to_tokens!(stream inner expr $pos),
@@ -96,14 +97,25 @@ macro_rules! define_unary_op {
         }
 
         pub(crate) mod mir_unaryop {
-            use crate::mir::ToMir;
+            use crate::{mir::ToMir, token::PunctToken};
             use super::UnaryOp;
+            use std::fmt;
 
-            #[derive(Debug, Clone, PartialEq)]
+            #[derive(Debug, Clone, Copy, PartialEq, Eq)]
             pub enum MirUnaryOp {
                 $($name),+
             }
 
+            impl fmt::Display for MirUnaryOp {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    match self {
+                        $(
+                            Self::$name => f.write_str(PunctToken::$inner.to_str()),
+                        )+
+                    }
+                }
+            }
+
             impl ToMir for UnaryOp {
                 type Output = MirUnaryOp;
 
@@ -124,8 +136,14 @@ define_unary_op!(
     left Negate: Minus,
 );
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Associativity {
+    Left,
+    Right,
+}
+
 macro_rules! define_binary_op {
-    ($($name:ident : $inner:ident),+ $(,)?) => {
+    ($($name:ident : $inner:ident => $precedence:literal $assoc:ident),+ $(,)?) => {
         ast_item!(
             pub enum BinaryOp {
                 $($name($inner)),+
@@ -133,6 +151,17 @@ macro_rules! define_binary_op {
         );
 
         impl BinaryOp {
+            /// Precedence and associativity of the operator that `punct` would start, if any.
+            /// Higher precedences bind tighter.
+            fn binding(punct: PunctToken) -> Option<(u8, Associativity)> {
+                match punct {
+                    $(
+                        PunctToken::$inner => Some(($precedence, Associativity::$assoc)),
+                    )+
+                    _ => None,
+                }
+            }
+
             fn parse(token_iter: &mut TokenIter, punct: PunctToken) -> SyntaxResult<Option<BinaryOp>> {
                 Ok(match punct {
                     $(
@@ -144,14 +173,25 @@ macro_rules! define_binary_op {
         }
 
         pub(crate) mod mir_binaryop {
-            use crate::mir::ToMir;
+            use crate::{mir::ToMir, token::PunctToken};
             use super::BinaryOp;
+            use std::fmt;
 
-            #[derive(Debug, Clone, PartialEq)]
+            #[derive(Debug, Clone, Copy, PartialEq, Eq)]
             pub enum MirBinaryOp {
                 $($name),+
             }
 
+            impl fmt::Display for MirBinaryOp {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    match self {
+                        $(
+                            Self::$name => f.write_str(PunctToken::$inner.to_str()),
+                        )+
+                    }
+                }
+            }
+
             impl ToMir for BinaryOp {
                 type Output = MirBinaryOp;
 
@@ -167,33 +207,47 @@ macro_rules! define_binary_op {
     }
 }
 
+// Operators are listed from loosest to tightest binding, new operators only need an entry here.
 define_binary_op!(
-    Add: Plus,
-    Subtract: Minus,
-    Multiply: Star,
-    Divide: Slash,
-    Modulo: Percent,
-    Equals: Equals,
-    NotEquals: NotEquals,
-    LessThan: LessThan,
-    LessThanEquals: LessThanEquals,
-    GreaterThan: GreaterThan,
-    GreaterThanEquals: GreaterThanEquals,
-    And: And,
-    Or: Or,
+    Coalesce: NullishCoalescing => 1 Right,
+    Or: Or => 2 Left,
+    And: And => 3 Left,
+    Equals: Equals => 4 Left,
+    NotEquals: NotEquals => 4 Left,
+    LessThan: LessThan => 5 Left,
+    LessThanEquals: LessThanEquals => 5 Left,
+    GreaterThan: GreaterThan => 5 Left,
+    GreaterThanEquals: GreaterThanEquals => 5 Left,
+    Add: Plus => 6 Left,
+    Subtract: Minus => 6 Left,
+    Multiply: Star => 7 Left,
+    Divide: Slash => 7 Left,
+    Modulo: Percent => 7 Left,
 );
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expression {
     Literal(Literal),
+    Null(Null),
     Variable(Path),
     Property(Box<Expression>, Dot, Ident),
     Call(Path, Parenthesis<Punctuated<Expression, Comma>>),
+    Struct(Path, Brace<Punctuated<FieldValue, Comma>>),
     Parenthesized(Box<Parenthesis<Expression>>),
+    Array(Bracket<Punctuated<Expression, Comma>>),
     Index(Box<Expression>, Box<Bracket<Expression>>),
     UnaryOp(UnaryOp, Box<Expression>),
     BinaryOp(Box<Expression>, BinaryOp, Box<Expression>),
+    Cast(Box<Expression>, As, Primitive),
+    Ternary(
+        Box<Expression>,
+        Ternary,
+        Box<Expression>,
+        Colon,
+        Box<Expression>,
+    ),
     Macro(MacroPrefix, Path, Parenthesis<TokenStream>),
+    Match(Box<MatchExpr>),
 }
 
 impl Expression {
@@ -213,7 +267,15 @@ impl Expression {
                         let index = token_iter.parse()?;
                         Self::Index(Box::new(self), Box::new(index)).continue_parsing(token_iter)
                     }
-                    _ => Ok(self),
+                    // Conditions are parenthesized, so a block never directly follows a path.
+                    Delimiter::Brace => {
+                        if let Self::Variable(path) = self {
+                            let fields = token_iter.parse()?;
+                            Self::Struct(path, fields).continue_parsing(token_iter)
+                        } else {
+                            Ok(self)
+                        }
+                    }
                 },
                 TokenTree::Punct(punct) => {
                     let token = punct.inner();
@@ -222,10 +284,8 @@ impl Expression {
                         let ident = token_iter.parse()?;
                         Self::Property(Box::new(self), dot, ident).continue_parsing(token_iter)
                     } else if let Some(op) = UnaryOp::parse_right(*punct) {
-                        Ok(Self::UnaryOp(op, Box::new(self)))
-                    } else if let Some(op) = BinaryOp::parse(token_iter, token)? {
-                        let right = token_iter.parse()?;
-                        Ok(Self::BinaryOp(Box::new(self), op, Box::new(right)))
+                        token_iter.consume();
+                        Self::UnaryOp(op, Box::new(self)).continue_parsing(token_iter)
                     } else {
                         Ok(self)
                     }
@@ -236,54 +296,148 @@ impl Expression {
             Ok(self)
         }
     }
-}
 
-impl Parse for Expression {
-    fn parse(token_iter: &mut TokenIter) -> SyntaxResult<Self> {
+    /// Precedence climbing over [`BinaryOp`], only operators binding at least as tightly as
+    /// `min_precedence` are folded into the returned expression.
+    fn parse_binary(token_iter: &mut TokenIter, min_precedence: u8) -> SyntaxResult<Self> {
+        let mut left = Self::parse_cast(token_iter)?;
+
+        while let Some(TokenTree::Punct(punct)) = token_iter.peek() {
+            let token = punct.inner();
+            let Some((precedence, associativity)) = BinaryOp::binding(token) else {
+                break;
+            };
+
+            if precedence < min_precedence {
+                break;
+            }
+
+            let op = BinaryOp::parse(token_iter, token)?.expect("operator has a binding");
+            let right = Self::parse_binary(
+                token_iter,
+                match associativity {
+                    Associativity::Left => precedence + 1,
+                    Associativity::Right => precedence,
+                },
+            )?;
+
+            left = Self::BinaryOp(Box::new(left), op, Box::new(right));
+        }
+
+        Ok(left)
+    }
+
+    /// `as` binds tighter than every binary operator but looser than unary ones, so `-a as long`
+    /// negates `a` before converting it.
+    fn parse_cast(token_iter: &mut TokenIter) -> SyntaxResult<Self> {
+        let mut value = Self::parse_unary(token_iter)?;
+        while let Some(TokenTree::Ident(ident)) = token_iter.peek() {
+            if !As::is_ident(ident) {
+                break;
+            }
+
+            let as_token = token_iter.parse()?;
+            let ty = token_iter.parse()?;
+            value = Self::Cast(Box::new(value), as_token, ty);
+        }
+        Ok(value)
+    }
+
+    fn parse_unary(token_iter: &mut TokenIter) -> SyntaxResult<Self> {
+        Self::parse_operand(token_iter, false)
+    }
+
+    /// Parses a unary expression, a `negated` number may be one past the maximum of its type so
+    /// `-2147483648` is in range.
+    fn parse_operand(token_iter: &mut TokenIter, negated: bool) -> SyntaxResult<Self> {
         let left = match token_iter.expect_peek()? {
-            TokenTree::Literal(_) => Self::Literal(token_iter.parse()?),
+            TokenTree::Literal(_) => {
+                let literal: Literal = token_iter.parse()?;
+                let range = literal.check_range();
+                let expr = Self::Literal(literal).continue_parsing(token_iter)?;
+                // Only a literal negated as a whole is folded into the minimum of its type.
+                if !(negated && matches!(expr, Self::Literal(_))) {
+                    range.map_err(SyntaxError::OutOfRange)?;
+                }
+                return Ok(expr);
+            }
+            TokenTree::Ident(ident) if Match::is_ident(ident) => {
+                Self::Match(Box::new(token_iter.parse()?))
+            }
+            TokenTree::Ident(ident) if Null::is_ident(ident) => Self::Null(token_iter.parse()?),
             TokenTree::Ident(_) => Self::Variable(token_iter.parse()?),
-            TokenTree::Group(group) => {
-                if group.delimiter() == Delimiter::Parenthesis {
+            TokenTree::Group(group) => match group.delimiter() {
+                Delimiter::Parenthesis => {
                     let inner = token_iter.parse()?;
                     Self::Parenthesized(Box::new(inner))
-                } else {
-                    return token_iter.expect_consume()?.unexpected();
                 }
-            }
+                Delimiter::Bracket => Self::Array(token_iter.parse()?),
+                Delimiter::Brace => return token_iter.expect_consume()?.unexpected(),
+            },
             TokenTree::Punct(punct) => {
                 if MacroPrefix::is_punct(punct) {
                     let prefix_token = token_iter.parse()?;
                     let path = token_iter.parse()?;
                     let inner = token_iter.parse()?;
+                    MacroCall::new(&path, &inner)?;
                     Self::Macro(prefix_token, path, inner)
-                } else if let Some(op) = UnaryOp::parse_left(token_iter.parse()?)? {
-                    let right = token_iter.parse()?;
-                    Self::UnaryOp(op, Box::new(right))
+                } else if let Some(op) = UnaryOp::parse_left(*punct) {
+                    token_iter.consume();
+                    // `- -2147483648` negates the literal twice, it is out of range again.
+                    let negated = matches!(op, UnaryOp::Negate(_)) && !negated;
+                    let right = Self::parse_operand(token_iter, negated)?;
+                    return Ok(Self::UnaryOp(op, Box::new(right)));
                 } else {
                     return token_iter.expect_consume()?.unexpected();
                 }
             }
+            TokenTree::DocComment(_) => return token_iter.expect_consume()?.unexpected(),
         };
 
         left.continue_parsing(token_iter)
     }
 }
 
+impl Parse for Expression {
+    /// `?:` binds looser than every binary operator and groups to the right, so
+    /// `a ? b : c ? d : e` is `a ? b : (c ? d : e)`.
+    fn parse(token_iter: &mut TokenIter) -> SyntaxResult<Self> {
+        let condition = Self::parse_binary(token_iter, 0)?;
+        match token_iter.peek() {
+            Some(TokenTree::Punct(punct)) if Ternary::is_punct(punct) => Ok(Self::Ternary(
+                Box::new(condition),
+                token_iter.parse()?,
+                Box::new(token_iter.parse()?),
+                token_iter.parse()?,
+                Box::new(token_iter.parse()?),
+            )),
+            _ => Ok(condition),
+        }
+    }
+}
+
 impl Spanned for Expression {
     fn span(&self) -> Span {
         match self {
             Self::Literal(literal) => literal.span(),
+            Self::Null(null) => null.span(),
             Self::Variable(variable) => variable.span(),
             Self::Parenthesized(inner) => inner.span(),
             Self::Property(left, _dot, name) => Span::from_start_end(left.span(), name.span()),
             Self::Call(path, args) => Span::from_start_end(path.span(), args.span()),
+            Self::Struct(path, fields) => Span::from_start_end(path.span(), fields.span()),
+            Self::Array(elements) => elements.span(),
             Self::Index(left, index) => Span::from_start_end(left.span(), index.span()),
             Self::BinaryOp(left, _op, right) => Span::from_start_end(left.span(), right.span()),
             Self::UnaryOp(op, expr) => op.span_with_expr(expr),
+            Self::Cast(value, _as, ty) => Span::from_start_end(value.span(), ty.span()),
+            Self::Ternary(condition, _question, _then, _colon, otherwise) => {
+                Span::from_start_end(condition.span(), otherwise.span())
+            }
             Self::Macro(prefix_token, _, inner) => {
                 Span::from_start_end(prefix_token.span(), inner.span())
             }
+            Self::Match(inner) => inner.span(),
         }
     }
 }
@@ -292,6 +446,7 @@ impl ToTokens for Expression {
     fn write_into_stream(self, stream: &mut Vec<TokenTree>) {
         match self {
             Self::Literal(literal) => literal.write_into_stream(stream),
+            Self::Null(null) => null.write_into_stream(stream),
             Self::Variable(variable) => variable.write_into_stream(stream),
             Self::Parenthesized(inner) => inner.write_into_stream(stream),
             Self::Property(left, dot, name) => {
@@ -303,6 +458,11 @@ impl ToTokens for Expression {
                 path.write_into_stream(stream);
                 args.write_into_stream(stream);
             }
+            Self::Struct(path, fields) => {
+                path.write_into_stream(stream);
+                fields.write_into_stream(stream);
+            }
+            Self::Array(elements) => elements.write_into_stream(stream),
             Self::Index(left, index) => {
                 left.write_into_stream(stream);
                 index.write_into_stream(stream);
@@ -312,12 +472,25 @@ impl ToTokens for Expression {
                 op.write_into_stream(stream);
                 right.write_into_stream(stream);
             }
-            Self::UnaryOp(op, expr) => op.to_tokens_with_expr(*expr, stream),
+            Self::UnaryOp(op, expr) => op.write_into_stream_with_expr(*expr, stream),
+            Self::Cast(value, as_token, ty) => {
+                value.write_into_stream(stream);
+                as_token.write_into_stream(stream);
+                ty.write_into_stream(stream);
+            }
+            Self::Ternary(condition, question, then, colon, otherwise) => {
+                condition.write_into_stream(stream);
+                question.write_into_stream(stream);
+                then.write_into_stream(stream);
+                colon.write_into_stream(stream);
+                otherwise.write_into_stream(stream);
+            }
             Self::Macro(prefix_token, path, inner) => {
                 prefix_token.write_into_stream(stream);
                 path.write_into_stream(stream);
                 inner.write_into_stream(stream);
             }
+            Self::Match(inner) => inner.write_into_stream(stream),
         }
     }
 }
@@ -326,9 +499,182 @@ trait UnexpectedToken: Sized + ToTokenTree {
     fn unexpected<T>(self) -> SyntaxResult<T> {
         Err(SyntaxError::UnexpectedToken(
             self.to_token_tree(),
-            "expression",
+            "an expression",
         ))
     }
 }
 
 impl<T: ToTokenTree> UnexpectedToken for T {}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        hir::{Expression, ToTokens},
+        mir::{MirExpression, MirExpressionKind, ToMir},
+        parse_str,
+        token::XID,
+        SyntaxError, TokenIter,
+    };
+
+    fn parse_mir(input: &str) -> eyre::Result<MirExpression<XID>> {
+        let tokens = parse_str(input)?;
+        let expr: Expression = TokenIter::from(&tokens).parse()?;
+        Ok(expr.into_mir())
+    }
+
+    fn shape(expr: &MirExpression<XID>) -> String {
+        match &expr.kind {
+            MirExpressionKind::Literal(literal) => format!("{:?}", literal),
+            MirExpressionKind::Variable(path) => {
+                path.iter().map(XID::inner).collect::<Vec<_>>().join("::")
+            }
+            MirExpressionKind::UnaryOp(op, expr) => format!("({:?} {})", op, shape(expr)),
+            MirExpressionKind::BinaryOp(left, op, right) => {
+                format!("({} {:?} {})", shape(left), op, shape(right))
+            }
+            MirExpressionKind::Array(elements) => {
+                let elements: Vec<String> = elements.iter().map(shape).collect();
+                format!("[{}]", elements.join(" "))
+            }
+            MirExpressionKind::Index(left, index) => {
+                format!("({} Index {})", shape(left), shape(index))
+            }
+            MirExpressionKind::Cast(value, ty) => format!("({} as {:?})", shape(value), ty),
+            MirExpressionKind::Null => "null".to_string(),
+            MirExpressionKind::Ternary(condition, then, otherwise) => {
+                format!(
+                    "({} ? {} : {})",
+                    shape(condition),
+                    shape(then),
+                    shape(otherwise)
+                )
+            }
+            other => panic!("unexpected expression in shape test: {:?}", other),
+        }
+    }
+
+    fn assert_shape(input: &str, expected: &str) -> eyre::Result<()> {
+        assert_eq!(
+            shape(&parse_mir(input)?),
+            expected,
+            "while parsing `{}`",
+            input
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn multiplicative_binds_tighter() -> eyre::Result<()> {
+        assert_shape("a * b + c", "((a Multiply b) Add c)")?;
+        assert_shape("a + b * c", "(a Add (b Multiply c))")?;
+        assert_shape("1 + a / (2 * cat)", "(1i Add (a Divide (2i Multiply cat)))")
+    }
+
+    #[test]
+    fn left_associative() -> eyre::Result<()> {
+        assert_shape("a / b / c", "((a Divide b) Divide c)")?;
+        assert_shape("a + b + c + d", "(((a Add b) Add c) Add d)")?;
+        assert_shape("a % b * c", "((a Modulo b) Multiply c)")
+    }
+
+    #[test]
+    fn logical_and_comparison_levels() -> eyre::Result<()> {
+        assert_shape("a || b && c", "(a Or (b And c))")?;
+        assert_shape(
+            "a < b + 1 && b == c || d",
+            "(((a LessThan (b Add 1i)) And (b Equals c)) Or d)",
+        )?;
+        assert_shape("a == b < c", "(a Equals (b LessThan c))")
+    }
+
+    #[test]
+    fn unary_binds_tighter_than_binary() -> eyre::Result<()> {
+        assert_shape("!a && b", "((Not a) And b)")?;
+        assert_shape("!(a || b) && c", "((Not (a Or b)) And c)")
+    }
+
+    #[test]
+    fn minus_is_subtraction_or_negation() -> eyre::Result<()> {
+        assert_shape("a-1", "(a Subtract 1i)")?;
+        assert_shape("a - b - c", "((a Subtract b) Subtract c)")?;
+        assert_shape("x - -1", "(x Subtract -1i)")?;
+        assert_shape("x--y", "(x Subtract (Negate y))")?;
+        assert_shape("-a * b", "((Negate a) Multiply b)")
+    }
+
+    #[test]
+    fn negated_literals_are_folded() -> eyre::Result<()> {
+        assert_shape("-5", "-5i")?;
+        assert_shape("-(2.5)", "-2.5f")?;
+        assert_shape("- -5l", "5l")?;
+        assert_shape("-0x80000000", "-2147483648i")?;
+        assert_shape("1 + -2b", "(1i Add -2b)")
+    }
+
+    #[test]
+    fn casts_bind_tighter_than_binary() -> eyre::Result<()> {
+        assert_shape("a + b as long * c", "(a Add ((b as Long) Multiply c))")?;
+        assert_shape("-a as long", "((Negate a) as Long)")?;
+        assert_shape("a as short as int", "((a as Short) as Int)")
+    }
+
+    #[test]
+    fn negated_minimums_are_in_range() -> eyre::Result<()> {
+        assert_shape("-128b", "-128b")?;
+        assert_shape("-32768s", "-32768s")?;
+        assert_shape("-2147483648", "-2147483648i")?;
+        assert_shape("-9223372036854775808l", "-9223372036854775808l")?;
+        assert_shape("a - -2147483648", "(a Subtract -2147483648i)")?;
+
+        for input in [
+            "2147483648",
+            "a - 128b",
+            "-(32768s)",
+            "- -2147483648",
+            "-9223372036854775808l[0]",
+        ] {
+            let tokens = parse_str(input)?;
+            let result: Result<Expression, _> = TokenIter::from(&tokens).parse();
+            assert!(
+                matches!(result, Err(SyntaxError::OutOfRange(_))),
+                "expected `{}` to be out of range",
+                input
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn negated_minimums_print_as_written() -> eyre::Result<()> {
+        for (input, expected) in [
+            ("-2147483648", "- 2147483648i"),
+            ("a - -128b", "a - - 128b"),
+        ] {
+            let tokens = parse_str(input)?;
+            let expr: Expression = TokenIter::from(&tokens).parse()?;
+            let printed = format!("{:?}", expr.into_tokens());
+            assert_eq!(printed, expected);
+
+            let tokens = parse_str(&printed)?;
+            let reparsed: Expression = TokenIter::from(&tokens).parse()?;
+            assert_eq!(format!("{:?}", reparsed.into_tokens()), printed);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn ternaries_and_coalescing_bind_loosest() -> eyre::Result<()> {
+        assert_shape("a || b ? c + 1 : d", "((a Or b) ? (c Add 1i) : d)")?;
+        assert_shape("a ? b : c ? d : e", "(a ? b : (c ? d : e))")?;
+        assert_shape("a ?? b || c", "(a Coalesce (b Or c))")?;
+        assert_shape("a ?? b ?? null", "(a Coalesce (b Coalesce null))")
+    }
+
+    #[test]
+    fn arrays_and_indices() -> eyre::Result<()> {
+        assert_shape("[1, a, b + 1]", "[1i a (b Add 1i)]")?;
+        assert_shape("[]", "[]")?;
+        assert_shape("a[i + 1] * 2", "((a Index (i Add 1i)) Multiply 2i)")?;
+        assert_shape("[a, b][1]", "([a b] Index 1i)")
+    }
+}
@@ -1,8 +1,8 @@
 use super::Statement;
 use crate::{
     ast_item,
-    hir::{Block, Declaration, Expression},
-    token::{For, Parenthesis, Semicolon},
+    hir::{Block, Declaration, Expression, Label},
+    token::{For, Parenthesis, Semicolon, Spread},
     Span, Spanned,
 };
 
@@ -22,6 +22,8 @@ impl Spanned for ForLoopInner {
 
 ast_item!(
     pub struct ForLoop {
+        label: Option<Label>,
+        spread: Option<Spread>,
         for_token: For,
         inner: Parenthesis<ForLoopInner>,
         block: Block,
@@ -30,6 +32,13 @@ ast_item!(
 
 impl Spanned for ForLoop {
     fn span(&self) -> Span {
-        Span::from_start_end(self.for_token.span(), self.block.span())
+        let start = if let Some(label) = &self.label {
+            label.span()
+        } else if let Some(spread_token) = self.spread {
+            spread_token.span()
+        } else {
+            self.for_token.span()
+        };
+        Span::from_start_end(start, self.block.span())
     }
 }
@@ -0,0 +1,258 @@
+use super::{Expression, Path, Punctuated};
+use crate::{
+    token::{Comma, LiteralType, Parenthesis, XID},
+    Span, Spanned, SyntaxError, SyntaxResult, TokenIter, TokenStream, TokenTree,
+};
+
+/// The arguments of an expression macro like `$command("say hi")`, they are checked when the
+/// macro is parsed.
+pub enum MacroCall {
+    /// A command run for its result, with the span of the string it is written in.
+    Command(Vec<CommandSegment>, Span),
+    /// The NBT path a number is read from.
+    Nbt(String),
+    /// The players to show the text to and the values it is made of.
+    Tellraw(String, Vec<Expression>),
+}
+
+/// A piece of a `$command`. Values are interpolated as `${name}` or `${player.name}`, braces
+/// without a `$` are kept as they are so JSON and NBT in commands need no escaping.
+pub enum CommandSegment {
+    Text(String),
+    /// A variable and the fields read from it.
+    Value(Vec<XID>),
+}
+
+impl MacroCall {
+    pub fn new(path: &Path, args: &Parenthesis<TokenStream>) -> SyntaxResult<Self> {
+        let name = path
+            .clone()
+            .into_tokens()
+            .iter()
+            .map(|ident| ident.inner().to_string())
+            .collect::<Vec<_>>()
+            .join("::");
+
+        match name.as_str() {
+            "command" => {
+                let (command, span) = string_argument(&name, args)?;
+                Ok(Self::Command(command_segments(&command, span)?, span))
+            }
+            "nbt" => {
+                let (path, span) = string_argument(&name, args)?;
+                if !is_nbt_path(&path) {
+                    return Err(SyntaxError::InvalidMacroArguments(
+                        name,
+                        span,
+                        "where to read from and the path, like `\"entity @s Pos[0]\"`",
+                    ));
+                }
+                Ok(Self::Nbt(path))
+            }
+            "tellraw" => {
+                // Missing arguments are reported at the closing parenthesis.
+                let span = args.span();
+                let close = Span::new(span.file, span.index + span.width - 1, 1);
+                let args: Punctuated<Expression, Comma> =
+                    TokenIter::new(args.contents(), close).parse()?;
+                let mut args = args.into_tokens().into_iter();
+
+                let targets = args.next();
+                if let Some(Expression::Literal(literal)) = &targets {
+                    if let LiteralType::String(targets) = literal.inner() {
+                        return Ok(Self::Tellraw(targets.clone(), args.collect()));
+                    }
+                }
+
+                Err(SyntaxError::InvalidMacroArguments(
+                    name,
+                    targets.map_or(close, |targets| targets.span()),
+                    "a string selecting the players first",
+                ))
+            }
+            _ => Err(SyntaxError::UnknownMacro(name, path.span())),
+        }
+    }
+}
+
+/// The only argument of `$command` and `$nbt`, a string.
+fn string_argument(name: &str, args: &Parenthesis<TokenStream>) -> SyntaxResult<(String, Span)> {
+    if let [TokenTree::Literal(literal)] = args.contents().0.as_slice() {
+        if let LiteralType::String(value) = literal.inner() {
+            return Ok((value.clone(), literal.span()));
+        }
+    }
+
+    Err(SyntaxError::InvalidMacroArguments(
+        name.to_string(),
+        args.span(),
+        "a single string",
+    ))
+}
+
+fn command_segments(command: &str, span: Span) -> SyntaxResult<Vec<CommandSegment>> {
+    // Each line of a function file is its own command.
+    if command.contains(['\n', '\r']) {
+        return Err(SyntaxError::InvalidMacroArguments(
+            "command".to_string(),
+            span,
+            "a single line",
+        ));
+    }
+
+    let invalid = || {
+        SyntaxError::InvalidMacroArguments(
+            "command".to_string(),
+            span,
+            "a variable or a field of one after `${`, like `${player.name}`",
+        )
+    };
+
+    let mut segments = vec![];
+    let mut rest = command;
+    while let Some(start) = rest.find("${") {
+        if start > 0 {
+            segments.push(CommandSegment::Text(rest[..start].to_string()));
+        }
+
+        let (inner, after) = rest[start + 2..].split_once('}').ok_or_else(invalid)?;
+        let names = inner
+            .split('.')
+            .map(|name| name.parse().map_err(|_| invalid()))
+            .collect::<SyntaxResult<_>>()?;
+        segments.push(CommandSegment::Value(names));
+        rest = after;
+    }
+
+    if !rest.is_empty() {
+        segments.push(CommandSegment::Text(rest.to_string()));
+    }
+    Ok(segments)
+}
+
+/// Whether `path` is where `data get` reads from followed by the path inside it, like
+/// `entity @s Pos[0]` or `block ~ ~ ~ Items[0].Count`.
+fn is_nbt_path(path: &str) -> bool {
+    let words: Vec<&str> = path.split_whitespace().collect();
+    match words.first() {
+        Some(&"block") => words.len() >= 5,
+        Some(&"entity" | &"storage") => words.len() >= 3,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CommandSegment, MacroCall};
+    use crate::{
+        hir::{Expression, Statement},
+        parse_str,
+        token::XID,
+        SyntaxError, TokenIter,
+    };
+
+    fn parse_expression(input: &str) -> eyre::Result<Result<Expression, SyntaxError>> {
+        let tokens = parse_str(input)?;
+        Ok(TokenIter::from(&tokens).parse())
+    }
+
+    #[test]
+    fn invalid_arguments_are_reported() -> eyre::Result<()> {
+        for (input, name) in [
+            ("$command()", "command"),
+            ("$command(1)", "command"),
+            ("$command(\"say\" \"hi\")", "command"),
+            ("$tellraw(name, \"hi\")", "tellraw"),
+            ("$tellraw()", "tellraw"),
+            ("$command(\"say ${name\")", "command"),
+            ("$command(\"say ${}\")", "command"),
+            ("$command(\"say ${a..b}\")", "command"),
+            ("$command(\"say a\\nb\")", "command"),
+            ("$command(\"say a\nb\")", "command"),
+            ("$command(\"say a\\rb\")", "command"),
+        ] {
+            match parse_expression(input)? {
+                Err(SyntaxError::InvalidMacroArguments(found, _, _)) => {
+                    assert_eq!(found, name, "{}", input)
+                }
+                other => panic!("expected {} to be invalid, got {:?}", input, other),
+            }
+        }
+
+        let input = "$tellraw(1 + 2, \"hi\")";
+        let Err(SyntaxError::InvalidMacroArguments(_, span, _)) = parse_expression(input)? else {
+            panic!("expected the targets of {} to be invalid", input);
+        };
+        assert_eq!(&input[span.index..span.index + span.width], "1 + 2");
+        Ok(())
+    }
+
+    #[test]
+    fn commands_interpolate_only_marked_values() -> eyre::Result<()> {
+        let Ok(Expression::Macro(_, path, args)) =
+            parse_expression("$command(\"data merge entity @s {Invulnerable:1b} ${a.b}{c}\")")?
+        else {
+            panic!("expected a macro");
+        };
+        let Ok(MacroCall::Command(segments, _)) = MacroCall::new(&path, &args) else {
+            panic!("expected a command");
+        };
+
+        let segments: Vec<String> = segments
+            .iter()
+            .map(|segment| match segment {
+                CommandSegment::Text(text) => text.clone(),
+                CommandSegment::Value(names) => format!(
+                    "<{}>",
+                    names.iter().map(XID::inner).collect::<Vec<_>>().join(".")
+                ),
+            })
+            .collect();
+        assert_eq!(
+            segments,
+            ["data merge entity @s {Invulnerable:1b} ", "<a.b>", "{c}"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn nbt_paths_name_their_source() -> eyre::Result<()> {
+        for input in [
+            "$nbt(\"entity @s Pos[0]\")",
+            "$nbt(\"block ~ ~ ~ Items[0].Count\")",
+            "$nbt(\"storage test:data values[1]\")",
+        ] {
+            assert!(parse_expression(input)?.is_ok(), "{}", input);
+        }
+
+        for input in [
+            "$nbt(\"Pos[0]\")",
+            "$nbt(\"\")",
+            "$nbt(\"entity @s\")",
+            "$nbt(\"block ~ ~ ~\")",
+            "$nbt(entity)",
+        ] {
+            let result = parse_expression(input)?;
+            assert!(
+                matches!(result, Err(SyntaxError::InvalidMacroArguments(ref name, _, _)) if name == "nbt"),
+                "expected {} to be invalid, got {:?}",
+                input,
+                result
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_macros_are_reported() -> eyre::Result<()> {
+        let result = parse_expression("$conversion(1)")?;
+        assert!(matches!(result, Err(SyntaxError::UnknownMacro(name, _)) if name == "conversion"));
+        let result = parse_expression("$a::b(1)")?;
+        assert!(matches!(result, Err(SyntaxError::UnknownMacro(name, _)) if name == "a::b"));
+
+        let tokens = parse_str("$command { say hi }")?;
+        let result: Result<Statement, _> = TokenIter::from(&tokens).parse();
+        assert!(matches!(result, Err(SyntaxError::StatementMacro(_))));
+        Ok(())
+    }
+}
@@ -0,0 +1,104 @@
+use super::{Block, Expression, Path, Punctuated};
+use crate::{
+    ast_item,
+    token::{Brace, Comma, Delimiter, FatArrow, Ident, Match, Parenthesis, ToTokenTree},
+    Parse, Span, Spanned, SyntaxError, SyntaxResult, TokenIter, TokenTree,
+};
+
+/// Either `_`, matching anything, or a path to an enum variant followed by names for its
+/// payload.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    pub path: Path,
+    pub bindings: Option<Parenthesis<Punctuated<Ident, Comma>>>,
+}
+
+impl Pattern {
+    pub fn is_wildcard(&self) -> bool {
+        self.path.len() == 1 && self.path.first_token().inner() == "_" && self.bindings.is_none()
+    }
+}
+
+impl Parse for Pattern {
+    fn parse(token_iter: &mut TokenIter) -> SyntaxResult<Self> {
+        let path: Path = token_iter.parse()?;
+        let pattern = Self {
+            path,
+            bindings: token_iter.parse()?,
+        };
+
+        if pattern.path.len() < 2 && !pattern.is_wildcard() {
+            return Err(SyntaxError::UnexpectedToken(
+                pattern.path.first_token().clone().to_token_tree(),
+                "`_` or a path to an enum variant",
+            ));
+        }
+
+        Ok(pattern)
+    }
+}
+
+impl Spanned for Pattern {
+    fn span(&self) -> Span {
+        match &self.bindings {
+            Some(bindings) => Span::from_start_end(self.path.span(), bindings.span()),
+            None => self.path.span(),
+        }
+    }
+}
+
+impl super::ToTokens for Pattern {
+    fn write_into_stream(self, stream: &mut Vec<TokenTree>) {
+        self.path.write_into_stream(stream);
+        if let Some(bindings) = self.bindings {
+            bindings.write_into_stream(stream);
+        }
+    }
+}
+
+ast_item!(
+    pub enum ArmBody {
+        Block(Block),
+        Expression(Expression),
+    }
+);
+
+impl Parse for ArmBody {
+    fn parse(token_iter: &mut TokenIter) -> SyntaxResult<Self> {
+        match token_iter.expect_peek()? {
+            TokenTree::Group(group) if group.delimiter() == Delimiter::Brace => {
+                Ok(Self::Block(token_iter.parse()?))
+            }
+            _ => Ok(Self::Expression(token_iter.parse()?)),
+        }
+    }
+}
+
+ast_item!(
+    pub struct MatchArm {
+        pattern: Pattern,
+        arrow: FatArrow,
+        body: ArmBody,
+        comma: Option<Comma>,
+    }
+);
+
+impl Spanned for MatchArm {
+    fn span(&self) -> Span {
+        Span::from_start_end(self.pattern.span(), self.body.span())
+    }
+}
+
+ast_item!(
+    pub struct MatchExpr {
+        match_token: Match,
+        scrutinee: Parenthesis<Expression>,
+        arms: Brace<Vec<MatchArm>>,
+    }
+);
+
+impl Spanned for MatchExpr {
+    fn span(&self) -> Span {
+        Span::from_start_end(self.match_token.span(), self.arms.span())
+    }
+}
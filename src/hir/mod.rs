@@ -1,14 +1,19 @@
 pub use self::{
-    assign::Assignment,
+    assign::{AssignOp, Assignment},
+    control::{check_loop_control, BreakStatement, ContinueStatement, Label, LoopControlError},
     decl::{DeclStart, Declaration},
     expr::Expression,
     func::{FunctionArg, FunctionDecl},
     punctuated::Punctuated,
+    r#enum::{EnumDecl, EnumVariant},
     r#for::ForLoop,
     r#if::{ElseBlock, IfBlock},
+    r#macro::{CommandSegment, MacroCall},
+    r#match::{ArmBody, MatchArm, MatchExpr, Pattern},
+    r#struct::{FieldValue, StructDecl, StructField},
     r#type::{Primitive, Type},
     r#while::WhileLoop,
-    stmt::Statement,
+    stmt::{parse_statements, Statement},
 };
 use crate::{
     token::{Brace, Ident, PathSeparator, ToTokenTree},
@@ -16,15 +21,20 @@ use crate::{
 };
 
 mod assign;
+mod control;
 mod decl;
+mod r#enum;
 mod expr;
 mod r#for;
 mod func;
 mod r#if;
+mod r#macro;
+mod r#match;
 mod parse;
 pub(super) mod prelude;
 mod punctuated;
 mod stmt;
+mod r#struct;
 mod r#type;
 mod r#while;
 
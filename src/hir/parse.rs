@@ -1,16 +1,84 @@
-use crate::{token::Token, SyntaxError, SyntaxResult, TokenStream, TokenTree};
-use std::{collections::VecDeque, slice::Iter};
+use crate::{
+    token::{Delimiter, Parenthesis, Token},
+    FileId, Span, Spanned, SyntaxError, SyntaxResult, TokenStream, TokenTree,
+};
+use std::{collections::VecDeque, iter::Enumerate, slice::Iter};
 
 pub struct TokenIter<'a> {
-    iterator: Iter<'a, TokenTree>,
-    buffer: VecDeque<TokenTree>,
+    tokens: &'a TokenStream,
+    iterator: Enumerate<Iter<'a, TokenTree>>,
+    /// Peeked tokens and their indices in the stream.
+    buffer: VecDeque<(usize, TokenTree)>,
+    /// Where running out of tokens is reported.
+    end: Span,
+    consumed: usize,
+    /// Errors parsing continued after, only collected by [`TokenIter::recovering`] iterators.
+    recovered: Option<Vec<SyntaxError>>,
 }
 
-impl TokenIter<'_> {
+impl<'a> TokenIter<'a> {
+    pub(crate) fn new(tokens: &'a TokenStream, end: Span) -> Self {
+        Self {
+            tokens,
+            iterator: tokens.0.iter().enumerate(),
+            buffer: VecDeque::new(),
+            end,
+            consumed: 0,
+            recovered: None,
+        }
+    }
+
+    /// Makes parsers which can skip past errors, like [`Statement`](super::Statement)s, continue
+    /// after them. The errors are collected instead, see [`TokenIter::take_recovered`].
+    pub(crate) fn recovering(mut self) -> Self {
+        self.recovered = Some(vec![]);
+        self
+    }
+
+    /// Parses the tokens inside a group, which recovers from errors if this does.
+    pub(crate) fn parse_group<T: Parse>(
+        &mut self,
+        tokens: &TokenStream,
+        end: Span,
+    ) -> SyntaxResult<T> {
+        let mut inner = TokenIter::new(tokens, end);
+        inner.recovered = self.recovered.as_ref().map(|_| vec![]);
+        let result = inner.parse();
+        if let (Some(recovered), Some(inner)) = (&mut self.recovered, inner.recovered) {
+            recovered.extend(inner);
+        }
+        result
+    }
+
+    /// Collects `err` if this recovers from errors, otherwise it is returned.
+    pub(crate) fn recover(&mut self, err: SyntaxError) -> SyntaxResult<()> {
+        match &mut self.recovered {
+            Some(recovered) => {
+                recovered.push(err);
+                Ok(())
+            }
+            None => Err(err),
+        }
+    }
+
+    pub(crate) fn take_recovered(&mut self) -> Vec<SyntaxError> {
+        self.recovered
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    /// Doc comments are skipped, they are not part of the syntax.
+    fn next_token(&mut self) -> Option<(usize, TokenTree)> {
+        self.iterator
+            .find(|(_, token)| !matches!(token, TokenTree::DocComment(_)))
+            .map(|(index, token)| (index, token.clone()))
+    }
+
     fn fill(&mut self, amount: usize) {
         for _ in 0..amount {
-            if let Some(item) = self.iterator.next() {
-                self.buffer.push_back(item.clone())
+            if let Some(item) = self.next_token() {
+                self.buffer.push_back(item)
             }
         }
     }
@@ -23,7 +91,7 @@ impl TokenIter<'_> {
             self.fill(inc_index - buf_len);
         }
 
-        self.buffer.get(index)
+        self.buffer.get(index).map(|(_, token)| token)
     }
 
     pub(crate) fn peek(&mut self) -> Option<&TokenTree> {
@@ -31,15 +99,29 @@ impl TokenIter<'_> {
     }
 
     pub(crate) fn consume(&mut self) -> Option<TokenTree> {
-        if self.buffer.is_empty() {
-            self.iterator.next().cloned()
+        let (index, token) = if self.buffer.is_empty() {
+            self.next_token()
         } else {
             self.buffer.pop_front()
-        }
+        }?;
+        self.consumed = index + 1;
+        Some(token)
+    }
+
+    /// How many tokens of the stream have been consumed so far, counting the skipped doc comments
+    /// before them. The last token consumed is at the index before this.
+    pub(crate) fn consumed(&self) -> usize {
+        self.consumed
+    }
+
+    pub(crate) fn last_consumed(&self) -> Option<&'a TokenTree> {
+        let tokens = self.tokens;
+        self.consumed.checked_sub(1).map(|index| &tokens.0[index])
     }
 
     pub(crate) fn expect_peek_ahead(&mut self, index: usize) -> SyntaxResult<&TokenTree> {
-        self.peek_ahead(index).ok_or(SyntaxError::EarlyEof)
+        let end = self.end;
+        self.peek_ahead(index).ok_or(SyntaxError::EarlyEof(end))
     }
 
     pub(crate) fn expect_peek(&mut self) -> SyntaxResult<&TokenTree> {
@@ -47,7 +129,7 @@ impl TokenIter<'_> {
     }
 
     pub(crate) fn expect_consume(&mut self) -> SyntaxResult<TokenTree> {
-        self.consume().ok_or(SyntaxError::EarlyEof)
+        self.consume().ok_or(SyntaxError::EarlyEof(self.end))
     }
 
     pub fn parse<T: Parse>(&mut self) -> SyntaxResult<T> {
@@ -57,15 +139,29 @@ impl TokenIter<'_> {
 
 impl<'a> From<&'a TokenStream> for TokenIter<'a> {
     fn from(value: &'a TokenStream) -> Self {
-        Self {
-            iterator: value.0.iter(),
-            buffer: VecDeque::new(),
-        }
+        let end = value
+            .0
+            .last()
+            .map_or(Span::new(FileId::default(), 0, 0), |token| {
+                token.span().end()
+            });
+        Self::new(value, end)
     }
 }
 
 pub trait Parse: Sized {
     fn parse(token_iter: &mut TokenIter) -> SyntaxResult<Self>;
+
+    /// Parses items until the tokens run out, this is how `Vec<Self>` is parsed.
+    fn parse_all(token_iter: &mut TokenIter) -> SyntaxResult<Vec<Self>> {
+        let mut items = vec![];
+
+        while token_iter.peek().is_some() {
+            items.push(token_iter.parse()?);
+        }
+
+        Ok(items)
+    }
 }
 
 impl<T: Token> Parse for T {
@@ -94,15 +190,20 @@ impl<T: Token> Parse for Option<T> {
     }
 }
 
-impl<T: Parse> Parse for Vec<T> {
+impl<T: Parse> Parse for Option<Parenthesis<T>> {
     fn parse(token_iter: &mut TokenIter) -> SyntaxResult<Self> {
-        let mut items = vec![];
-
-        while let Some(_) = token_iter.peek() {
-            items.push(token_iter.parse()?);
+        match token_iter.peek() {
+            Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis => {
+                Ok(Some(token_iter.parse()?))
+            }
+            _ => Ok(None),
         }
+    }
+}
 
-        Ok(items)
+impl<T: Parse> Parse for Vec<T> {
+    fn parse(token_iter: &mut TokenIter) -> SyntaxResult<Self> {
+        T::parse_all(token_iter)
     }
 }
 
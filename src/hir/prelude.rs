@@ -1,17 +1,48 @@
 pub use super::parse::{Parse, TokenIter};
-use crate::TokenTree;
+use crate::{diagnostic::Diagnostic, ParseError, Span, Spanned, TokenTree};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum SyntaxError {
-    #[error("Unexpected token '{0:?}', expected {1}")]
+    #[error("Unexpected token, expected {1}")]
     UnexpectedToken(TokenTree, &'static str),
     #[error("Unexpected end of file")]
-    EarlyEof,
+    EarlyEof(Span),
+    #[error("Unknown macro `${0}`")]
+    UnknownMacro(String, Span),
+    #[error("Invalid arguments to `${0}`")]
+    InvalidMacroArguments(String, Span, &'static str),
+    #[error("Statement macros are not supported")]
+    StatementMacro(Span),
+    /// A number which is only in range negated, like `2147483648`.
+    #[error(transparent)]
+    OutOfRange(ParseError),
 }
 
 pub type SyntaxResult<T> = Result<T, SyntaxError>;
 
+impl From<SyntaxError> for Diagnostic {
+    fn from(err: SyntaxError) -> Self {
+        let diagnostic = Diagnostic::error(&err);
+        match err {
+            SyntaxError::UnexpectedToken(token, expected) => {
+                diagnostic.with_primary(token.span(), format!("expected {}", expected))
+            }
+            SyntaxError::EarlyEof(span) => diagnostic.with_primary(span, "expected more tokens"),
+            SyntaxError::UnknownMacro(_, span) => diagnostic
+                .with_primary(span, "")
+                .with_help("the macros are `$command`, `$nbt` and `$tellraw`"),
+            SyntaxError::InvalidMacroArguments(_, span, expected) => {
+                diagnostic.with_primary(span, format!("expected {}", expected))
+            }
+            SyntaxError::StatementMacro(span) => diagnostic
+                .with_primary(span, "")
+                .with_help("macros are called with parentheses, like `$command(\"say hi\");`"),
+            SyntaxError::OutOfRange(err) => err.into(),
+        }
+    }
+}
+
 #[macro_export]
 #[doc(hidden)]
 macro_rules! _parse_field {
@@ -23,7 +23,7 @@ impl<T, P> Punctuated<T, P> {
 
     pub fn first_token(&self) -> &T {
         if let Some((token, _)) = self.inner.first() {
-            &token
+            token
         } else {
             self.last.as_ref().expect("Punctuated should not be empty")
         }
@@ -53,7 +53,7 @@ impl<T: Parse, P: Token> Parse for Punctuated<T, P> {
                         break;
                     }
                 }
-                Err(SyntaxError::EarlyEof) => break,
+                Err(SyntaxError::EarlyEof(_)) => break,
                 Err(other_err) => return Err(other_err),
             }
         }
@@ -1,28 +1,35 @@
 use super::{
-    Assignment, Block, Declaration, Expression, ForLoop, FunctionDecl, IfBlock, Path, WhileLoop,
+    AssignOp, Assignment, Block, BreakStatement, ContinueStatement, Declaration, EnumDecl,
+    Expression, ForLoop, FunctionDecl, IfBlock, MatchExpr, Path, StructDecl, WhileLoop,
 };
 use crate::{
     ast_item,
+    diagnostic::Diagnostics,
     token::{
-        Assign, Brace, Colon, Delimiter, For, Function, If, Import, Let, MacroPrefix,
-        PathSeparator, Return, Semicolon, Static, Unsafe, While,
+        Brace, Break, Colon, Continue, Delimiter, Enum, For, Function, If, Import, Let,
+        MacroPrefix, Match, PathSeparator, PunctToken, Return, Semicolon, Spread, Static, Struct,
+        Unsafe, While,
     },
-    Parse, Span, SyntaxResult, TokenIter, TokenStream, TokenTree,
+    Parse, Span, Spanned, SyntaxError, SyntaxResult, TokenIter, TokenStream, TokenTree,
 };
 
 ast_item!(
     pub enum Statement {
         Block(Block),
         Function(FunctionDecl),
+        Struct(StructDecl),
+        Enum(EnumDecl),
         Declaration(Declaration),
         Expression((Expression, Semicolon)),
         Assignment(Assignment),
         Return((Return, Expression, Semicolon)),
         While(WhileLoop),
         If(IfBlock),
+        Match(MatchExpr),
         For(Box<ForLoop>),
+        Break(BreakStatement),
+        Continue(ContinueStatement),
         Unsafe((Unsafe, Block)),
-        Macro((MacroPrefix, Path, Brace<TokenStream>)),
         Import((Import, Path, Semicolon)),
     }
 );
@@ -60,18 +67,105 @@ fn is_stmt_macro(
     }
 }
 
+enum LoopKind {
+    While,
+    For,
+}
+
+/// Finds the loop starting at the next token, which may be preceded by a label and `spread`.
+fn loop_kind(token_iter: &mut TokenIter) -> Option<LoopKind> {
+    let mut index = 0;
+    if let Some(TokenTree::Ident(_)) = token_iter.peek() {
+        if let Some(TokenTree::Punct(punct)) = token_iter.peek_ahead(1) {
+            if Colon::is_punct(punct) {
+                index = 2;
+            }
+        }
+    }
+
+    let mut keyword = |index| match token_iter.peek_ahead(index) {
+        Some(TokenTree::Ident(ident)) => Some(ident.clone()),
+        _ => None,
+    };
+
+    let mut ident = keyword(index)?;
+    if Spread::is_ident(&ident) {
+        ident = keyword(index + 1)?;
+    }
+
+    if While::is_ident(&ident) {
+        Some(LoopKind::While)
+    } else if For::is_ident(&ident) {
+        Some(LoopKind::For)
+    } else {
+        None
+    }
+}
+
+/// Parses every statement in `tokens`. After an error the rest of the statement is skipped, so
+/// the following statements, in blocks too, are still parsed and their errors reported as well.
+pub fn parse_statements(tokens: &TokenStream, diagnostics: &mut Diagnostics) -> Vec<Statement> {
+    let mut token_iter = TokenIter::from(tokens).recovering();
+    let statements = Statement::parse_all(&mut token_iter);
+    diagnostics.extend(token_iter.take_recovered());
+    statements.unwrap_or_else(|err| {
+        diagnostics.push(err);
+        vec![]
+    })
+}
+
+/// Statements end with a `;` or a block.
+fn ends_statement(token: &TokenTree) -> bool {
+    match token {
+        TokenTree::Punct(punct) => punct.inner() == PunctToken::Semicolon,
+        TokenTree::Group(group) => group.delimiter() == Delimiter::Brace,
+        _ => false,
+    }
+}
+
 impl Parse for Statement {
-    fn parse(token_iter: &mut TokenIter) -> SyntaxResult<Self> {
-        match token_iter.expect_peek()? {
-            TokenTree::Punct(punct) => {
-                if MacroPrefix::is_punct(punct) && is_stmt_macro(token_iter, 1, true)? {
-                    return Ok(Self::Macro(token_iter.parse()?));
+    fn parse_all(token_iter: &mut TokenIter) -> SyntaxResult<Vec<Self>> {
+        let mut statements = vec![];
+
+        while token_iter.peek().is_some() {
+            let start = token_iter.consumed();
+            match token_iter.parse() {
+                Ok(statement) => statements.push(statement),
+                Err(err) => {
+                    token_iter.recover(err)?;
+
+                    let mut at_boundary = token_iter.consumed() > start
+                        && token_iter.last_consumed().is_some_and(ends_statement);
+                    while !at_boundary {
+                        match token_iter.consume() {
+                            Some(token) => at_boundary = ends_statement(&token),
+                            None => break,
+                        }
+                    }
                 }
             }
-            TokenTree::Group(group) => {
-                if group.delimiter() == Delimiter::Brace {
-                    return Ok(Self::Block(token_iter.parse()?));
-                }
+        }
+
+        Ok(statements)
+    }
+
+    fn parse(token_iter: &mut TokenIter) -> SyntaxResult<Self> {
+        match loop_kind(token_iter) {
+            Some(LoopKind::While) => return Ok(Self::While(token_iter.parse()?)),
+            Some(LoopKind::For) => return Ok(Self::For(Box::new(token_iter.parse()?))),
+            None => (),
+        }
+
+        if let TokenTree::Punct(punct) = token_iter.expect_peek()? {
+            if MacroPrefix::is_punct(punct) && is_stmt_macro(token_iter, 1, true)? {
+                let (_, path, _): (MacroPrefix, Path, Brace<TokenStream>) = token_iter.parse()?;
+                return Err(SyntaxError::StatementMacro(path.span()));
+            }
+        }
+
+        match token_iter.expect_peek()? {
+            TokenTree::Group(group) if group.delimiter() == Delimiter::Brace => {
+                return Ok(Self::Block(token_iter.parse()?));
             }
             TokenTree::Ident(ident) => {
                 if Import::is_ident(ident) {
@@ -82,26 +176,38 @@ impl Parse for Statement {
                     return Ok(Self::Return(token_iter.parse()?));
                 }
 
-                if Unsafe::is_ident(ident) {
-                    return Ok(Self::Unsafe(token_iter.parse()?));
+                if Break::is_ident(ident) {
+                    return Ok(Self::Break(token_iter.parse()?));
                 }
 
-                if While::is_ident(ident) {
-                    return Ok(Self::While(token_iter.parse()?));
+                if Continue::is_ident(ident) {
+                    return Ok(Self::Continue(token_iter.parse()?));
                 }
 
-                if If::is_ident(ident) {
-                    return Ok(Self::If(token_iter.parse()?));
+                if Unsafe::is_ident(ident) {
+                    return Ok(Self::Unsafe(token_iter.parse()?));
                 }
 
-                if For::is_ident(ident) {
-                    return Ok(Self::For(Box::new(token_iter.parse()?)));
+                if If::is_ident(ident) {
+                    return Ok(Self::If(token_iter.parse()?));
                 }
 
                 if Function::is_ident(ident) {
                     return Ok(Self::Function(token_iter.parse()?));
                 }
 
+                if Struct::is_ident(ident) {
+                    return Ok(Self::Struct(token_iter.parse()?));
+                }
+
+                if Enum::is_ident(ident) {
+                    return Ok(Self::Enum(token_iter.parse()?));
+                }
+
+                if Match::is_ident(ident) {
+                    return Ok(Self::Match(token_iter.parse()?));
+                }
+
                 if Let::is_ident(ident) {
                     return Ok(Self::Declaration(token_iter.parse()?));
                 }
@@ -120,16 +226,125 @@ impl Parse for Statement {
                     if Colon::is_punct(next_punct) {
                         return Ok(Self::Declaration(token_iter.parse()?));
                     }
-
-                    if Assign::is_punct(next_punct) {
-                        return Ok(Self::Assignment(token_iter.parse()?));
-                    }
                 }
             }
             _ => (),
         }
 
         let expr = token_iter.parse()?;
-        Ok(Self::Expression(expr))
+        if let Some(TokenTree::Punct(punct)) = token_iter.peek() {
+            if AssignOp::is_punct(punct) {
+                return Ok(Self::Assignment(Assignment {
+                    target: expr,
+                    assign: token_iter.parse()?,
+                    value: token_iter.parse()?,
+                    semicolon: token_iter.parse()?,
+                }));
+            }
+        }
+
+        Ok(Self::Expression((expr, token_iter.parse()?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_statements, Statement};
+    use crate::{diagnostic::Diagnostics, SourceMap};
+
+    #[test]
+    fn parsing_recovers_after_errors() {
+        let mut diagnostics = Diagnostics::new();
+        let (_, tokens) = SourceMap::new().load(
+            "test.ss",
+            "let a: int = ;
+            static function f(): void { let b: = 1; }
+            let c: int = 1;
+            return ;"
+                .to_string(),
+            &mut diagnostics,
+        );
+        assert!(diagnostics.is_empty());
+
+        let statements = parse_statements(&tokens, &mut diagnostics);
+        assert_eq!(diagnostics.len(), 3);
+        assert!(matches!(
+            statements.as_slice(),
+            [Statement::Function(_), Statement::Declaration(_)]
+        ));
+    }
+
+    #[test]
+    fn expected_tokens_are_spelled_out() {
+        for (input, message) in [
+            ("let a int = 1;", "Unexpected token, expected `:`"),
+            (
+                "static function f() void {}",
+                "Unexpected token, expected `:`",
+            ),
+            (
+                "static function f: void {}",
+                "Unexpected token, expected `(`",
+            ),
+            ("1 + ;", "Unexpected token, expected an expression"),
+        ] {
+            let mut diagnostics = Diagnostics::new();
+            let (_, tokens) = SourceMap::new().load("test.ss", input.to_string(), &mut diagnostics);
+            parse_statements(&tokens, &mut diagnostics);
+            let messages: Vec<&str> = diagnostics
+                .iter()
+                .map(|diagnostic| diagnostic.message.as_str())
+                .collect();
+            assert_eq!(messages, [message], "{}", input);
+        }
+    }
+
+    #[test]
+    fn parsing_recovers_inside_blocks() {
+        let mut diagnostics = Diagnostics::new();
+        let (_, tokens) = SourceMap::new().load(
+            "test.ss",
+            "static function f(): void {
+                let a: int = ;
+                if (1 < 2) { let b: = 1; return ; }
+                let c: int = 1;
+                c = ;
+            }
+            let d: = 1;"
+                .to_string(),
+            &mut diagnostics,
+        );
+        assert!(diagnostics.is_empty());
+
+        let statements = parse_statements(&tokens, &mut diagnostics);
+        assert_eq!(diagnostics.len(), 5);
+        let [Statement::Function(function)] = statements.as_slice() else {
+            panic!("expected only the function to parse, got {:?}", statements);
+        };
+        assert!(matches!(
+            function.block.contents().as_slice(),
+            [Statement::If(_), Statement::Declaration(_)]
+        ));
+    }
+
+    #[test]
+    fn recovery_skips_doc_comments() {
+        let mut diagnostics = Diagnostics::new();
+        let (_, tokens) = SourceMap::new().load(
+            "test.ss",
+            "/// Doc comments are not counted as consumed tokens.
+            let a: int = ;
+            let b: = 1;
+            /// Nor are ones between statements.
+            return ;
+            let c: int = 1;"
+                .to_string(),
+            &mut diagnostics,
+        );
+        assert!(diagnostics.is_empty());
+
+        let statements = parse_statements(&tokens, &mut diagnostics);
+        assert_eq!(diagnostics.len(), 3);
+        assert!(matches!(statements.as_slice(), [Statement::Declaration(_)]));
     }
 }
@@ -0,0 +1,48 @@
+use super::{Expression, Punctuated, Type};
+use crate::{
+    ast_item,
+    token::{Brace, Colon, Comma, Ident, Struct},
+    Span, Spanned,
+};
+
+ast_item!(
+    pub struct StructField {
+        name: Ident,
+        colon: Colon,
+        ty: Type,
+    }
+);
+
+impl Spanned for StructField {
+    fn span(&self) -> Span {
+        Span::from_start_end(self.name.span(), self.ty.span())
+    }
+}
+
+ast_item!(
+    pub struct StructDecl {
+        struct_token: Struct,
+        ident: Ident,
+        fields: Brace<Punctuated<StructField, Comma>>,
+    }
+);
+
+impl Spanned for StructDecl {
+    fn span(&self) -> Span {
+        Span::from_start_end(self.struct_token.span(), self.fields.span())
+    }
+}
+
+ast_item!(
+    pub struct FieldValue {
+        name: Ident,
+        colon: Colon,
+        value: Expression,
+    }
+);
+
+impl Spanned for FieldValue {
+    fn span(&self) -> Span {
+        Span::from_start_end(self.name.span(), self.value.span())
+    }
+}
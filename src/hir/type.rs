@@ -1,8 +1,8 @@
 use super::Path;
 use crate::{
     ast_item,
-    token::{Ident, ToTokenTree},
-    Parse, Span, Spanned, SyntaxResult, TokenIter, TokenTree,
+    token::{Bracket, Delimiter, Ident, Literal, LiteralType, Ternary, ToTokenTree},
+    Parse, Span, Spanned, SyntaxError, SyntaxResult, TokenIter, TokenTree,
 };
 
 macro_rules! define_primitive {
@@ -44,12 +44,23 @@ macro_rules! define_primitive {
         pub(crate) mod mir {
             use crate::mir::ToMir;
             use super::Primitive;
+            use std::fmt;
 
-            #[derive(Debug, Clone, PartialEq)]
+            #[derive(Debug, Clone, Copy, PartialEq, Eq)]
             pub enum MirPrimitive {
                 $($variant),+
             }
 
+            impl fmt::Display for MirPrimitive {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    match self {
+                        $(
+                            Self::$variant => f.write_str($value),
+                        )+
+                    }
+                }
+            }
+
             impl ToMir for Primitive {
                 type Output = MirPrimitive;
 
@@ -67,6 +78,7 @@ macro_rules! define_primitive {
 
 define_primitive!(
     Void => "void",
+    Bool => "bool",
     Byte => "byte",
     Short => "short",
     Int => "int",
@@ -80,19 +92,61 @@ ast_item!(
     pub enum Type {
         Primitive(Primitive),
         UserDefined(Path),
+        Array(Box<(Type, Bracket<Literal>)>),
+        Optional(Box<(Type, Ternary)>),
     }
 );
 
+impl Type {
+    /// The length of an array type, the literal is checked to be a non-negative int when parsing.
+    pub fn array_len(length: &Bracket<Literal>) -> u32 {
+        match length.contents().inner() {
+            LiteralType::Int(value) => *value as u32,
+            _ => unreachable!("array lengths are int literals"),
+        }
+    }
+}
+
+impl Parse for Primitive {
+    fn parse(token_iter: &mut TokenIter) -> SyntaxResult<Self> {
+        let ident: Ident = token_iter.parse()?;
+        Self::from_ident(&ident)
+            .ok_or_else(|| SyntaxError::UnexpectedToken(ident.to_token_tree(), "a primitive type"))
+    }
+}
+
 impl Parse for Type {
     fn parse(token_iter: &mut TokenIter) -> SyntaxResult<Self> {
         let path: Path = token_iter.parse()?;
 
-        if path.len() == 1 {
-            if let Some(primitive) = Primitive::from_ident(path.first_token()) {
-                return Ok(Self::Primitive(primitive));
+        let mut ty = match path.len() {
+            1 => match Primitive::from_ident(path.first_token()) {
+                Some(primitive) => Self::Primitive(primitive),
+                None => Self::UserDefined(path),
+            },
+            _ => Self::UserDefined(path),
+        };
+
+        loop {
+            match token_iter.peek() {
+                Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Bracket => (),
+                Some(TokenTree::Punct(punct)) if Ternary::is_punct(punct) => {
+                    ty = Self::Optional(Box::new((ty, token_iter.parse()?)));
+                    continue;
+                }
+                _ => break,
+            }
+
+            let length: Bracket<Literal> = token_iter.parse()?;
+            if !matches!(length.contents().inner(), LiteralType::Int(value) if *value >= 0) {
+                return Err(SyntaxError::UnexpectedToken(
+                    length.into_contents().to_token_tree(),
+                    "an array length",
+                ));
             }
+            ty = Self::Array(Box::new((ty, length)));
         }
 
-        Ok(Self::UserDefined(path))
+        Ok(ty)
     }
 }
@@ -1,12 +1,14 @@
 use crate::{
     ast_item,
-    hir::{Block, Expression},
-    token::{Parenthesis, While},
+    hir::{Block, Expression, Label},
+    token::{Parenthesis, Spread, While},
     Span, Spanned,
 };
 
 ast_item!(
     pub struct WhileLoop {
+        label: Option<Label>,
+        spread: Option<Spread>,
         while_token: While,
         condition: Parenthesis<Expression>,
         block: Block,
@@ -15,6 +17,13 @@ ast_item!(
 
 impl Spanned for WhileLoop {
     fn span(&self) -> Span {
-        Span::from_start_end(self.while_token.span(), self.block.span())
+        let start = if let Some(label) = &self.label {
+            label.span()
+        } else if let Some(spread_token) = self.spread {
+            spread_token.span()
+        } else {
+            self.while_token.span()
+        };
+        Span::from_start_end(start, self.block.span())
     }
 }
@@ -1,30 +1,44 @@
-pub use self::{hir::prelude::*, token::prelude::*};
+pub use self::{
+    hir::prelude::*,
+    source::{FileId, SourceMap},
+    token::prelude::*,
+};
 pub(crate) use private::Sealed;
 
 mod private {
     pub trait Sealed {}
 }
 
+pub mod backend;
 pub mod config;
+pub mod diagnostic;
 pub mod hir;
 pub mod mir;
+pub mod source;
 pub mod token;
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// A range of characters in a source file.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Span {
+    pub file: FileId,
     pub index: usize,
     pub width: usize,
 }
 
 impl Span {
-    pub fn new(index: usize, width: usize) -> Self {
-        Self { index, width }
+    pub fn new(file: FileId, index: usize, width: usize) -> Self {
+        Self { file, index, width }
     }
 
     pub fn from_start_end(start: Span, end: Span) -> Self {
-        Self::new(start.index, end.index + end.width - start.index)
+        Self::new(start.file, start.index, end.index + end.width - start.index)
+    }
+
+    /// The empty span right after this one.
+    pub fn end(self) -> Self {
+        Self::new(self.file, self.index + self.width, 0)
     }
 }
 
@@ -1,14 +1,13 @@
 use clap::Parser;
-use std::{
-    fs::{self, File},
-    path::PathBuf,
-};
+use eyre::bail;
+use std::{fs, path::PathBuf};
 use stonescript::{
+    backend::{Datapack, MinecraftVersion},
     config::ProjectConfig,
-    hir::{Statement, ToTokens},
-    mir::{AbsoluteScope, MangleScope, ToMir},
-    token::parse_from_reader,
-    TokenIter, VERSION,
+    diagnostic::Diagnostics,
+    hir::{check_loop_control, parse_statements, ToTokens},
+    mir::{check_types, AbsoluteScope, MangleScope, ToMir},
+    SourceMap, VERSION,
 };
 
 #[derive(Parser, Debug)]
@@ -23,6 +22,9 @@ pub struct Args {
     /// Entrypoint file
     #[arg(short, long, default_value = "src/main.ss")]
     pub entrypoint: PathBuf,
+    /// Minecraft version to compile for, overrides `target.minecraft_version`
+    #[arg(long)]
+    pub mc_version: Option<MinecraftVersion>,
 }
 
 fn main() -> eyre::Result<()> {
@@ -36,8 +38,8 @@ fn main() -> eyre::Result<()> {
         args.entrypoint.display()
     );
 
-    let project_config: ProjectConfig =
-        toml::from_str(&fs::read_to_string(args.root.join("stonescript.toml"))?)?;
+    let config_source = fs::read_to_string(args.root.join("stonescript.toml"))?;
+    let project_config: ProjectConfig = toml::from_str(&config_source)?;
 
     let target_dir = args.root.join(args.target);
     println!("{{ target_dir = '{}' }}", target_dir.display());
@@ -47,21 +49,79 @@ fn main() -> eyre::Result<()> {
         project_config.package, project_config.dependencies
     );
 
-    let entrypoint_file = File::open(args.root.join(args.entrypoint))?;
-    let tokenized = parse_from_reader(entrypoint_file)?;
+    let source = fs::read_to_string(args.root.join(&args.entrypoint))?;
+    let mut diagnostics = Diagnostics::new();
+    let mut source_map = SourceMap::new();
+    let (_, tokenized) = source_map.load(args.entrypoint.clone(), source, &mut diagnostics);
+    let config_file = source_map.add("stonescript.toml", config_source);
+
+    let abort_on_errors = |diagnostics: &Diagnostics| -> eyre::Result<()> {
+        if diagnostics.has_errors() {
+            eprintln!("{}", diagnostics.render(&source_map));
+            bail!(
+                "Could not compile `{}` due to {} previous error(s)",
+                project_config.package.name,
+                diagnostics.error_count()
+            );
+        }
+        Ok(())
+    };
 
-    let statements: Vec<Statement> = TokenIter::from(&tokenized).parse()?;
+    // Tokens around lexer errors are missing, parsing would only report follow-up errors.
+    abort_on_errors(&diagnostics)?;
+    let statements = parse_statements(&tokenized, &mut diagnostics);
+    diagnostics.extend(check_loop_control(&statements));
+    // Later passes expect a complete program.
+    abort_on_errors(&diagnostics)?;
     println!("\nAST:\n\n{:#?}", statements.clone().into_tokens());
 
     let mir_first = statements.into_mir();
     println!("MIR (first): {:?}", mir_first);
 
-    let mir_absolute = AbsoluteScope::root_to_absolute(mir_first);
+    let mut mir_absolute = AbsoluteScope::root_to_absolute(mir_first);
     println!("MIR (absolute): {:?}", mir_absolute);
+    diagnostics.extend(check_types(&mut mir_absolute));
+    abort_on_errors(&diagnostics)?;
 
     let mir_mangled = MangleScope::mangle_root(&project_config.package.name, mir_absolute);
     println!("\nMIR (mangled): {:?}", mir_mangled);
 
+    let version = args
+        .mc_version
+        .or(project_config.target.minecraft_version)
+        .unwrap_or_default();
+    println!("{{ minecraft_version = '{}' }}", version);
+
+    let mut datapack = Datapack::new(&project_config.package, version);
+    if let Some(length) = project_config.target.max_command_chain_length {
+        datapack.set_max_command_chain_length(length);
+    }
+    if let Some(scale) = &project_config.target.float_scale {
+        let span = source_map.span(config_file, scale.span());
+        if let Err(err) = datapack.set_float_scale(*scale.get_ref(), span) {
+            diagnostics.push(err);
+        }
+    }
+    if let Some(scale) = &project_config.target.double_scale {
+        let span = source_map.span(config_file, scale.span());
+        if let Err(err) = datapack.set_double_scale(*scale.get_ref(), span) {
+            diagnostics.push(err);
+        }
+    }
+    abort_on_errors(&diagnostics)?;
+
+    let result = datapack.lower_root(mir_mangled);
+    diagnostics.extend(datapack.take_warnings());
+    if let Err(err) = result {
+        diagnostics.push(err);
+        abort_on_errors(&diagnostics)?;
+    }
+    if !diagnostics.is_empty() {
+        eprintln!("{}", diagnostics.render(&source_map));
+    }
+    datapack.write(&target_dir)?;
+    println!("\nWrote datapack to '{}'", target_dir.display());
+
     // let rebuilt_statements: Vec<RebuiltStatement> = rebuild_from_ast(statements, &project_config);
     // println!("\nRebuilt AST:\n\n{:#?}", rebuilt_statements);
 
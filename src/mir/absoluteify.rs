@@ -1,9 +1,13 @@
 use super::{
-    MirAssignment, MirDeclaration, MirElseBlock, MirExpression, MirFor, MirFunction, MirIf,
-    MirStatement, MirType, MirWhile, RelativePath, VariableName,
+    CommandPart, MirArm, MirArmBody, MirAssignment, MirDeclaration, MirElseBlock, MirEnum,
+    MirExpression, MirExpressionKind, MirFor, MirFunction, MirIf, MirMatch, MirPattern,
+    MirStatement, MirStruct, MirType, MirWhile, RelativePath, VariableName,
 };
 use crate::{private::Sealed, token::XID};
-use std::{collections::HashMap, mem};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, mem,
+};
 
 #[derive(PartialEq, Eq, Clone, Debug)]
 pub struct AbsolutePath(Vec<AbsoluteVar>);
@@ -21,6 +25,13 @@ impl AbsolutePath {
     }
 }
 
+impl fmt::Display for AbsolutePath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let segments: Vec<&str> = self.0.iter().map(AbsoluteVar::inner).collect();
+        f.write_str(&segments.join("::"))
+    }
+}
+
 impl VariableName for AbsoluteVar {
     type VariablePath = AbsolutePath;
     type OtherPath = AbsolutePath;
@@ -29,6 +40,7 @@ impl VariableName for AbsoluteVar {
 pub struct AbsoluteScope<'a> {
     parent: Option<&'a mut AbsoluteScope<'a>>,
     imports: HashMap<XID, AbsolutePath>,
+    enums: HashSet<XID>,
 }
 
 impl<'a> AbsoluteScope<'a> {
@@ -36,6 +48,7 @@ impl<'a> AbsoluteScope<'a> {
         let mut scope = Self {
             parent: None,
             imports: HashMap::new(),
+            enums: HashSet::new(),
         };
 
         value.to_absolute(&mut scope)
@@ -44,8 +57,11 @@ impl<'a> AbsoluteScope<'a> {
     pub fn new_child<T>(&mut self, closure: impl FnOnce(&mut AbsoluteScope) -> T) -> T {
         // SAFETY: Widens the scope of self, the reference does not live longer than this function.
         closure(&mut Self {
-            parent: Some(unsafe { mem::transmute(self) }),
+            parent: Some(unsafe {
+                mem::transmute::<&mut AbsoluteScope<'a>, &'a mut AbsoluteScope<'a>>(self)
+            }),
             imports: HashMap::new(),
+            enums: HashSet::new(),
         })
     }
 
@@ -61,11 +77,33 @@ impl<'a> AbsoluteScope<'a> {
     pub fn new_variable(&self, xid: XID) -> AbsoluteVar {
         AbsoluteVar(xid)
     }
+
+    fn is_enum(&self, xid: &XID) -> bool {
+        self.enums.contains(xid)
+            || self
+                .parent
+                .as_ref()
+                .is_some_and(|parent| parent.is_enum(xid))
+    }
+
+    /// Splits `path` into an enum and one of its variants, if its first segment is an enum.
+    fn as_variant(&mut self, path: &RelativePath) -> Option<(AbsolutePath, XID)> {
+        match path.as_slice() {
+            [name, variant] if self.is_enum(name) => {
+                Some((vec![name.clone()].to_absolute(self), variant.clone()))
+            }
+            _ => None,
+        }
+    }
 }
 
 pub trait ToAbsolute: Sized {
     type Output;
     fn to_absolute(self, scope: &mut AbsoluteScope) -> Self::Output;
+
+    /// Called on every item of a block before any of them is converted, items are visible in the
+    /// whole block.
+    fn declare(&self, _scope: &mut AbsoluteScope) {}
 }
 
 impl ToAbsolute for RelativePath {
@@ -92,6 +130,10 @@ impl ToAbsolute for RelativePath {
 impl<T: ToAbsolute> ToAbsolute for Vec<T> {
     type Output = Vec<T::Output>;
     fn to_absolute(self, scope: &mut AbsoluteScope) -> Self::Output {
+        for item in &self {
+            item.declare(scope);
+        }
+
         self.into_iter()
             .map(|item| item.to_absolute(scope))
             .collect()
@@ -103,6 +145,8 @@ impl ToAbsolute for MirStatement<XID> {
     fn to_absolute(self, scope: &mut AbsoluteScope) -> Self::Output {
         match self {
             Self::Import(path) => MirStatement::Import(scope.new_import(path.clone())),
+            Self::Break(label, span) => MirStatement::Break(label.clone(), span),
+            Self::Continue(label, span) => MirStatement::Continue(label.clone(), span),
             Self::Assignment(assign) => MirStatement::Assignment(assign.to_absolute(scope)),
             Self::Block(block) => {
                 MirStatement::Block(scope.new_child(|scope| block.to_absolute(scope)))
@@ -111,7 +155,10 @@ impl ToAbsolute for MirStatement<XID> {
             Self::Expression(expr) => MirStatement::Expression(expr.to_absolute(scope)),
             Self::For(for_loop) => MirStatement::For(Box::new(for_loop.to_absolute(scope))),
             Self::Function(func) => MirStatement::Function(func.to_absolute(scope)),
+            Self::Struct(decl) => MirStatement::Struct(decl.to_absolute(scope)),
+            Self::Enum(decl) => MirStatement::Enum(decl.to_absolute(scope)),
             Self::If(if_block) => MirStatement::If(if_block.to_absolute(scope)),
+            Self::Match(match_stmt) => MirStatement::Match(match_stmt.to_absolute(scope)),
             Self::Return(expr) => MirStatement::Return(expr.to_absolute(scope)),
             Self::Unsafe(block) => {
                 MirStatement::Unsafe(scope.new_child(|scope| block.to_absolute(scope)))
@@ -119,33 +166,92 @@ impl ToAbsolute for MirStatement<XID> {
             Self::While(while_loop) => MirStatement::While(while_loop.to_absolute(scope)),
         }
     }
+
+    fn declare(&self, scope: &mut AbsoluteScope) {
+        if let Self::Enum(decl) = self {
+            scope.enums.insert(decl.name.clone());
+        }
+    }
 }
 
 impl ToAbsolute for MirExpression<XID> {
     type Output = MirExpression<AbsoluteVar>;
     fn to_absolute(self, scope: &mut AbsoluteScope) -> Self::Output {
-        match self {
-            Self::BinaryOp(left, op, right) => MirExpression::BinaryOp(
+        let kind = match self.kind {
+            MirExpressionKind::BinaryOp(left, op, right) => MirExpressionKind::BinaryOp(
                 Box::new(left.to_absolute(scope)),
                 op,
                 Box::new(right.to_absolute(scope)),
             ),
-            Self::Call(path, args) => {
-                MirExpression::Call(path.to_absolute(scope), args.to_absolute(scope))
+            MirExpressionKind::Call(path, args) => match scope.as_variant(&path) {
+                Some((enum_path, variant)) => {
+                    MirExpressionKind::Variant(enum_path, variant, args.to_absolute(scope))
+                }
+                None => MirExpressionKind::Call(path.to_absolute(scope), args.to_absolute(scope)),
+            },
+            MirExpressionKind::Variant(path, variant, args) => MirExpressionKind::Variant(
+                path.to_absolute(scope),
+                variant,
+                args.to_absolute(scope),
+            ),
+            MirExpressionKind::Match(match_expr) => {
+                MirExpressionKind::Match(Box::new(match_expr.to_absolute(scope)))
+            }
+            MirExpressionKind::Command(parts) => {
+                MirExpressionKind::Command(parts.to_absolute(scope))
             }
-            Self::Command(cmd) => MirExpression::Command(cmd),
-            Self::Index(left, index) => MirExpression::Index(
+            MirExpressionKind::Tellraw(targets, parts) => {
+                MirExpressionKind::Tellraw(targets, parts.to_absolute(scope))
+            }
+            MirExpressionKind::Struct(path, fields) => MirExpressionKind::Struct(
+                path.to_absolute(scope),
+                fields
+                    .into_iter()
+                    .map(|(name, value)| (name, value.to_absolute(scope)))
+                    .collect(),
+            ),
+            MirExpressionKind::Array(elements) => {
+                MirExpressionKind::Array(elements.to_absolute(scope))
+            }
+            MirExpressionKind::Index(left, index) => MirExpressionKind::Index(
                 Box::new(left.to_absolute(scope)),
                 Box::new(index.to_absolute(scope)),
             ),
-            Self::Literal(literal) => MirExpression::Literal(literal),
-            Self::Property(left, property) => {
-                MirExpression::Property(Box::new(left.to_absolute(scope)), property)
+            MirExpressionKind::Literal(literal) => MirExpressionKind::Literal(literal),
+            MirExpressionKind::Null => MirExpressionKind::Null,
+            MirExpressionKind::Nbt(path) => MirExpressionKind::Nbt(path),
+            MirExpressionKind::Cast(expr, primitive) => {
+                MirExpressionKind::Cast(Box::new(expr.to_absolute(scope)), primitive)
+            }
+            MirExpressionKind::Ternary(condition, then, otherwise) => MirExpressionKind::Ternary(
+                Box::new(condition.to_absolute(scope)),
+                Box::new(then.to_absolute(scope)),
+                Box::new(otherwise.to_absolute(scope)),
+            ),
+            MirExpressionKind::Property(left, property) => {
+                MirExpressionKind::Property(Box::new(left.to_absolute(scope)), property)
             }
-            Self::UnaryOp(op, expr) => {
-                MirExpression::UnaryOp(op, Box::new(expr.to_absolute(scope)))
+            MirExpressionKind::UnaryOp(op, expr) => {
+                MirExpressionKind::UnaryOp(op, Box::new(expr.to_absolute(scope)))
             }
-            Self::Variable(path) => MirExpression::Variable(path.to_absolute(scope)),
+            MirExpressionKind::Variable(path) => match scope.as_variant(&path) {
+                Some((enum_path, variant)) => {
+                    MirExpressionKind::Variant(enum_path, variant, vec![])
+                }
+                None => MirExpressionKind::Variable(path.to_absolute(scope)),
+            },
+        };
+
+        MirExpression::new(kind, self.span)
+    }
+}
+
+impl ToAbsolute for CommandPart<XID> {
+    type Output = CommandPart<AbsoluteVar>;
+    fn to_absolute(self, scope: &mut AbsoluteScope) -> Self::Output {
+        match self {
+            Self::Text(text) => CommandPart::Text(text),
+            Self::Value(value) => CommandPart::Value(value.to_absolute(scope)),
         }
     }
 }
@@ -154,7 +260,8 @@ impl ToAbsolute for MirAssignment<XID> {
     type Output = MirAssignment<AbsoluteVar>;
     fn to_absolute(self, scope: &mut AbsoluteScope) -> Self::Output {
         MirAssignment {
-            variable: self.variable.to_absolute(scope),
+            target: self.target.to_absolute(scope),
+            op: self.op,
             value: self.value.to_absolute(scope),
         }
     }
@@ -168,6 +275,8 @@ impl ToAbsolute for MirDeclaration<XID> {
             name: scope.new_variable(self.name),
             ty: self.ty.to_absolute(scope),
             value: self.value.map(|val| val.to_absolute(scope)),
+            span: self.span,
+            ty_span: self.ty_span,
         }
     }
 }
@@ -178,6 +287,8 @@ impl ToAbsolute for MirType<XID> {
         match self {
             Self::Primitive(primitive) => MirType::Primitive(primitive),
             Self::UserDefined(path) => MirType::UserDefined(path.to_absolute(scope)),
+            Self::Array(inner, len) => MirType::Array(Box::new(inner.to_absolute(scope)), len),
+            Self::Optional(inner) => MirType::Optional(Box::new(inner.to_absolute(scope))),
         }
     }
 }
@@ -201,6 +312,81 @@ impl ToAbsolute for MirFunction<XID> {
             args,
             return_type: self.return_type.to_absolute(scope),
             block,
+            span: self.span,
+        }
+    }
+}
+
+impl ToAbsolute for MirStruct<XID> {
+    type Output = MirStruct<AbsoluteVar>;
+    fn to_absolute(self, scope: &mut AbsoluteScope) -> Self::Output {
+        MirStruct {
+            name: self.name,
+            fields: self
+                .fields
+                .into_iter()
+                .map(|(name, ty)| (name, ty.to_absolute(scope)))
+                .collect(),
+            span: self.span,
+        }
+    }
+}
+
+impl ToAbsolute for MirEnum<XID> {
+    type Output = MirEnum<AbsoluteVar>;
+    fn to_absolute(self, scope: &mut AbsoluteScope) -> Self::Output {
+        MirEnum {
+            name: self.name,
+            variants: self
+                .variants
+                .into_iter()
+                .map(|(name, payload)| (name, payload.to_absolute(scope)))
+                .collect(),
+            span: self.span,
+        }
+    }
+}
+
+impl ToAbsolute for MirMatch<XID> {
+    type Output = MirMatch<AbsoluteVar>;
+    fn to_absolute(self, scope: &mut AbsoluteScope) -> Self::Output {
+        MirMatch {
+            scrutinee: self.scrutinee.to_absolute(scope),
+            arms: self
+                .arms
+                .into_iter()
+                .map(|arm| scope.new_child(|scope| arm.to_absolute(scope)))
+                .collect(),
+        }
+    }
+}
+
+impl ToAbsolute for MirArm<XID> {
+    type Output = MirArm<AbsoluteVar>;
+    fn to_absolute(self, scope: &mut AbsoluteScope) -> Self::Output {
+        let pattern = match self.pattern {
+            MirPattern::Wildcard => MirPattern::Wildcard,
+            MirPattern::Variant {
+                enum_path,
+                variant,
+                bindings,
+            } => MirPattern::Variant {
+                enum_path: enum_path.to_absolute(scope),
+                variant,
+                bindings: bindings
+                    .into_iter()
+                    .map(|binding| binding.map(|xid| scope.new_variable(xid)))
+                    .collect(),
+            },
+        };
+
+        MirArm {
+            pattern,
+            span: self.span,
+            body: match self.body {
+                MirArmBody::Block(block) => MirArmBody::Block(block.to_absolute(scope)),
+                MirArmBody::Expression(expr) => MirArmBody::Expression(expr.to_absolute(scope)),
+            },
         }
     }
 }
@@ -209,6 +395,8 @@ impl ToAbsolute for MirFor<XID> {
     type Output = MirFor<AbsoluteVar>;
     fn to_absolute(self, scope: &mut AbsoluteScope) -> Self::Output {
         scope.new_child(|scope| MirFor {
+            label: self.label,
+            is_spread: self.is_spread,
             init: self.init.to_absolute(scope),
             condition: self.condition.to_absolute(scope),
             update: self.update.to_absolute(scope),
@@ -244,6 +432,8 @@ impl ToAbsolute for MirWhile<XID> {
     type Output = MirWhile<AbsoluteVar>;
     fn to_absolute(self, scope: &mut AbsoluteScope) -> Self::Output {
         MirWhile {
+            label: self.label,
+            is_spread: self.is_spread,
             condition: self.condition.to_absolute(scope),
             block: scope.new_child(|scope| self.block.to_absolute(scope)),
         }
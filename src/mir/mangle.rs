@@ -1,12 +1,13 @@
 use super::{
-    AbsolutePath, AbsoluteVar, MirAssignment, MirDeclaration, MirElseBlock, MirExpression, MirFor,
-    MirFunction, MirIf, MirStatement, MirType, MirWhile, VariableName,
+    AbsolutePath, AbsoluteVar, CommandPart, MirArm, MirArmBody, MirAssignment, MirDeclaration,
+    MirElseBlock, MirEnum, MirExpression, MirExpressionKind, MirFor, MirFunction, MirIf, MirMatch,
+    MirPattern, MirStatement, MirStruct, MirType, MirWhile, VariableName,
 };
 use crate::private::Sealed;
 use rustc_hash::FxHasher;
 use std::{collections::HashMap, fmt, hash::Hasher, slice::Iter};
 
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct MangledVar(u64);
 impl fmt::Display for MangledVar {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -16,7 +17,7 @@ impl fmt::Display for MangledVar {
 
 impl fmt::Debug for MangledVar {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "MangledVar({})", self.to_string())
+        write!(f, "MangledVar({})", self)
     }
 }
 
@@ -83,14 +84,12 @@ impl<'a> MangleScope<'a> {
         self.variables.get(variable).copied()
     }
 
+    /// Variables in inner scopes shadow those of the same name further out.
     fn find_defined_variable(&self, variable: &AbsoluteVar) -> Option<MangledVar> {
-        if let Some(parent) = self.parent {
-            parent
-                .find_defined_variable(variable)
-                .or_else(|| self.find_defined_variable_self(variable))
-        } else {
-            self.find_defined_variable_self(variable)
-        }
+        self.find_defined_variable_self(variable).or_else(|| {
+            self.parent
+                .and_then(|parent| parent.find_defined_variable(variable))
+        })
     }
 
     fn find_absolute_variable(&self, mut segments: Iter<'_, AbsoluteVar>) -> MangledVar {
@@ -104,7 +103,7 @@ impl<'a> MangleScope<'a> {
 
     pub fn get_variable(&mut self, variable: AbsolutePath) -> MangledVar {
         if let [segment] = variable.inner() {
-            self.find_defined_variable(&segment)
+            self.find_defined_variable(segment)
                 .unwrap_or_else(|| self.hash_named(segment.inner()))
         } else {
             let (first, segments) = variable
@@ -157,10 +156,15 @@ impl Mangle for MirStatement<AbsoluteVar> {
             MirStatement::Assignment(assign) => MirStatement::Assignment(assign.mangle(scope)),
             MirStatement::Declaration(decl) => MirStatement::Declaration(decl.mangle(scope)),
             MirStatement::Function(func) => MirStatement::Function(func.mangle(scope)),
+            MirStatement::Struct(decl) => MirStatement::Struct(decl.mangle(scope)),
+            MirStatement::Enum(decl) => MirStatement::Enum(decl.mangle(scope)),
+            MirStatement::Match(match_stmt) => MirStatement::Match(match_stmt.mangle(scope)),
             MirStatement::For(for_loop) => MirStatement::For(Box::new(for_loop.mangle(scope))),
             MirStatement::While(while_loop) => MirStatement::While(while_loop.mangle(scope)),
             MirStatement::If(if_block) => MirStatement::If(if_block.mangle(scope)),
             MirStatement::Import(path) => MirStatement::Import(path),
+            MirStatement::Break(label, span) => MirStatement::Break(label, span),
+            MirStatement::Continue(label, span) => MirStatement::Continue(label, span),
         }
     }
 }
@@ -168,27 +172,69 @@ impl Mangle for MirStatement<AbsoluteVar> {
 impl Mangle for MirExpression<AbsoluteVar> {
     type Output = MirExpression<MangledVar>;
     fn mangle(self, scope: &mut MangleScope) -> Self::Output {
-        match self {
-            MirExpression::Literal(literal) => MirExpression::Literal(literal),
-            MirExpression::Command(command) => MirExpression::Command(command),
-            MirExpression::Variable(variable) => {
-                MirExpression::Variable(scope.get_variable(variable))
+        let kind = match self.kind {
+            MirExpressionKind::Literal(literal) => MirExpressionKind::Literal(literal),
+            MirExpressionKind::Null => MirExpressionKind::Null,
+            MirExpressionKind::Nbt(path) => MirExpressionKind::Nbt(path),
+            MirExpressionKind::Cast(expr, primitive) => {
+                MirExpressionKind::Cast(Box::new(expr.mangle(scope)), primitive)
             }
-            MirExpression::Call(path, args) => MirExpression::Call(path, args.mangle(scope)),
-            MirExpression::Index(left, index) => {
-                MirExpression::Index(Box::new(left.mangle(scope)), Box::new(index.mangle(scope)))
+            MirExpressionKind::Ternary(condition, then, otherwise) => MirExpressionKind::Ternary(
+                Box::new(condition.mangle(scope)),
+                Box::new(then.mangle(scope)),
+                Box::new(otherwise.mangle(scope)),
+            ),
+            MirExpressionKind::Command(parts) => MirExpressionKind::Command(parts.mangle(scope)),
+            MirExpressionKind::Tellraw(targets, parts) => {
+                MirExpressionKind::Tellraw(targets, parts.mangle(scope))
             }
-            MirExpression::Property(left, property) => {
-                MirExpression::Property(Box::new(left.mangle(scope)), property)
+            MirExpressionKind::Variable(variable) => {
+                MirExpressionKind::Variable(scope.get_variable(variable))
+            }
+            MirExpressionKind::Call(path, args) => {
+                MirExpressionKind::Call(path, args.mangle(scope))
+            }
+            MirExpressionKind::Struct(path, fields) => MirExpressionKind::Struct(
+                path,
+                fields
+                    .into_iter()
+                    .map(|(name, value)| (name, value.mangle(scope)))
+                    .collect(),
+            ),
+            MirExpressionKind::Variant(path, variant, args) => {
+                MirExpressionKind::Variant(path, variant, args.mangle(scope))
             }
-            MirExpression::UnaryOp(op, expr) => {
-                MirExpression::UnaryOp(op, Box::new(expr.mangle(scope)))
+            MirExpressionKind::Match(match_expr) => {
+                MirExpressionKind::Match(Box::new(match_expr.mangle(scope)))
             }
-            MirExpression::BinaryOp(left, op, right) => MirExpression::BinaryOp(
+            MirExpressionKind::Array(elements) => MirExpressionKind::Array(elements.mangle(scope)),
+            MirExpressionKind::Index(left, index) => MirExpressionKind::Index(
+                Box::new(left.mangle(scope)),
+                Box::new(index.mangle(scope)),
+            ),
+            MirExpressionKind::Property(left, property) => {
+                MirExpressionKind::Property(Box::new(left.mangle(scope)), property)
+            }
+            MirExpressionKind::UnaryOp(op, expr) => {
+                MirExpressionKind::UnaryOp(op, Box::new(expr.mangle(scope)))
+            }
+            MirExpressionKind::BinaryOp(left, op, right) => MirExpressionKind::BinaryOp(
                 Box::new(left.mangle(scope)),
                 op,
                 Box::new(right.mangle(scope)),
             ),
+        };
+
+        MirExpression::new(kind, self.span)
+    }
+}
+
+impl Mangle for CommandPart<AbsoluteVar> {
+    type Output = CommandPart<MangledVar>;
+    fn mangle(self, scope: &mut MangleScope) -> Self::Output {
+        match self {
+            Self::Text(text) => CommandPart::Text(text),
+            Self::Value(value) => CommandPart::Value(value.mangle(scope)),
         }
     }
 }
@@ -197,7 +243,8 @@ impl Mangle for MirAssignment<AbsoluteVar> {
     type Output = MirAssignment<MangledVar>;
     fn mangle(self, scope: &mut MangleScope) -> Self::Output {
         MirAssignment {
-            variable: scope.get_variable(self.variable),
+            target: self.target.mangle(scope),
+            op: self.op,
             value: self.value.mangle(scope),
         }
     }
@@ -211,6 +258,8 @@ impl Mangle for MirDeclaration<AbsoluteVar> {
             name: scope.new_variable(self.name),
             ty: self.ty.mangle(scope),
             value: self.value.map(|val| val.mangle(scope)),
+            span: self.span,
+            ty_span: self.ty_span,
         }
     }
 }
@@ -218,16 +267,95 @@ impl Mangle for MirDeclaration<AbsoluteVar> {
 impl Mangle for MirFunction<AbsoluteVar> {
     type Output = MirFunction<MangledVar>;
     fn mangle(self, scope: &mut MangleScope) -> Self::Output {
+        // Arguments are only visible inside the function.
+        let id = scope.hash_unnamed();
+        let mut child_scope = scope.new_child(id);
+
         MirFunction {
             is_static: self.is_static,
             name: self.name,
             args: self
                 .args
                 .into_iter()
-                .map(|(name, ty)| (scope.new_variable(name), ty.mangle(scope)))
+                .map(|(name, ty)| (child_scope.new_variable(name), ty.mangle(&mut child_scope)))
                 .collect(),
-            return_type: self.return_type.mangle(scope),
-            block: scope.mangle_unnamed_child(self.block),
+            return_type: self.return_type.mangle(&mut child_scope),
+            block: self.block.mangle(&mut child_scope),
+            span: self.span,
+        }
+    }
+}
+
+impl Mangle for MirStruct<AbsoluteVar> {
+    type Output = MirStruct<MangledVar>;
+    fn mangle(self, scope: &mut MangleScope) -> Self::Output {
+        MirStruct {
+            name: self.name,
+            fields: self
+                .fields
+                .into_iter()
+                .map(|(name, ty)| (name, ty.mangle(scope)))
+                .collect(),
+            span: self.span,
+        }
+    }
+}
+
+impl Mangle for MirEnum<AbsoluteVar> {
+    type Output = MirEnum<MangledVar>;
+    fn mangle(self, scope: &mut MangleScope) -> Self::Output {
+        MirEnum {
+            name: self.name,
+            variants: self
+                .variants
+                .into_iter()
+                .map(|(name, payload)| (name, payload.mangle(scope)))
+                .collect(),
+            span: self.span,
+        }
+    }
+}
+
+impl Mangle for MirMatch<AbsoluteVar> {
+    type Output = MirMatch<MangledVar>;
+    fn mangle(self, scope: &mut MangleScope) -> Self::Output {
+        MirMatch {
+            scrutinee: self.scrutinee.mangle(scope),
+            arms: self
+                .arms
+                .into_iter()
+                .map(|arm| scope.mangle_unnamed_child(arm))
+                .collect(),
+        }
+    }
+}
+
+impl Mangle for MirArm<AbsoluteVar> {
+    type Output = MirArm<MangledVar>;
+    fn mangle(self, scope: &mut MangleScope) -> Self::Output {
+        let pattern = match self.pattern {
+            MirPattern::Wildcard => MirPattern::Wildcard,
+            MirPattern::Variant {
+                enum_path,
+                variant,
+                bindings,
+            } => MirPattern::Variant {
+                enum_path,
+                variant,
+                bindings: bindings
+                    .into_iter()
+                    .map(|binding| binding.map(|name| scope.new_variable(name)))
+                    .collect(),
+            },
+        };
+
+        MirArm {
+            pattern,
+            span: self.span,
+            body: match self.body {
+                MirArmBody::Block(block) => MirArmBody::Block(block.mangle(scope)),
+                MirArmBody::Expression(expr) => MirArmBody::Expression(expr.mangle(scope)),
+            },
         }
     }
 }
@@ -239,6 +367,8 @@ impl Mangle for MirFor<AbsoluteVar> {
         let mut child_scope = scope.new_child(id);
 
         MirFor {
+            label: self.label,
+            is_spread: self.is_spread,
             init: self.init.mangle(&mut child_scope),
             condition: self.condition.mangle(&mut child_scope),
             update: self.update.mangle(&mut child_scope),
@@ -251,6 +381,8 @@ impl Mangle for MirWhile<AbsoluteVar> {
     type Output = MirWhile<MangledVar>;
     fn mangle(self, scope: &mut MangleScope) -> Self::Output {
         MirWhile {
+            label: self.label,
+            is_spread: self.is_spread,
             condition: self.condition.mangle(scope),
             block: scope.mangle_unnamed_child(self.block),
         }
@@ -286,6 +418,8 @@ impl Mangle for MirType<AbsoluteVar> {
         match self {
             Self::Primitive(primitive) => MirType::Primitive(primitive),
             Self::UserDefined(path) => MirType::UserDefined(path),
+            Self::Array(inner, len) => MirType::Array(Box::new(inner.mangle(_scope)), len),
+            Self::Optional(inner) => MirType::Optional(Box::new(inner.mangle(_scope))),
         }
     }
 }
@@ -2,9 +2,11 @@ pub use self::{
     absoluteify::{AbsolutePath, AbsoluteScope, AbsoluteVar, ToAbsolute},
     mangle::{Mangle, MangleScope, MangledVar},
     to_mir::{
-        MirAssignment, MirDeclaration, MirElseBlock, MirExpression, MirFor, MirFunction, MirIf,
-        MirStatement, MirType, MirWhile, RelativePath, ToMir,
+        CommandPart, MirArm, MirArmBody, MirAssignment, MirDeclaration, MirElseBlock, MirEnum,
+        MirExpression, MirExpressionKind, MirFor, MirFunction, MirIf, MirMatch, MirPattern,
+        MirStatement, MirStruct, MirType, MirWhile, RelativePath, ToMir,
     },
+    typeck::{check_types, TypeError, TypeResult},
 };
 pub use crate::hir::mir::{MirBinaryOp, MirUnaryOp};
 use crate::private::Sealed;
@@ -19,3 +21,4 @@ pub trait VariableName: Sealed {
 mod absoluteify;
 mod mangle;
 mod to_mir;
+mod typeck;
@@ -1,12 +1,14 @@
 use super::{MirBinaryOp, MirUnaryOp, VariableName};
 use crate::{
     hir::{
-        mir::MirPrimitive, Assignment, DeclStart, Declaration, ElseBlock, Expression, ForLoop,
-        FunctionDecl, IfBlock, Path, Statement, Type, WhileLoop,
+        mir::MirPrimitive, ArmBody, AssignOp, Assignment, CommandSegment, DeclStart, Declaration,
+        ElseBlock, EnumDecl, Expression, ForLoop, FunctionDecl, IfBlock, MacroCall, MatchExpr,
+        Path, Pattern, Statement, StructDecl, Type, WhileLoop,
     },
-    token::{LiteralType, XID},
-    TokenTree,
+    token::{Ident, LiteralType, XID},
+    Span, Spanned,
 };
+use std::fmt;
 
 pub trait ToMir {
     type Output;
@@ -39,9 +41,16 @@ pub enum MirStatement<V: VariableName> {
     Assignment(MirAssignment<V>),
     Declaration(MirDeclaration<V>),
     Function(MirFunction<V>),
+    Struct(MirStruct<V>),
+    Enum(MirEnum<V>),
     If(MirIf<V>),
+    Match(MirMatch<V>),
     While(MirWhile<V>),
     For(Box<MirFor<V>>),
+    /// Leaves the loop with the given label, or the innermost one.
+    Break(Option<XID>, Span),
+    /// Skips to the next iteration of the loop with the given label, or the innermost one.
+    Continue(Option<XID>, Span),
     Import(V::OtherPath),
 }
 
@@ -57,13 +66,21 @@ impl ToMir for Statement {
             Self::Assignment(assign) => MirStatement::Assignment(assign.into_mir()),
             Self::Declaration(decl) => MirStatement::Declaration(decl.into_mir()),
             Self::Function(func) => MirStatement::Function(func.into_mir()),
+            Self::Struct(decl) => MirStatement::Struct(decl.into_mir()),
+            Self::Enum(decl) => MirStatement::Enum(decl.into_mir()),
             Self::If(if_block) => MirStatement::If(if_block.into_mir()),
+            Self::Match(match_expr) => MirStatement::Match(match_expr.into_mir()),
             Self::While(while_loop) => MirStatement::While(while_loop.into_mir()),
             Self::For(for_loop) => MirStatement::For(Box::new(for_loop.into_mir())),
-            Self::Import((_, path, _)) => MirStatement::Import(path.into_mir()),
-            Self::Macro((_, _path, _contents)) => {
-                unimplemented!("Statement macros are currently unimplemented")
+            Self::Break(break_stmt) => {
+                let span = break_stmt.span();
+                MirStatement::Break(break_stmt.label.map(Ident::into_inner), span)
+            }
+            Self::Continue(continue_stmt) => {
+                let span = continue_stmt.span();
+                MirStatement::Continue(continue_stmt.label.map(Ident::into_inner), span)
             }
+            Self::Import((_, path, _)) => MirStatement::Import(path.into_mir()),
         }
     }
 }
@@ -77,28 +94,107 @@ impl ToMir for Vec<Statement> {
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub enum MirExpression<V: VariableName> {
+pub struct MirExpression<V: VariableName> {
+    pub kind: MirExpressionKind<V>,
+    pub span: Span,
+}
+
+impl<V: VariableName> MirExpression<V> {
+    pub fn new(kind: MirExpressionKind<V>, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
+impl<V: VariableName> Spanned for MirExpression<V> {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MirExpressionKind<V: VariableName> {
     Literal(LiteralType),
+    /// The missing value of an optional type.
+    Null,
     Variable(V::VariablePath),
     Property(Box<MirExpression<V>>, XID),
     Call(V::OtherPath, Vec<MirExpression<V>>),
+    /// A struct literal, fields are in the order they were written in.
+    Struct(V::OtherPath, Vec<(XID, MirExpression<V>)>),
+    /// A variant of the enum at the path, with its payload. Paths to variants are only told apart
+    /// from variables and calls once they are absolute.
+    Variant(V::OtherPath, XID, Vec<MirExpression<V>>),
+    Match(Box<MirMatch<V>>),
+    Array(Vec<MirExpression<V>>),
     Index(Box<MirExpression<V>>, Box<MirExpression<V>>),
     UnaryOp(MirUnaryOp, Box<MirExpression<V>>),
     BinaryOp(Box<MirExpression<V>>, MirBinaryOp, Box<MirExpression<V>>),
-    Command(String),
+    /// `condition ? then : otherwise`, only the branch taken is evaluated.
+    Ternary(
+        Box<MirExpression<V>>,
+        Box<MirExpression<V>>,
+        Box<MirExpression<V>>,
+    ),
+    Command(Vec<CommandPart<V>>),
+    /// Shows the text made of the values to the players the selector targets.
+    Tellraw(String, Vec<MirExpression<V>>),
+    /// Converts a number to another primitive type.
+    Cast(Box<MirExpression<V>>, MirPrimitive),
+    /// Reads the number at an NBT path like `entity @s Pos[0]`, as a `double`.
+    Nbt(String),
+}
+
+/// A piece of a `$command`, values written as `${name}` are interpolated when it runs.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandPart<V: VariableName> {
+    Text(String),
+    Value(MirExpression<V>),
+}
+
+/// Interpolated values read a variable or a field of one, like `${player.name}`.
+fn command_part(segment: CommandSegment, span: Span) -> CommandPart<XID> {
+    match segment {
+        CommandSegment::Text(text) => CommandPart::Text(text),
+        CommandSegment::Value(names) => {
+            let mut names = names.into_iter();
+            let variable = MirExpressionKind::Variable(names.next().into_iter().collect());
+            CommandPart::Value(
+                names.fold(MirExpression::new(variable, span), |value, name| {
+                    let property = MirExpressionKind::Property(Box::new(value), name);
+                    MirExpression::new(property, span)
+                }),
+            )
+        }
+    }
+}
+
+/// Folds a negated number literal, so `-5` is a literal like it is in the source. Other literals
+/// are left for type checking to reject.
+fn negate(literal: &LiteralType) -> Option<LiteralType> {
+    Some(match literal {
+        LiteralType::Byte(value) => LiteralType::Byte(value.wrapping_neg()),
+        LiteralType::Short(value) => LiteralType::Short(value.wrapping_neg()),
+        LiteralType::Int(value) => LiteralType::Int(value.wrapping_neg()),
+        LiteralType::Long(value) => LiteralType::Long(value.wrapping_neg()),
+        LiteralType::Float(value) => LiteralType::Float(-value),
+        LiteralType::Double(value) => LiteralType::Double(-value),
+        LiteralType::Bool(_) | LiteralType::String(_) => return None,
+    })
 }
 
 impl ToMir for Expression {
     type Output = MirExpression<XID>;
 
     fn into_mir(self) -> Self::Output {
-        match self {
-            Self::Literal(literal) => MirExpression::Literal(literal.into_inner()),
-            Self::Variable(path) => MirExpression::Variable(path.into_mir()),
+        let span = self.span();
+        let kind = match self {
+            Self::Literal(literal) => MirExpressionKind::Literal(literal.into_inner()),
+            Self::Null(_) => MirExpressionKind::Null,
+            Self::Variable(path) => MirExpressionKind::Variable(path.into_mir()),
             Self::Property(expr, _, ident) => {
-                MirExpression::Property(Box::new(expr.into_mir()), ident.into_inner())
+                MirExpressionKind::Property(Box::new(expr.into_mir()), ident.into_inner())
             }
-            Self::Call(path, args) => MirExpression::Call(
+            Self::Call(path, args) => MirExpressionKind::Call(
                 path.into_mir(),
                 args.into_contents()
                     .into_tokens()
@@ -106,39 +202,70 @@ impl ToMir for Expression {
                     .map(ToMir::into_mir)
                     .collect(),
             ),
-            Self::Parenthesized(paren) => paren.into_contents().into_mir(),
-            Self::Index(expr, args) => MirExpression::Index(
+            Self::Struct(path, fields) => MirExpressionKind::Struct(
+                path.into_mir(),
+                fields
+                    .into_contents()
+                    .into_tokens()
+                    .into_iter()
+                    .map(|field| (field.name.into_inner(), field.value.into_mir()))
+                    .collect(),
+            ),
+            Self::Match(match_expr) => MirExpressionKind::Match(Box::new(match_expr.into_mir())),
+            Self::Array(elements) => MirExpressionKind::Array(
+                elements
+                    .into_contents()
+                    .into_tokens()
+                    .into_iter()
+                    .map(ToMir::into_mir)
+                    .collect(),
+            ),
+            Self::Parenthesized(paren) => return paren.into_contents().into_mir(),
+            Self::Index(expr, args) => MirExpressionKind::Index(
                 Box::new(expr.into_mir()),
                 Box::new(args.into_contents().into_mir()),
             ),
             Self::UnaryOp(op, expr) => {
-                MirExpression::UnaryOp(op.into_mir(), Box::new(expr.into_mir()))
+                let (op, expr) = (op.into_mir(), expr.into_mir());
+                let folded = match (op, &expr.kind) {
+                    (MirUnaryOp::Negate, MirExpressionKind::Literal(literal)) => negate(literal),
+                    _ => None,
+                };
+                folded.map_or_else(
+                    || MirExpressionKind::UnaryOp(op, Box::new(expr)),
+                    MirExpressionKind::Literal,
+                )
             }
-            Self::BinaryOp(left, op, right) => MirExpression::BinaryOp(
+            Self::BinaryOp(left, op, right) => MirExpressionKind::BinaryOp(
                 Box::new(left.into_mir()),
                 op.into_mir(),
                 Box::new(right.into_mir()),
             ),
-            Self::Macro(_, path, contents) => {
-                // Temporary command parsing until macros are fully implemented
-
-                let path = path.into_tokens();
-                if let [ident] = path.as_slice() {
-                    if ident.inner() == "command" {
-                        if let [TokenTree::Literal(literal)] = contents.into_contents().0.as_slice()
-                        {
-                            if let LiteralType::String(value) = literal.inner() {
-                                return MirExpression::Command(value.clone());
-                            }
-                        }
-
-                        panic!("Invalid command macro invocation")
-                    }
-                }
-
-                unimplemented!("Expression macros other than $command are currently unimplemented")
+            Self::Cast(value, _, ty) => {
+                MirExpressionKind::Cast(Box::new(value.into_mir()), ty.into_mir())
             }
-        }
+            Self::Ternary(condition, _, then, _, otherwise) => MirExpressionKind::Ternary(
+                Box::new(condition.into_mir()),
+                Box::new(then.into_mir()),
+                Box::new(otherwise.into_mir()),
+            ),
+            Self::Macro(_, path, args) => match MacroCall::new(&path, &args) {
+                Ok(MacroCall::Command(segments, span)) => MirExpressionKind::Command(
+                    segments
+                        .into_iter()
+                        .map(|segment| command_part(segment, span))
+                        .collect(),
+                ),
+                Ok(MacroCall::Nbt(path)) => MirExpressionKind::Nbt(path),
+                Ok(MacroCall::Tellraw(targets, values)) => MirExpressionKind::Tellraw(
+                    targets,
+                    values.into_iter().map(ToMir::into_mir).collect(),
+                ),
+                Err(_) => unreachable!("macro arguments are checked when parsing"),
+            },
+        };
+
+        MirExpression::new(kind, span)
     }
 }
 
@@ -146,6 +273,24 @@ impl ToMir for Expression {
 pub enum MirType<V: VariableName> {
     Primitive(MirPrimitive),
     UserDefined(V::OtherPath),
+    /// A fixed-size array of elements of the inner type.
+    Array(Box<MirType<V>>, u32),
+    /// A value of the inner type which may be unset, written `int?`.
+    Optional(Box<MirType<V>>),
+}
+
+impl<V: VariableName> fmt::Display for MirType<V>
+where
+    V::OtherPath: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Primitive(primitive) => primitive.fmt(f),
+            Self::UserDefined(path) => path.fmt(f),
+            Self::Array(inner, len) => write!(f, "{}[{}]", inner, len),
+            Self::Optional(inner) => write!(f, "{}?", inner),
+        }
+    }
 }
 
 impl ToMir for Type {
@@ -155,6 +300,15 @@ impl ToMir for Type {
         match self {
             Self::Primitive(primitive) => MirType::Primitive(primitive.into_mir()),
             Self::UserDefined(ident) => MirType::UserDefined(ident.into_mir()),
+            Self::Array(array) => {
+                let (inner, len) = *array;
+                let len = Type::array_len(&len);
+                MirType::Array(Box::new(inner.into_mir()), len)
+            }
+            Self::Optional(optional) => {
+                let (inner, _) = *optional;
+                MirType::Optional(Box::new(inner.into_mir()))
+            }
         }
     }
 }
@@ -165,6 +319,10 @@ pub struct MirDeclaration<V: VariableName> {
     pub name: V,
     pub ty: MirType<V>,
     pub value: Option<MirExpression<V>>,
+    /// Span of the name.
+    pub span: Span,
+    /// Span of the type.
+    pub ty_span: Span,
 }
 
 impl ToMir for Declaration {
@@ -176,7 +334,9 @@ impl ToMir for Declaration {
                 DeclStart::Static(_) => true,
                 DeclStart::Let(_) => false,
             },
+            span: self.ident.span(),
             name: self.ident.into_inner(),
+            ty_span: self.ty.span(),
             ty: self.ty.into_mir(),
             value: self.value.map(|(_, expr)| expr.into_mir()),
         }
@@ -190,6 +350,8 @@ pub struct MirFunction<V: VariableName> {
     pub args: Vec<(V, MirType<V>)>,
     pub return_type: MirType<V>,
     pub block: Vec<MirStatement<V>>,
+    /// Span of the name.
+    pub span: Span,
 }
 
 impl ToMir for FunctionDecl {
@@ -198,6 +360,7 @@ impl ToMir for FunctionDecl {
     fn into_mir(self) -> Self::Output {
         MirFunction {
             is_static: self.staticness.is_some(),
+            span: self.ident.span(),
             name: self.ident.into_inner(),
             args: self
                 .args
@@ -212,6 +375,154 @@ impl ToMir for FunctionDecl {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct MirStruct<V: VariableName> {
+    pub name: XID,
+    pub fields: Vec<(XID, MirType<V>)>,
+    /// Span of the name.
+    pub span: Span,
+}
+
+impl ToMir for StructDecl {
+    type Output = MirStruct<XID>;
+
+    fn into_mir(self) -> Self::Output {
+        MirStruct {
+            span: self.ident.span(),
+            name: self.ident.into_inner(),
+            fields: self
+                .fields
+                .into_contents()
+                .into_tokens()
+                .into_iter()
+                .map(|field| (field.name.into_inner(), field.ty.into_mir()))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MirEnum<V: VariableName> {
+    pub name: XID,
+    /// Variants in declaration order, their index is the discriminant.
+    pub variants: Vec<(XID, Vec<MirType<V>>)>,
+    /// Span of the name.
+    pub span: Span,
+}
+
+impl ToMir for EnumDecl {
+    type Output = MirEnum<XID>;
+
+    fn into_mir(self) -> Self::Output {
+        MirEnum {
+            span: self.ident.span(),
+            name: self.ident.into_inner(),
+            variants: self
+                .variants
+                .into_contents()
+                .into_tokens()
+                .into_iter()
+                .map(|variant| {
+                    let payload = match variant.payload {
+                        Some(payload) => payload
+                            .into_contents()
+                            .into_tokens()
+                            .into_iter()
+                            .map(ToMir::into_mir)
+                            .collect(),
+                        None => vec![],
+                    };
+                    (variant.name.into_inner(), payload)
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MirMatch<V: VariableName> {
+    pub scrutinee: MirExpression<V>,
+    pub arms: Vec<MirArm<V>>,
+}
+
+impl ToMir for MatchExpr {
+    type Output = MirMatch<XID>;
+
+    fn into_mir(self) -> Self::Output {
+        MirMatch {
+            scrutinee: self.scrutinee.into_contents().into_mir(),
+            arms: self
+                .arms
+                .into_contents()
+                .into_iter()
+                .map(|arm| MirArm {
+                    span: arm.pattern.span(),
+                    pattern: arm.pattern.into_mir(),
+                    body: match arm.body {
+                        ArmBody::Block(block) => {
+                            MirArmBody::Block(block.into_contents().into_mir())
+                        }
+                        ArmBody::Expression(expr) => MirArmBody::Expression(expr.into_mir()),
+                    },
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MirArm<V: VariableName> {
+    pub pattern: MirPattern<V>,
+    /// Span of the pattern.
+    pub span: Span,
+    pub body: MirArmBody<V>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MirArmBody<V: VariableName> {
+    Block(Vec<MirStatement<V>>),
+    Expression(MirExpression<V>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MirPattern<V: VariableName> {
+    Wildcard,
+    /// Bindings are `None` for payload values ignored with `_`.
+    Variant {
+        enum_path: V::OtherPath,
+        variant: XID,
+        bindings: Vec<Option<V>>,
+    },
+}
+
+impl ToMir for Pattern {
+    type Output = MirPattern<XID>;
+
+    fn into_mir(self) -> Self::Output {
+        if self.is_wildcard() {
+            return MirPattern::Wildcard;
+        }
+
+        let mut enum_path = self.path.into_mir();
+        let variant = enum_path
+            .pop()
+            .expect("Patterns have at least two segments");
+        MirPattern::Variant {
+            enum_path,
+            variant,
+            bindings: match self.bindings {
+                Some(bindings) => bindings
+                    .into_contents()
+                    .into_tokens()
+                    .into_iter()
+                    .map(|ident| (ident.inner() != "_").then(|| ident.into_inner()))
+                    .collect(),
+                None => vec![],
+            },
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct MirIf<V: VariableName> {
     pub condition: MirExpression<V>,
@@ -250,6 +561,9 @@ impl ToMir for ElseBlock {
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct MirWhile<V: VariableName> {
+    pub label: Option<XID>,
+    /// Whether iterations may be spread over several ticks.
+    pub is_spread: bool,
     pub condition: MirExpression<V>,
     pub block: Vec<MirStatement<V>>,
 }
@@ -259,6 +573,8 @@ impl ToMir for WhileLoop {
 
     fn into_mir(self) -> Self::Output {
         MirWhile {
+            label: self.label.map(|label| label.ident.into_inner()),
+            is_spread: self.spread.is_some(),
             condition: self.condition.into_contents().into_mir(),
             block: self.block.into_contents().into_mir(),
         }
@@ -267,6 +583,9 @@ impl ToMir for WhileLoop {
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct MirFor<V: VariableName> {
+    pub label: Option<XID>,
+    /// Whether iterations may be spread over several ticks.
+    pub is_spread: bool,
     pub init: MirDeclaration<V>,
     pub condition: MirExpression<V>,
     pub update: MirStatement<V>,
@@ -279,6 +598,8 @@ impl ToMir for ForLoop {
     fn into_mir(self) -> Self::Output {
         let inner = self.inner.into_contents();
         MirFor {
+            label: self.label.map(|label| label.ident.into_inner()),
+            is_spread: self.spread.is_some(),
             init: inner.init.into_mir(),
             condition: inner.condition.0.into_mir(),
             update: inner.update.into_mir(),
@@ -289,7 +610,10 @@ impl ToMir for ForLoop {
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct MirAssignment<V: VariableName> {
-    pub variable: V::VariablePath,
+    /// A variable or a field of one, other expressions are rejected by type checking.
+    pub target: MirExpression<V>,
+    /// The operator of compound assignments, `x += 1` is `x = x + 1` with `x` evaluated once.
+    pub op: Option<MirBinaryOp>,
     pub value: MirExpression<V>,
 }
 
@@ -297,8 +621,17 @@ impl ToMir for Assignment {
     type Output = MirAssignment<XID>;
 
     fn into_mir(self) -> Self::Output {
+        let op = match self.assign {
+            AssignOp::Assign(_) => None,
+            AssignOp::Add(_) => Some(MirBinaryOp::Add),
+            AssignOp::Subtract(_) => Some(MirBinaryOp::Subtract),
+            AssignOp::Multiply(_) => Some(MirBinaryOp::Multiply),
+            AssignOp::Divide(_) => Some(MirBinaryOp::Divide),
+            AssignOp::Modulo(_) => Some(MirBinaryOp::Modulo),
+        };
         MirAssignment {
-            variable: self.variable.into_mir(),
+            target: self.target.into_mir(),
+            op,
             value: self.value.into_mir(),
         }
     }
@@ -0,0 +1,1552 @@
+use super::{
+    AbsolutePath, AbsoluteVar, CommandPart, MirArm, MirArmBody, MirAssignment, MirBinaryOp,
+    MirDeclaration, MirElseBlock, MirEnum, MirExpression, MirExpressionKind, MirFor, MirFunction,
+    MirIf, MirMatch, MirPattern, MirStatement, MirStruct, MirType, MirUnaryOp,
+};
+use crate::{
+    diagnostic::Diagnostic,
+    hir::mir::MirPrimitive,
+    token::{LiteralType, XID},
+    Span,
+};
+use std::collections::HashMap;
+use thiserror::Error;
+
+type Type = MirType<AbsoluteVar>;
+
+#[derive(Debug, Error)]
+pub enum TypeError {
+    #[error("Mismatched types, expected `{0}` but found `{1}`")]
+    Mismatch(Type, Type, Span),
+    #[error("Cannot combine `{0}` with `{2}`")]
+    OperandMismatch(Type, Span, Type, Span),
+    #[error("Cannot find variable `{0}`")]
+    UnknownVariable(AbsolutePath, Span),
+    #[error("Cannot find function `{0}`")]
+    UnknownFunction(AbsolutePath, Span),
+    #[error("Function `{0}` takes {1} arguments but {2} were supplied")]
+    ArgumentCount(AbsolutePath, usize, usize, Span),
+    #[error("Cannot apply `{0}` to a value of type `{1}`")]
+    InvalidOperand(String, Type, Span),
+    #[error("Cannot convert a value of type `{0}` to `{1}`")]
+    InvalidConversion(Type, Type, Span),
+    #[error("Cannot implicitly convert a value of type `{0}` to `{1}`")]
+    LossyConversion(Type, Type, Span),
+    #[error("Conditions must be `bool`, but found a value of type `{0}`")]
+    InvalidCondition(Type, Span),
+    #[error("Cannot use `null` as a value of type `{0}`")]
+    NonOptionalNull(Type, Span),
+    #[error("Cannot infer the type of `null`")]
+    UntypedNull(Span),
+    #[error("Cannot find struct `{0}`")]
+    UnknownStruct(AbsolutePath, Span),
+    #[error("No field `{1}` on type `{0}`")]
+    UnknownField(Type, XID, Span),
+    #[error("Field `{0}` is specified more than once")]
+    DuplicateField(XID, Span),
+    #[error("Missing {} in struct `{0}`", list("field", .1))]
+    MissingFields(AbsolutePath, Vec<XID>, Span),
+    #[error("Cannot index into a value of type `{0}`")]
+    InvalidIndex(Type, Span),
+    #[error("Index {0} is out of bounds for an array of length {1}")]
+    IndexOutOfBounds(i32, u32, Span),
+    #[error("Cannot assign to this expression")]
+    InvalidAssignment(Span),
+    #[error("Cannot find enum `{0}`")]
+    UnknownEnum(AbsolutePath, Span),
+    #[error("No variant `{1}` in enum `{0}`")]
+    UnknownVariant(AbsolutePath, XID, Span),
+    #[error("Variant `{0}` holds {1} values but {2} were supplied")]
+    VariantPayload(XID, usize, usize, Span),
+    #[error("Cannot match on a value of type `{0}`")]
+    InvalidMatch(Type, Span),
+    #[error("Non-exhaustive match, {} not covered", list("variant", .0))]
+    NonExhaustive(Vec<XID>, Span),
+    #[error("Unreachable match arm")]
+    UnreachableArm(Span),
+    #[error("Match arm has no value")]
+    ArmWithoutValue(Span),
+}
+
+/// Lists `names` after `noun`, which is made plural if there is more than one name.
+fn list(noun: &str, names: &[XID]) -> String {
+    let names: Vec<String> = names.iter().map(|name| format!("`{}`", name)).collect();
+    match names.as_slice() {
+        [name] => format!("{} {}", noun, name),
+        names => format!("{}s {}", noun, names.join(", ")),
+    }
+}
+
+pub type TypeResult<T> = Result<T, TypeError>;
+
+impl From<TypeError> for Diagnostic {
+    fn from(err: TypeError) -> Self {
+        let diagnostic = match err {
+            TypeError::UnreachableArm(_) => Diagnostic::warning(&err),
+            _ => Diagnostic::error(&err),
+        };
+        match err {
+            TypeError::Mismatch(expected, found, span) => {
+                diagnostic.with_primary(span, format!("expected `{}`, found `{}`", expected, found))
+            }
+            TypeError::OperandMismatch(left, left_span, right, right_span) => {
+                let help = (is_numeric(&left) && is_numeric(&right))
+                    .then(|| format!("convert one side with `as`, like `… as {}`", left));
+                let diagnostic = diagnostic
+                    .with_primary(right_span, format!("this is `{}`", right))
+                    .with_secondary(left_span, format!("this is `{}`", left))
+                    .with_note("both sides of an operator must have the same type");
+                match help {
+                    Some(help) => diagnostic.with_help(help),
+                    None => diagnostic,
+                }
+            }
+            TypeError::UnknownVariable(_, span)
+            | TypeError::UnknownFunction(_, span)
+            | TypeError::UnknownStruct(_, span)
+            | TypeError::UnknownEnum(_, span) => {
+                diagnostic.with_primary(span, "not found in this scope")
+            }
+            TypeError::UnknownField(ty, _, span) => {
+                diagnostic.with_primary(span, format!("`{}` has no such field", ty))
+            }
+            TypeError::DuplicateField(_, span) => {
+                diagnostic.with_primary(span, "already specified")
+            }
+            TypeError::MissingFields(_, fields, span) => {
+                diagnostic.with_primary(span, format!("missing {}", list("field", &fields)))
+            }
+            TypeError::UnknownVariant(path, _, span) => {
+                diagnostic.with_primary(span, format!("not a variant of `{}`", path))
+            }
+            TypeError::VariantPayload(_, expected, _, span) => {
+                diagnostic.with_primary(span, format!("expected {} values", expected))
+            }
+            TypeError::InvalidMatch(ty, span) => diagnostic
+                .with_primary(span, format!("this is `{}`", ty))
+                .with_help("only enums can be matched on"),
+            TypeError::NonExhaustive(variants, span) => diagnostic
+                .with_primary(span, format!("{} not covered", list("variant", &variants)))
+                .with_help("add an arm for them, or a `_` arm matching anything else"),
+            TypeError::UnreachableArm(span) => {
+                diagnostic.with_primary(span, "earlier arms already match every value this does")
+            }
+            TypeError::ArmWithoutValue(span) => diagnostic
+                .with_primary(span, "this arm runs a block")
+                .with_help("arms of a `match` used as a value must be expressions"),
+            TypeError::InvalidIndex(ty, span) => diagnostic
+                .with_primary(span, format!("this is `{}`", ty))
+                .with_help("only arrays can be indexed"),
+            TypeError::IndexOutOfBounds(_, _, span) => {
+                diagnostic.with_primary(span, "out of bounds")
+            }
+            TypeError::InvalidAssignment(span) => {
+                diagnostic.with_primary(span, "not a variable").with_help(
+                    "only variables, their fields and elements of arrays can be assigned to",
+                )
+            }
+            TypeError::ArgumentCount(_, expected, _, span) => {
+                diagnostic.with_primary(span, format!("expected {} arguments", expected))
+            }
+            TypeError::InvalidOperand(_, ty, span) => {
+                diagnostic.with_primary(span, format!("this is `{}`", ty))
+            }
+            TypeError::InvalidConversion(ty, _, span) => diagnostic
+                .with_primary(span, format!("this is `{}`", ty))
+                .with_help("numbers convert to other numbers and `string`, `bool`s to integers"),
+            TypeError::LossyConversion(found, expected, span) => diagnostic
+                .with_primary(span, format!("expected `{}`, found `{}`", expected, found))
+                .with_note("only conversions which keep every value are implicit")
+                .with_help(format!(
+                    "convert it explicitly with `as {}` if the value always fits",
+                    expected
+                )),
+            TypeError::InvalidCondition(ty, span) => {
+                let help =
+                    is_numeric(&ty).then_some("compare the value instead, like `value != 0`");
+                diagnostic
+                    .with_primary(span, format!("expected `bool`, found `{}`", ty))
+                    .with_help(help.unwrap_or("conditions are `bool` values or comparisons"))
+            }
+            TypeError::NonOptionalNull(ty, span) => diagnostic
+                .with_primary(span, format!("expected `{}`", ty))
+                .with_help(format!("make the type optional with `{}?`", ty)),
+            TypeError::UntypedNull(span) => diagnostic
+                .with_primary(span, "the type of this is unknown")
+                .with_help("use it where an optional type like `int?` is expected"),
+        }
+    }
+}
+
+/// Checks that every expression has the type its surroundings expect.
+///
+/// Values whose type cannot be known yet, like properties and items from other modules, are
+/// accepted anywhere. `unsafe` blocks are not checked. Checking continues after an error, every
+/// statement reports its first one.
+///
+/// Numbers are converted implicitly where no value can change, which is made explicit by
+/// wrapping them in a [`MirExpressionKind::Cast`]. Integer literals take the integer type
+/// expected of them if their value fits. Values of any type can be used where an optional of it
+/// is expected, the other way around takes `??`.
+pub fn check_types(statements: &mut [MirStatement<AbsoluteVar>]) -> Vec<TypeError> {
+    let mut checker = TypeChecker {
+        functions: HashMap::new(),
+        structs: HashMap::new(),
+        enums: HashMap::new(),
+        scopes: vec![],
+        return_type: None,
+        errors: vec![],
+    };
+    checker.collect_items(statements);
+    checker.check_block(statements);
+    checker.errors
+}
+
+/// What calls to a function are checked against.
+struct Signature {
+    args: Vec<Type>,
+    return_type: Type,
+}
+
+struct TypeChecker {
+    functions: HashMap<String, Signature>,
+    structs: HashMap<String, MirStruct<AbsoluteVar>>,
+    enums: HashMap<String, MirEnum<AbsoluteVar>>,
+    /// Variables visible at the current statement, innermost scope last.
+    scopes: Vec<HashMap<String, Type>>,
+    /// Return type of the function being checked, if any.
+    return_type: Option<Type>,
+    errors: Vec<TypeError>,
+}
+
+fn primitive(primitive: MirPrimitive) -> Type {
+    MirType::Primitive(primitive)
+}
+
+fn is_integer(ty: &Type) -> bool {
+    matches!(
+        ty,
+        MirType::Primitive(
+            MirPrimitive::Byte | MirPrimitive::Short | MirPrimitive::Int | MirPrimitive::Long
+        )
+    )
+}
+
+fn is_bool(ty: &Type) -> bool {
+    *ty == primitive(MirPrimitive::Bool)
+}
+
+fn is_string(ty: &Type) -> bool {
+    *ty == primitive(MirPrimitive::String)
+}
+
+fn is_numeric(ty: &Type) -> bool {
+    is_integer(ty)
+        || matches!(
+            ty,
+            MirType::Primitive(MirPrimitive::Float | MirPrimitive::Double)
+        )
+}
+
+/// Whether every value of `from` is also a value of `to`, which makes the conversion implicit.
+/// `float`s and `double`s are fixed-point numbers with a configurable scale, only `byte`s and
+/// `short`s fit them at any scale.
+fn widens(from: &Type, to: &Type) -> bool {
+    let (MirType::Primitive(from), MirType::Primitive(to)) = (from, to) else {
+        return false;
+    };
+    matches!(
+        (from, to),
+        (
+            MirPrimitive::Byte,
+            MirPrimitive::Short
+                | MirPrimitive::Int
+                | MirPrimitive::Long
+                | MirPrimitive::Float
+                | MirPrimitive::Double
+        ) | (
+            MirPrimitive::Short,
+            MirPrimitive::Int | MirPrimitive::Long | MirPrimitive::Float | MirPrimitive::Double
+        ) | (MirPrimitive::Int, MirPrimitive::Long)
+    )
+}
+
+/// Whether `as` can convert values of `from` to `to`. Numbers convert to every other number and
+/// are formatted as strings, `bool`s convert to integers.
+fn converts(from: &Type, to: MirPrimitive) -> bool {
+    let to = primitive(to);
+    *from == to
+        || (is_numeric(from) && (is_numeric(&to) || is_string(&to)))
+        || (is_bool(from) && is_integer(&to))
+}
+
+/// The integer literal `literal` as a literal of the integer type `ty`, if its value fits. Only
+/// unsuffixed literals, which are `int`s, shrink to fit, so `2l` stays a `long`.
+fn fit_literal(literal: &LiteralType, ty: &Type) -> Option<LiteralType> {
+    let (value, from) = match *literal {
+        LiteralType::Byte(value) => (value as i64, MirPrimitive::Byte),
+        LiteralType::Short(value) => (value as i64, MirPrimitive::Short),
+        LiteralType::Int(value) => (value as i64, MirPrimitive::Int),
+        LiteralType::Long(value) => (value, MirPrimitive::Long),
+        _ => return None,
+    };
+    if from != MirPrimitive::Int && !widens(&primitive(from), ty) {
+        return None;
+    }
+    match ty {
+        MirType::Primitive(MirPrimitive::Byte) => value.try_into().ok().map(LiteralType::Byte),
+        MirType::Primitive(MirPrimitive::Short) => value.try_into().ok().map(LiteralType::Short),
+        MirType::Primitive(MirPrimitive::Int) => value.try_into().ok().map(LiteralType::Int),
+        MirType::Primitive(MirPrimitive::Long) => Some(LiteralType::Long(value)),
+        _ => None,
+    }
+}
+
+/// Turns the integer literal `expr` into a literal of type `ty`, if its value fits.
+fn convert_literal(expr: &mut MirExpression<AbsoluteVar>, ty: &Type) -> bool {
+    let MirExpressionKind::Literal(literal) = &expr.kind else {
+        return false;
+    };
+    match fit_literal(literal, ty) {
+        Some(literal) => {
+            expr.kind = MirExpressionKind::Literal(literal);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Converts `expr`, a value of type `found`, to `expected` if that happens implicitly.
+fn coerce(expr: &mut MirExpression<AbsoluteVar>, found: &Type, expected: &Type) -> bool {
+    // Optional values are stored like others, only being unset sets them apart.
+    if let MirType::Optional(inner) = expected {
+        return found == &**inner || coerce(expr, found, inner);
+    }
+
+    if convert_literal(expr, expected) {
+        return true;
+    }
+
+    let MirType::Primitive(to) = expected else {
+        return false;
+    };
+    if !widens(found, expected) {
+        return false;
+    }
+
+    let placeholder = MirExpressionKind::Literal(LiteralType::Bool(false));
+    let inner = MirExpression::new(std::mem::replace(&mut expr.kind, placeholder), expr.span);
+    expr.kind = MirExpressionKind::Cast(Box::new(inner), *to);
+    true
+}
+
+/// The type both `left` and `right` have after converting one of them implicitly, if any. A
+/// literal takes the type of the other side if it fits, so `small + 1` stays a `byte`. Otherwise
+/// the narrower side is widened.
+fn unify(
+    left: &mut MirExpression<AbsoluteVar>,
+    left_type: Type,
+    right: &mut MirExpression<AbsoluteVar>,
+    right_type: Type,
+) -> Option<Type> {
+    if left_type == right_type {
+        return Some(left_type);
+    }
+
+    let is_literal = |expr: &MirExpression<_>| matches!(expr.kind, MirExpressionKind::Literal(_));
+    let (left_literal, right_literal) = (is_literal(left), is_literal(right));
+    if !left_literal && right_literal && convert_literal(right, &left_type) {
+        Some(left_type)
+    } else if left_literal && !right_literal && convert_literal(left, &right_type)
+        || coerce(left, &left_type, &right_type)
+    {
+        Some(right_type)
+    } else if coerce(right, &right_type, &left_type) {
+        Some(left_type)
+    } else {
+        None
+    }
+}
+
+fn is_null(expr: &MirExpression<AbsoluteVar>) -> bool {
+    matches!(expr.kind, MirExpressionKind::Null)
+}
+
+fn optional(ty: Type) -> Type {
+    match ty {
+        MirType::Optional(_) => ty,
+        ty => MirType::Optional(Box::new(ty)),
+    }
+}
+
+/// Whether `expr` can be assigned to.
+fn is_place(expr: &MirExpression<AbsoluteVar>) -> bool {
+    match &expr.kind {
+        MirExpressionKind::Variable(_) => true,
+        MirExpressionKind::Property(left, _) | MirExpressionKind::Index(left, _) => is_place(left),
+        _ => false,
+    }
+}
+
+fn literal_type(literal: &LiteralType) -> Type {
+    primitive(match literal {
+        LiteralType::Bool(_) => MirPrimitive::Bool,
+        LiteralType::Byte(_) => MirPrimitive::Byte,
+        LiteralType::Short(_) => MirPrimitive::Short,
+        LiteralType::Int(_) => MirPrimitive::Int,
+        LiteralType::Long(_) => MirPrimitive::Long,
+        LiteralType::Float(_) => MirPrimitive::Float,
+        LiteralType::Double(_) => MirPrimitive::Double,
+        LiteralType::String(_) => MirPrimitive::String,
+    })
+}
+
+impl TypeChecker {
+    fn collect_items(&mut self, statements: &[MirStatement<AbsoluteVar>]) {
+        for statement in statements {
+            match statement {
+                MirStatement::Function(func) => {
+                    self.functions.insert(
+                        func.name.inner().to_string(),
+                        Signature {
+                            args: func.args.iter().map(|(_, ty)| ty.clone()).collect(),
+                            return_type: func.return_type.clone(),
+                        },
+                    );
+                    self.collect_items(&func.block);
+                }
+                MirStatement::Struct(decl) => {
+                    self.structs
+                        .insert(decl.name.inner().to_string(), decl.clone());
+                }
+                MirStatement::Enum(decl) => {
+                    self.enums
+                        .insert(decl.name.inner().to_string(), decl.clone());
+                }
+                MirStatement::Block(block) | MirStatement::Unsafe(block) => {
+                    self.collect_items(block)
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn variable(&self, path: &AbsolutePath, span: Span) -> TypeResult<Option<Type>> {
+        let [variable] = path.inner() else {
+            return Ok(None);
+        };
+
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(variable.inner()))
+            .map(|ty| Some(ty.clone()))
+            .ok_or_else(|| TypeError::UnknownVariable(path.clone(), span))
+    }
+
+    fn declare(&mut self, decl: &MirDeclaration<AbsoluteVar>) {
+        self.scopes
+            .last_mut()
+            .expect("Declarations are always inside a scope")
+            .insert(decl.name.inner().to_string(), decl.ty.clone());
+    }
+
+    fn report(&mut self, result: TypeResult<()>) {
+        if let Err(err) = result {
+            self.errors.push(err);
+        }
+    }
+
+    fn check_block(&mut self, statements: &mut [MirStatement<AbsoluteVar>]) {
+        self.scopes.push(HashMap::new());
+
+        // Statics exist for the whole program, they can be used before their declaration.
+        for statement in statements.iter() {
+            if let MirStatement::Declaration(decl) = statement {
+                if decl.is_static {
+                    self.declare(decl);
+                }
+            }
+        }
+
+        for statement in statements {
+            self.check_statement(statement);
+        }
+        self.scopes.pop();
+    }
+
+    fn check_statement(&mut self, statement: &mut MirStatement<AbsoluteVar>) {
+        let result = match statement {
+            MirStatement::Block(block) => {
+                self.check_block(block);
+                Ok(())
+            }
+            MirStatement::Expression(expr) => self.infer(expr).map(|_| ()),
+            MirStatement::Return(expr) => match self.return_type.clone() {
+                Some(return_type) => self.expect(&return_type, expr),
+                None => self.infer(expr).map(|_| ()),
+            },
+            MirStatement::Assignment(assign) => self.check_assignment(assign),
+            MirStatement::Declaration(decl) => {
+                self.check_declaration(decl);
+                Ok(())
+            }
+            MirStatement::Function(func) => {
+                self.check_function(func);
+                Ok(())
+            }
+            MirStatement::If(if_block) => {
+                self.check_if(if_block);
+                Ok(())
+            }
+            MirStatement::While(while_loop) => {
+                let result = self.check_condition(&mut while_loop.condition);
+                self.report(result);
+                self.check_block(&mut while_loop.block);
+                Ok(())
+            }
+            MirStatement::For(for_loop) => {
+                self.check_for(for_loop);
+                Ok(())
+            }
+            MirStatement::Match(match_stmt) => self.check_match(match_stmt, false).map(|_| ()),
+            MirStatement::Struct(_)
+            | MirStatement::Enum(_)
+            | MirStatement::Unsafe(_)
+            | MirStatement::Break(_, _)
+            | MirStatement::Continue(_, _)
+            | MirStatement::Import(_) => Ok(()),
+        };
+        self.report(result);
+    }
+
+    /// The variable is declared even if its value is invalid, so its uses are still checked.
+    fn check_declaration(&mut self, decl: &mut MirDeclaration<AbsoluteVar>) {
+        if let Some(value) = &mut decl.value {
+            let result = self.expect(&decl.ty, value);
+            self.report(result);
+        }
+        self.declare(decl);
+    }
+
+    fn check_assignment(&mut self, assign: &mut MirAssignment<AbsoluteVar>) -> TypeResult<()> {
+        if !is_place(&assign.target) {
+            return Err(TypeError::InvalidAssignment(assign.target.span));
+        }
+
+        let ty = self.infer(&mut assign.target)?;
+        let Some(op) = assign.op else {
+            return match ty {
+                Some(ty) => self.expect(&ty, &mut assign.value),
+                None => self.infer(&mut assign.value).map(|_| ()),
+            };
+        };
+
+        // `x += y` is checked as `x = x + y`, keeping any conversion of `y`.
+        let span = Span::from_start_end(assign.target.span, assign.value.span);
+        let mut combined = MirExpression::new(
+            MirExpressionKind::BinaryOp(
+                Box::new(assign.target.clone()),
+                op,
+                Box::new(assign.value.clone()),
+            ),
+            span,
+        );
+        let result = match ty {
+            Some(ty) => self.expect(&ty, &mut combined),
+            None => self.infer(&mut combined).map(|_| ()),
+        };
+        if let MirExpressionKind::BinaryOp(_, _, value) = combined.kind {
+            assign.value = *value;
+        }
+        result
+    }
+
+    fn check_function(&mut self, func: &mut MirFunction<AbsoluteVar>) {
+        let args = func
+            .args
+            .iter()
+            .map(|(name, ty)| (name.inner().to_string(), ty.clone()))
+            .collect();
+        self.scopes.push(args);
+        let return_type = self.return_type.replace(func.return_type.clone());
+
+        self.check_block(&mut func.block);
+
+        self.return_type = return_type;
+        self.scopes.pop();
+    }
+
+    fn check_if(&mut self, if_block: &mut MirIf<AbsoluteVar>) {
+        let result = self.check_condition(&mut if_block.condition);
+        self.report(result);
+        self.check_block(&mut if_block.block);
+
+        match &mut if_block.else_block {
+            Some(MirElseBlock::ElseIf(if_block)) => self.check_if(if_block),
+            Some(MirElseBlock::Else(block)) => self.check_block(block),
+            None => (),
+        }
+    }
+
+    fn check_for(&mut self, for_loop: &mut MirFor<AbsoluteVar>) {
+        // The loop variable is only visible inside the loop.
+        self.scopes.push(HashMap::new());
+        self.check_declaration(&mut for_loop.init);
+        let result = self.check_condition(&mut for_loop.condition);
+        self.report(result);
+        self.check_statement(&mut for_loop.update);
+        self.check_block(&mut for_loop.block);
+        self.scopes.pop();
+    }
+
+    fn check_condition(&mut self, condition: &mut MirExpression<AbsoluteVar>) -> TypeResult<()> {
+        match self.infer(condition)? {
+            Some(ty) if !is_bool(&ty) => Err(TypeError::InvalidCondition(ty, condition.span)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fails unless `expr` could be a value of type `expected`, converting it implicitly if
+    /// needed.
+    fn expect(&mut self, expected: &Type, expr: &mut MirExpression<AbsoluteVar>) -> TypeResult<()> {
+        // Elements of array literals are converted one at a time.
+        if let (MirType::Array(element, len), MirExpressionKind::Array(elements)) =
+            (expected, &mut expr.kind)
+        {
+            if *len as usize == elements.len() {
+                for value in elements {
+                    self.expect(element, value)?;
+                }
+                return Ok(());
+            }
+        }
+
+        if is_null(expr) {
+            return match expected {
+                MirType::Optional(_) => Ok(()),
+                _ => Err(TypeError::NonOptionalNull(expected.clone(), expr.span)),
+            };
+        }
+
+        match self.infer(expr)? {
+            Some(found) => self.convert(expected, expr, found),
+            None => Ok(()),
+        }
+    }
+
+    /// Fails unless `expr`, a value of type `found`, converts to `expected` implicitly.
+    fn convert(
+        &self,
+        expected: &Type,
+        expr: &mut MirExpression<AbsoluteVar>,
+        found: Type,
+    ) -> TypeResult<()> {
+        if found == *expected || coerce(expr, &found, expected) {
+            Ok(())
+        } else if is_numeric(&found) && is_numeric(expected) {
+            Err(TypeError::LossyConversion(
+                found,
+                expected.clone(),
+                expr.span,
+            ))
+        } else {
+            Err(TypeError::Mismatch(expected.clone(), found, expr.span))
+        }
+    }
+
+    /// The type of `expr`, or `None` if it cannot be known.
+    fn infer(&mut self, expr: &mut MirExpression<AbsoluteVar>) -> TypeResult<Option<Type>> {
+        let span = expr.span;
+        match &mut expr.kind {
+            MirExpressionKind::Literal(literal) => Ok(Some(literal_type(literal))),
+            MirExpressionKind::Null => Err(TypeError::UntypedNull(span)),
+            MirExpressionKind::Variable(path) => self.variable(path, span),
+            MirExpressionKind::Command(parts) => {
+                for part in parts {
+                    if let CommandPart::Value(value) = part {
+                        self.infer(value)?;
+                    }
+                }
+                Ok(Some(primitive(MirPrimitive::Int)))
+            }
+            MirExpressionKind::Tellraw(_, parts) => {
+                for part in parts {
+                    self.infer(part)?;
+                }
+                Ok(Some(primitive(MirPrimitive::Void)))
+            }
+            MirExpressionKind::Property(left, field) => match self.infer(left)? {
+                Some(ty) => self.field_type(ty, field, span),
+                None => Ok(None),
+            },
+            MirExpressionKind::Array(elements) => self.infer_array(elements),
+            MirExpressionKind::Index(left, index) => self.infer_index(left, index),
+            MirExpressionKind::Call(path, args) => self.infer_call(path, args, span),
+            MirExpressionKind::Struct(path, fields) => self.infer_struct(path, fields, span),
+            MirExpressionKind::Variant(path, variant, args) => {
+                self.infer_variant(path, variant, args, span)
+            }
+            MirExpressionKind::Match(match_expr) => self.check_match(match_expr, true),
+            MirExpressionKind::UnaryOp(op, operand) => {
+                let ty = self.infer(operand)?;
+                let (valid, result): (fn(&Type) -> bool, _) = match op {
+                    MirUnaryOp::Not => (is_bool, Some(MirPrimitive::Bool)),
+                    MirUnaryOp::Negate => (is_numeric, None),
+                };
+                self.check_operand(&op.to_string(), &ty, valid, operand.span)?;
+                Ok(result.map(primitive).or(ty))
+            }
+            MirExpressionKind::BinaryOp(left, op, right) => self.infer_binary_op(left, *op, right),
+            MirExpressionKind::Ternary(condition, then, otherwise) => {
+                self.infer_ternary(condition, then, otherwise)
+            }
+            MirExpressionKind::Nbt(_) => Ok(Some(primitive(MirPrimitive::Double))),
+            MirExpressionKind::Cast(value, target) => match self.infer(value)? {
+                Some(ty) if !converts(&ty, *target) => Err(TypeError::InvalidConversion(
+                    ty,
+                    primitive(*target),
+                    value.span,
+                )),
+                _ => Ok(Some(primitive(*target))),
+            },
+        }
+    }
+
+    fn infer_call(
+        &mut self,
+        path: &AbsolutePath,
+        args: &mut [MirExpression<AbsoluteVar>],
+        span: Span,
+    ) -> TypeResult<Option<Type>> {
+        let [name] = path.inner() else {
+            for arg in args {
+                self.infer(arg)?;
+            }
+            return Ok(None);
+        };
+
+        let func = self
+            .functions
+            .get(name.inner())
+            .ok_or_else(|| TypeError::UnknownFunction(path.clone(), span))?;
+        if func.args.len() != args.len() {
+            return Err(TypeError::ArgumentCount(
+                path.clone(),
+                func.args.len(),
+                args.len(),
+                span,
+            ));
+        }
+
+        let (params, return_type) = (func.args.clone(), func.return_type.clone());
+        for (ty, arg) in params.iter().zip(args) {
+            self.expect(ty, arg)?;
+        }
+
+        Ok(Some(return_type))
+    }
+
+    /// Elements must all have the type of the first one.
+    fn infer_array(
+        &mut self,
+        elements: &mut [MirExpression<AbsoluteVar>],
+    ) -> TypeResult<Option<Type>> {
+        let mut element_type: Option<Type> = None;
+        for element in elements.iter_mut() {
+            match &element_type {
+                Some(ty) => {
+                    let ty = ty.clone();
+                    self.expect(&ty, element)?
+                }
+                None => element_type = self.infer(element)?,
+            }
+        }
+
+        Ok(element_type.map(|ty| MirType::Array(Box::new(ty), elements.len() as u32)))
+    }
+
+    /// Constant indices are checked against the length of the array.
+    fn infer_index(
+        &mut self,
+        left: &mut MirExpression<AbsoluteVar>,
+        index: &mut MirExpression<AbsoluteVar>,
+    ) -> TypeResult<Option<Type>> {
+        let array = self.infer(left)?;
+        match self.infer(index)? {
+            Some(ty) if !is_integer(&ty) => {
+                return Err(TypeError::Mismatch(
+                    primitive(MirPrimitive::Int),
+                    ty,
+                    index.span,
+                ))
+            }
+            _ => (),
+        }
+
+        match array {
+            Some(MirType::Array(element, len)) => {
+                if let MirExpressionKind::Literal(LiteralType::Int(value)) = index.kind {
+                    if value < 0 || value as u32 >= len {
+                        return Err(TypeError::IndexOutOfBounds(value, len, index.span));
+                    }
+                }
+                Ok(Some(*element))
+            }
+            Some(ty) => Err(TypeError::InvalidIndex(ty, left.span)),
+            None => Ok(None),
+        }
+    }
+
+    /// The struct declaration `ty` refers to, if it can be found.
+    fn struct_decl(&self, ty: &Type) -> Option<&MirStruct<AbsoluteVar>> {
+        match ty {
+            MirType::UserDefined(path) => match path.inner() {
+                [name] => self.structs.get(name.inner()),
+                _ => None,
+            },
+            MirType::Primitive(_) | MirType::Array(_, _) | MirType::Optional(_) => None,
+        }
+    }
+
+    fn is_enum(&self, ty: &Type) -> bool {
+        match ty {
+            MirType::UserDefined(path) => match path.inner() {
+                [name] => self.enums.contains_key(name.inner()),
+                _ => false,
+            },
+            MirType::Primitive(_) | MirType::Array(_, _) | MirType::Optional(_) => false,
+        }
+    }
+
+    fn field_type(&self, ty: Type, field: &XID, span: Span) -> TypeResult<Option<Type>> {
+        match self.struct_decl(&ty) {
+            Some(decl) => match decl.fields.iter().find(|(name, _)| name == field) {
+                Some((_, field_type)) => Ok(Some(field_type.clone())),
+                None => Err(TypeError::UnknownField(ty, field.clone(), span)),
+            },
+            None if matches!(
+                ty,
+                MirType::Primitive(_) | MirType::Array(_, _) | MirType::Optional(_)
+            ) || self.is_enum(&ty) =>
+            {
+                Err(TypeError::UnknownField(ty, field.clone(), span))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn infer_struct(
+        &mut self,
+        path: &AbsolutePath,
+        fields: &mut [(XID, MirExpression<AbsoluteVar>)],
+        span: Span,
+    ) -> TypeResult<Option<Type>> {
+        let [name] = path.inner() else {
+            for (_, value) in fields {
+                self.infer(value)?;
+            }
+            return Ok(None);
+        };
+
+        let ty = MirType::UserDefined(path.clone());
+        let decl = self
+            .structs
+            .get(name.inner())
+            .cloned()
+            .ok_or_else(|| TypeError::UnknownStruct(path.clone(), span))?;
+
+        let mut seen: Vec<XID> = vec![];
+        for (field, value) in fields {
+            if seen.contains(field) {
+                return Err(TypeError::DuplicateField(field.clone(), value.span));
+            }
+            seen.push(field.clone());
+
+            let (_, field_type) = decl
+                .fields
+                .iter()
+                .find(|(name, _)| name == field)
+                .ok_or_else(|| TypeError::UnknownField(ty.clone(), field.clone(), value.span))?;
+            self.expect(field_type, value)?;
+        }
+
+        let missing: Vec<XID> = decl
+            .fields
+            .iter()
+            .filter(|(name, _)| !seen.contains(name))
+            .map(|(name, _)| name.clone())
+            .collect();
+        if !missing.is_empty() {
+            return Err(TypeError::MissingFields(path.clone(), missing, span));
+        }
+
+        Ok(Some(ty))
+    }
+
+    /// The enum declaration `path` refers to, if it can be found.
+    fn enum_decl(&self, path: &AbsolutePath, span: Span) -> TypeResult<MirEnum<AbsoluteVar>> {
+        match path.inner() {
+            [name] => self.enums.get(name.inner()).cloned(),
+            _ => None,
+        }
+        .ok_or_else(|| TypeError::UnknownEnum(path.clone(), span))
+    }
+
+    /// The index of `variant` in `decl`, which is also its discriminant.
+    fn variant_index(
+        decl: &MirEnum<AbsoluteVar>,
+        path: &AbsolutePath,
+        variant: &XID,
+        span: Span,
+    ) -> TypeResult<usize> {
+        decl.variants
+            .iter()
+            .position(|(name, _)| name == variant)
+            .ok_or_else(|| TypeError::UnknownVariant(path.clone(), variant.clone(), span))
+    }
+
+    fn infer_variant(
+        &mut self,
+        path: &AbsolutePath,
+        variant: &XID,
+        args: &mut [MirExpression<AbsoluteVar>],
+        span: Span,
+    ) -> TypeResult<Option<Type>> {
+        let decl = self.enum_decl(path, span)?;
+        let (_, payload) = &decl.variants[Self::variant_index(&decl, path, variant, span)?];
+        if payload.len() != args.len() {
+            return Err(TypeError::VariantPayload(
+                variant.clone(),
+                payload.len(),
+                args.len(),
+                span,
+            ));
+        }
+
+        for (ty, arg) in payload.iter().zip(args) {
+            self.expect(ty, arg)?;
+        }
+
+        Ok(Some(MirType::UserDefined(path.clone())))
+    }
+
+    /// Checks every arm of `match_expr` and that together they cover every variant. If
+    /// `is_value`, the arms must be expressions of the same type, which is returned.
+    fn check_match(
+        &mut self,
+        match_expr: &mut MirMatch<AbsoluteVar>,
+        is_value: bool,
+    ) -> TypeResult<Option<Type>> {
+        let scrutinee = &mut match_expr.scrutinee;
+        let mut matched = self.infer(scrutinee)?;
+        if let Some(ty) = &matched {
+            if matches!(
+                ty,
+                MirType::Primitive(_) | MirType::Array(_, _) | MirType::Optional(_)
+            ) || self.struct_decl(ty).is_some()
+            {
+                return Err(TypeError::InvalidMatch(ty.clone(), scrutinee.span));
+            }
+        }
+
+        let mut decl = None;
+        let mut covered = vec![];
+        let mut has_wildcard = false;
+        let mut value_type = None;
+        for arm in &mut match_expr.arms {
+            self.scopes.push(HashMap::new());
+            let result = self.check_arm(arm, &mut matched, &mut decl, is_value, &value_type);
+            self.scopes.pop();
+
+            let (index, ty) = result?;
+            if has_wildcard || index.is_some_and(|index| covered.contains(&index)) {
+                self.errors.push(TypeError::UnreachableArm(arm.span));
+            }
+            match index {
+                Some(index) => covered.push(index),
+                None => has_wildcard = true,
+            }
+            value_type = value_type.or(ty);
+        }
+
+        if let (Some(decl), false) = (decl, has_wildcard) {
+            let missing: Vec<XID> = decl
+                .variants
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| !covered.contains(index))
+                .map(|(_, (name, _))| name.clone())
+                .collect();
+            if !missing.is_empty() {
+                return Err(TypeError::NonExhaustive(missing, match_expr.scrutinee.span));
+            }
+        }
+
+        Ok(value_type)
+    }
+
+    /// Checks one arm, its bindings must already have a scope to go in. Returns the index of the
+    /// variant it matches, `None` for `_`, and the type of its value.
+    fn check_arm(
+        &mut self,
+        arm: &mut MirArm<AbsoluteVar>,
+        matched: &mut Option<Type>,
+        decl: &mut Option<MirEnum<AbsoluteVar>>,
+        is_value: bool,
+        value_type: &Option<Type>,
+    ) -> TypeResult<(Option<usize>, Option<Type>)> {
+        let index = match &arm.pattern {
+            MirPattern::Wildcard => None,
+            MirPattern::Variant {
+                enum_path,
+                variant,
+                bindings,
+            } => {
+                let pattern_decl = self.enum_decl(enum_path, arm.span)?;
+                let pattern_type = MirType::UserDefined(enum_path.clone());
+                match matched {
+                    Some(ty) if *ty != pattern_type => {
+                        return Err(TypeError::Mismatch(ty.clone(), pattern_type, arm.span));
+                    }
+                    _ => *matched = Some(pattern_type),
+                }
+
+                let index = Self::variant_index(&pattern_decl, enum_path, variant, arm.span)?;
+                let (_, payload) = &pattern_decl.variants[index];
+                if payload.len() != bindings.len() {
+                    return Err(TypeError::VariantPayload(
+                        variant.clone(),
+                        payload.len(),
+                        bindings.len(),
+                        arm.span,
+                    ));
+                }
+
+                let scope = self.scopes.last_mut().expect("Arms have their own scope");
+                for (binding, ty) in bindings.iter().zip(payload) {
+                    if let Some(binding) = binding {
+                        scope.insert(binding.inner().to_string(), ty.clone());
+                    }
+                }
+                *decl = Some(pattern_decl);
+                Some(index)
+            }
+        };
+
+        let ty = match &mut arm.body {
+            MirArmBody::Block(_) if is_value => return Err(TypeError::ArmWithoutValue(arm.span)),
+            MirArmBody::Block(block) => {
+                self.check_block(block);
+                None
+            }
+            MirArmBody::Expression(expr) => match value_type {
+                Some(ty) if is_value => {
+                    self.expect(ty, expr)?;
+                    None
+                }
+                _ => self.infer(expr)?,
+            },
+        };
+
+        Ok((index, ty))
+    }
+
+    /// Both sides must have the same type, after converting a literal or the narrower side
+    /// implicitly.
+    fn infer_binary_op(
+        &mut self,
+        left: &mut MirExpression<AbsoluteVar>,
+        op: MirBinaryOp,
+        right: &mut MirExpression<AbsoluteVar>,
+    ) -> TypeResult<Option<Type>> {
+        if op == MirBinaryOp::Coalesce {
+            return self.infer_coalesce(left, right);
+        }
+
+        let left_type = self.infer(left)?;
+        let right_type = self.infer(right)?;
+
+        let (valid, result): (fn(&Type) -> bool, _) = match op {
+            MirBinaryOp::Coalesce => unreachable!("`??` is checked separately"),
+            // Adding strings concatenates them.
+            MirBinaryOp::Add => (|ty: &Type| is_numeric(ty) || is_string(ty), None),
+            MirBinaryOp::Subtract
+            | MirBinaryOp::Multiply
+            | MirBinaryOp::Divide
+            | MirBinaryOp::Modulo => (is_numeric, None),
+            MirBinaryOp::LessThan
+            | MirBinaryOp::LessThanEquals
+            | MirBinaryOp::GreaterThan
+            | MirBinaryOp::GreaterThanEquals => (is_numeric, Some(MirPrimitive::Bool)),
+            // Structs are compared field by field by hand for now.
+            MirBinaryOp::Equals | MirBinaryOp::NotEquals => (
+                |ty: &Type| matches!(ty, MirType::Primitive(ty) if *ty != MirPrimitive::Void),
+                Some(MirPrimitive::Bool),
+            ),
+            MirBinaryOp::And | MirBinaryOp::Or => (is_bool, Some(MirPrimitive::Bool)),
+        };
+
+        self.check_operand(&op.to_string(), &left_type, valid, left.span)?;
+        self.check_operand(&op.to_string(), &right_type, valid, right.span)?;
+        let operand_type = match (left_type, right_type) {
+            (Some(left_type), Some(right_type)) => {
+                match unify(left, left_type.clone(), right, right_type.clone()) {
+                    Some(ty) => Some(ty),
+                    None => {
+                        return Err(TypeError::OperandMismatch(
+                            left_type, left.span, right_type, right.span,
+                        ))
+                    }
+                }
+            }
+            (left_type, right_type) => left_type.or(right_type),
+        };
+
+        Ok(result.map(primitive).or(operand_type))
+    }
+
+    /// The left side of `??` is optional, the right side is used where it is unset. The result
+    /// is only optional if the right side is.
+    fn infer_coalesce(
+        &mut self,
+        left: &mut MirExpression<AbsoluteVar>,
+        right: &mut MirExpression<AbsoluteVar>,
+    ) -> TypeResult<Option<Type>> {
+        let inner = match self.infer(left)? {
+            Some(MirType::Optional(inner)) => *inner,
+            Some(ty) => return Err(TypeError::InvalidOperand("??".to_string(), ty, left.span)),
+            None => {
+                // The fallback is still checked on its own.
+                if !is_null(right) {
+                    self.infer(right)?;
+                }
+                return Ok(None);
+            }
+        };
+
+        if is_null(right) {
+            return Ok(Some(optional(inner)));
+        }
+        match self.infer(right)? {
+            Some(MirType::Optional(found)) => {
+                let expected = optional(inner);
+                self.convert(&expected, right, optional(*found))?;
+                Ok(Some(expected))
+            }
+            Some(found) => {
+                self.convert(&inner, right, found)?;
+                Ok(Some(inner))
+            }
+            None => Ok(Some(inner)),
+        }
+    }
+
+    /// Both branches must have the same type, a `null` branch makes the other one optional.
+    fn infer_ternary(
+        &mut self,
+        condition: &mut MirExpression<AbsoluteVar>,
+        then: &mut MirExpression<AbsoluteVar>,
+        otherwise: &mut MirExpression<AbsoluteVar>,
+    ) -> TypeResult<Option<Type>> {
+        self.check_condition(condition)?;
+        match (is_null(then), is_null(otherwise)) {
+            (true, true) => Err(TypeError::UntypedNull(then.span)),
+            (true, false) => Ok(self.infer(otherwise)?.map(optional)),
+            (false, true) => Ok(self.infer(then)?.map(optional)),
+            (false, false) => match (self.infer(then)?, self.infer(otherwise)?) {
+                (Some(then_type), Some(otherwise_type)) => {
+                    match unify(then, then_type.clone(), otherwise, otherwise_type.clone()) {
+                        Some(ty) => Ok(Some(ty)),
+                        None => Err(TypeError::Mismatch(
+                            then_type,
+                            otherwise_type,
+                            otherwise.span,
+                        )),
+                    }
+                }
+                (then_type, otherwise_type) => Ok(then_type.or(otherwise_type)),
+            },
+        }
+    }
+
+    fn check_operand(
+        &self,
+        op: &str,
+        ty: &Option<Type>,
+        valid: fn(&Type) -> bool,
+        span: Span,
+    ) -> TypeResult<()> {
+        match ty {
+            Some(ty) if !valid(ty) => {
+                Err(TypeError::InvalidOperand(op.to_string(), ty.clone(), span))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_types, TypeError};
+    use crate::{
+        hir::Statement,
+        mir::{AbsoluteScope, ToMir},
+        parse_str, TokenIter,
+    };
+
+    fn check_all(input: &str) -> eyre::Result<Vec<TypeError>> {
+        let tokens = parse_str(input)?;
+        let statements: Vec<Statement> = TokenIter::from(&tokens).parse()?;
+        let mut mir = AbsoluteScope::root_to_absolute(statements.into_mir());
+        Ok(check_types(&mut mir))
+    }
+
+    fn check(input: &str) -> eyre::Result<Result<(), TypeError>> {
+        Ok(match check_all(input)?.into_iter().next() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        })
+    }
+
+    #[test]
+    fn well_typed_programs() -> eyre::Result<()> {
+        check(
+            "static total: int = count(2) + offset;
+            static offset: int = 1;
+            static function count(n: int): int {
+                let result: int = 0;
+                for (let i: int = 0; i < n; i = i + 1;) {
+                    if (i % 2 == 0 && result >= 0) { result = result + i; }
+                }
+                return result;
+            }
+            static function tick(): void {
+                let small: byte = 1b;
+                let ratio: double = 0.5d * 2.0d;
+                while (!(total == 0)) { let done: bool = small == 1b || false; count(total); }
+                unsafe { eval(\"say unchecked\"); }
+            }",
+        )??;
+        Ok(())
+    }
+
+    #[test]
+    fn mismatched_types() -> eyre::Result<()> {
+        let input = "{ let scoped: float = \"hello\"; }";
+        let Err(TypeError::Mismatch(expected, found, span)) = check(input)? else {
+            panic!("expected a type mismatch");
+        };
+        assert_eq!(
+            (expected.to_string(), found.to_string()),
+            ("float".into(), "string".into())
+        );
+        assert_eq!(&input[span.index..span.index + span.width], "\"hello\"");
+
+        let input = "static function f(): int { return true; }";
+        assert!(matches!(check(input)?, Err(TypeError::Mismatch(_, _, _))));
+
+        let input = "static x: int = 1; static function f(): void { x = 1 + 2l; }";
+        let Err(TypeError::LossyConversion(found, expected, span)) = check(input)? else {
+            panic!("expected a lossy conversion");
+        };
+        assert_eq!(
+            (found.to_string(), expected.to_string()),
+            ("long".into(), "int".into())
+        );
+        assert_eq!(&input[span.index..span.index + span.width], "1 + 2l");
+
+        let input = "static function f(a: int, b: float): int { return a + b; }";
+        let Err(TypeError::OperandMismatch(_, left, _, right)) = check(input)? else {
+            panic!("expected mismatched operands");
+        };
+        assert_eq!(&input[left.index..left.index + left.width], "a");
+        assert_eq!(&input[right.index..right.index + right.width], "b");
+        Ok(())
+    }
+
+    #[test]
+    fn compound_assignments_are_checked() -> eyre::Result<()> {
+        check(
+            "let x: int = 1; x += 2; x %= x;
+            let b: byte = 1; b -= 1;
+            let s: string = \"a\"; s += \"b\";
+            let values: double[2] = [1.0d, 2.0d]; values[0] *= 2.0d;",
+        )??;
+
+        let input = "let x: int = 1; x += 2l;";
+        let Err(TypeError::LossyConversion(_, _, span)) = check(input)? else {
+            panic!("expected a lossy conversion");
+        };
+        assert_eq!(&input[span.index..span.index + span.width], "x += 2l");
+        let result = check("let s: string = \"a\"; s -= \"b\";")?;
+        assert!(matches!(result, Err(TypeError::InvalidOperand(_, _, _))));
+        let result = check("let x: int = 1; 1 += x;")?;
+        assert!(matches!(result, Err(TypeError::InvalidAssignment(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn optionals_hold_null() -> eyre::Result<()> {
+        check(
+            "let x: int? = null; x = 1; let y: int = x ?? 2;
+            let z: int? = x ?? null; let b: bool = true;
+            let w: int? = b ? 1 : null; let v: long = b ? 1 : 2l;",
+        )??;
+
+        let result = check("let x: int = null;")?;
+        assert!(matches!(result, Err(TypeError::NonOptionalNull(_, _))));
+        let result = check("let x: int? = null; let y: int = x;")?;
+        assert!(matches!(result, Err(TypeError::Mismatch(_, _, _))));
+        let result = check("let x: int = 1; let y: int = x ?? 2;")?;
+        assert!(matches!(result, Err(TypeError::InvalidOperand(_, _, _))));
+        let result = check("let b: bool = true; let x: int? = b ? null : null;")?;
+        assert!(matches!(result, Err(TypeError::UntypedNull(_))));
+        let result = check("let b: bool = true; let x: int = b ? 1 : \"a\";")?;
+        assert!(matches!(result, Err(TypeError::Mismatch(_, _, _))));
+        Ok(())
+    }
+
+    #[test]
+    fn calls_are_checked_against_signatures() -> eyre::Result<()> {
+        let functions = "static function f(a: int, b: short): int { return a; }
+            static function g(): void {}";
+
+        check(&format!("{} let x: int = f(1, 2);", functions))??;
+        let result = check(&format!("{} let x: int = f(1, 70000);", functions))?;
+        assert!(matches!(result, Err(TypeError::LossyConversion(_, _, _))));
+        let result = check(&format!("{} let x: int = f(1, true);", functions))?;
+        assert!(matches!(result, Err(TypeError::Mismatch(_, _, _))));
+        let result = check(&format!("{} let x: int = f(1);", functions))?;
+        assert!(matches!(result, Err(TypeError::ArgumentCount(_, 2, 1, _))));
+        let result = check(&format!("{} let x: int = g();", functions))?;
+        assert!(matches!(result, Err(TypeError::Mismatch(_, _, _))));
+        let result = check(&format!("{} let x: int = h();", functions))?;
+        assert!(matches!(result, Err(TypeError::UnknownFunction(_, _))));
+        Ok(())
+    }
+
+    #[test]
+    fn booleans_are_not_numbers() -> eyre::Result<()> {
+        check("let b: bool = !(1 < 2) || true == false; if (b && true) {} while (false) {}")??;
+
+        let result = check("let x: int = 1; if (x) {}")?;
+        assert!(matches!(result, Err(TypeError::InvalidCondition(_, _))));
+        let result = check("let b: bool = 1;")?;
+        assert!(matches!(result, Err(TypeError::Mismatch(_, _, _))));
+        let result = check("let x: int = 1 + true;")?;
+        assert!(matches!(result, Err(TypeError::InvalidOperand(_, _, _))));
+        let result = check("let b: bool = !1;")?;
+        assert!(matches!(result, Err(TypeError::InvalidOperand(_, _, _))));
+        let result = check("let b: bool = true && 1;")?;
+        assert!(matches!(result, Err(TypeError::InvalidOperand(_, _, _))));
+        Ok(())
+    }
+
+    #[test]
+    fn structs_are_checked() -> eyre::Result<()> {
+        let point = "struct Point { x: int, y: int } struct Line { from: Point, to: Point }";
+        check(&format!(
+            "{} let line: Line = Line {{ to: Point {{ x: 1, y: 2 }}, from: Point {{ y: 0, x: 0 }} }};
+            line.to.x = line.from.y + 1; line.from = line.to;",
+            point
+        ))??;
+
+        let result = check(&format!("{} let p: Point = Point {{ x: 1, z: 2 }};", point))?;
+        assert!(
+            matches!(result, Err(TypeError::UnknownField(_, field, _)) if field.inner() == "z")
+        );
+        let result = check(&format!("{} let p: Point = Point {{ x: 1 }};", point))?;
+        assert!(matches!(result, Err(TypeError::MissingFields(_, fields, _)) if fields.len() == 1));
+        let result = check(&format!(
+            "{} let p: Point = Point {{ x: 1, x: 1, y: 2 }};",
+            point
+        ))?;
+        assert!(matches!(result, Err(TypeError::DuplicateField(_, _))));
+        let result = check(&format!("{} let p: Line = Point {{ x: 1, y: 2 }};", point))?;
+        assert!(matches!(result, Err(TypeError::Mismatch(_, _, _))));
+        let result = check("let p: int = Point { x: 1 };")?;
+        assert!(matches!(result, Err(TypeError::UnknownStruct(_, _))));
+        let result = check("let x: int = 1; let y: int = x.y;")?;
+        assert!(matches!(result, Err(TypeError::UnknownField(_, _, _))));
+        Ok(())
+    }
+
+    #[test]
+    fn only_places_are_assigned() -> eyre::Result<()> {
+        let result = check("static function f(): int { return 1; } f() = 2;")?;
+        assert!(matches!(result, Err(TypeError::InvalidAssignment(_))));
+        let result = check("let x: int = 1; (x + 1) = 2;")?;
+        assert!(matches!(result, Err(TypeError::InvalidAssignment(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn arrays_are_checked() -> eyre::Result<()> {
+        check(
+            "let values: int[3] = [1, 2, 3];
+            let i: int = values[2];
+            values[i] = values[0] + 1;
+            let flags: bool[2] = [true, values[1] == 2];",
+        )??;
+
+        let result = check("let values: int[3] = [1, 2];")?;
+        assert!(matches!(result, Err(TypeError::Mismatch(_, _, _))));
+        let result = check("let values: int[2] = [1, true];")?;
+        assert!(matches!(result, Err(TypeError::Mismatch(_, _, _))));
+        let result = check("let values: int[2] = [1, 2]; let x: int = values[2];")?;
+        assert!(matches!(result, Err(TypeError::IndexOutOfBounds(2, 2, _))));
+        let result = check("let values: int[2] = [1, 2]; let x: int = values[true];")?;
+        assert!(matches!(result, Err(TypeError::Mismatch(_, _, _))));
+        let result = check("let x: int = 1; let y: int = x[0];")?;
+        assert!(matches!(result, Err(TypeError::InvalidIndex(_, _))));
+        Ok(())
+    }
+
+    #[test]
+    fn strings_are_checked() -> eyre::Result<()> {
+        check(
+            "static function greet(name: string): string { return \"Hi \" + name; }
+            let name: string = \"Alex\";
+            let same: bool = greet(name) == \"Hi Alex\";
+            $tellraw(\"@a\", name, same);
+            $command(\"say ${name}\");",
+        )??;
+
+        let result = check("let x: string = \"a\" + 1;")?;
+        assert!(matches!(
+            result,
+            Err(TypeError::OperandMismatch(_, _, _, _))
+        ));
+        let result = check("let x: int = $tellraw(\"@a\", \"hi\");")?;
+        assert!(matches!(result, Err(TypeError::Mismatch(_, _, _))));
+        let result = check("$command(\"say ${missing}\");")?;
+        assert!(matches!(result, Err(TypeError::UnknownVariable(_, _))));
+        Ok(())
+    }
+
+    #[test]
+    fn conversions_are_checked() -> eyre::Result<()> {
+        check(
+            "let x: double = $nbt(\"entity @s Pos[0]\") * 2.0d;
+            let y: float = x as float + 3 as float;
+            let z: int = y as int;
+            let text: string = z as string;
+            let flag: byte = (z > 1) as byte;",
+        )??;
+
+        check(
+            "static function f(a: byte, b: short, c: int): long {
+                let d: double = b;
+                let e: short = a + b;
+                let small: byte = a + 1;
+                let wide: long = 2s;
+                return c * a;
+            }",
+        )??;
+        let result = check("static function f(a: int): byte { let b: byte = a; return b; }")?;
+        assert!(matches!(result, Err(TypeError::LossyConversion(_, _, _))));
+        let result = check("let b: byte = 300;")?;
+        assert!(matches!(result, Err(TypeError::LossyConversion(_, _, _))));
+        let result = check("let s: short = 2l;")?;
+        assert!(matches!(result, Err(TypeError::LossyConversion(_, _, _))));
+
+        let result = check("let x: float = 1.0 + 1;")?;
+        assert!(matches!(
+            result,
+            Err(TypeError::OperandMismatch(_, _, _, _))
+        ));
+        let result = check("let x: int = $nbt(\"entity @s Pos[0]\");")?;
+        assert!(matches!(result, Err(TypeError::LossyConversion(_, _, _))));
+        let result = check("let x: int = \"1\" as int;")?;
+        assert!(matches!(result, Err(TypeError::InvalidConversion(_, _, _))));
+        let result = check("let x: bool = 1 as bool;")?;
+        assert!(matches!(result, Err(TypeError::InvalidConversion(_, _, _))));
+        Ok(())
+    }
+
+    #[test]
+    fn matches_are_checked() -> eyre::Result<()> {
+        let state = "enum State { Idle, Running(int, bool), Done }
+            static state: State = State::Running(1, true);";
+        check(&format!(
+            "{} let ticks: int = match (state) {{
+                State::Running(ticks, _) => ticks,
+                _ => 0,
+            }};
+            match (state) {{
+                State::Idle => {{ state = State::Done; }}
+                State::Running(_, paused) => {{ if (paused) {{ state = State::Idle; }} }}
+                State::Done => {{}}
+            }}",
+            state
+        ))??;
+
+        let result = check(&format!(
+            "{} match (state) {{ State::Idle => {{}} }}",
+            state
+        ))?;
+        assert!(
+            matches!(result, Err(TypeError::NonExhaustive(variants, _)) if variants.len() == 2)
+        );
+        let result = check(&format!("{} state = State::Running(1);", state))?;
+        assert!(matches!(result, Err(TypeError::VariantPayload(_, 2, 1, _))));
+        let result = check(&format!("{} state = State::Paused;", state))?;
+        assert!(matches!(result, Err(TypeError::UnknownVariant(_, _, _))));
+        let result = check(&format!(
+            "{} let x: int = match (state) {{ State::Running(x, _) => x, _ => true }};",
+            state
+        ))?;
+        assert!(matches!(result, Err(TypeError::Mismatch(_, _, _))));
+        let result = check(&format!(
+            "{} let x: int = match (state) {{ State::Idle => {{}} _ => 1 }};",
+            state
+        ))?;
+        assert!(matches!(result, Err(TypeError::ArmWithoutValue(_))));
+        let result = check("let x: int = 1; match (x) { _ => {} }")?;
+        assert!(matches!(result, Err(TypeError::InvalidMatch(_, _))));
+
+        let errors = check_all(&format!(
+            "{} match (state) {{ _ => {{}} State::Done => {{}} }}",
+            state
+        ))?;
+        assert!(matches!(errors.as_slice(), [TypeError::UnreachableArm(_)]));
+        Ok(())
+    }
+
+    #[test]
+    fn conditions_and_operands() -> eyre::Result<()> {
+        let result = check("if (\"yes\") {}")?;
+        assert!(matches!(result, Err(TypeError::InvalidCondition(_, _))));
+        let result = check("while (1.0) {}")?;
+        assert!(matches!(result, Err(TypeError::InvalidCondition(_, _))));
+        let result = check("let x: string = \"a\" - \"b\";")?;
+        assert!(matches!(result, Err(TypeError::InvalidOperand(_, _, _))));
+        let result = check("let x: int = y;")?;
+        assert!(matches!(result, Err(TypeError::UnknownVariable(_, _))));
+        let result = check("{ let y: int = 1; } let x: int = y;")?;
+        assert!(matches!(result, Err(TypeError::UnknownVariable(_, _))));
+        Ok(())
+    }
+
+    #[test]
+    fn checking_continues_after_errors() -> eyre::Result<()> {
+        let errors = check_all(
+            "static function f(): void {
+                let a: int = \"a\";
+                let b: string = a;
+                if (b) { b = 1; }
+            }",
+        )?;
+        assert!(matches!(
+            errors.as_slice(),
+            [
+                TypeError::Mismatch(_, _, _),
+                TypeError::Mismatch(_, _, _),
+                TypeError::InvalidCondition(_, _),
+                TypeError::Mismatch(_, _, _),
+            ]
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,187 @@
+use crate::{
+    diagnostic::Diagnostics,
+    token::prelude::{lex, Lexed},
+    Span, TokenStream,
+};
+use std::{
+    fmt,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+/// Identifies a file in a [`SourceMap`].
+///
+/// Sources lexed without a source map, like by [`parse_str`](crate::parse_str), use the default
+/// id.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FileId(usize);
+
+/// A 1-based line and column, columns count characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+pub struct SourceFile {
+    path: PathBuf,
+    source: String,
+    /// Character index of the first character of every line.
+    line_starts: Vec<usize>,
+}
+
+impl SourceFile {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn location(&self, index: usize) -> Location {
+        let line = self.line_starts.partition_point(|start| *start <= index);
+        Location {
+            line,
+            column: index - self.line_starts[line - 1] + 1,
+        }
+    }
+
+    /// The text of a 1-based line, without its line ending.
+    pub fn line(&self, line: usize) -> &str {
+        self.source
+            .split('\n')
+            .nth(line - 1)
+            .unwrap_or_default()
+            .trim_end_matches('\r')
+    }
+}
+
+/// Every file of the program being compiled, spans refer to them by their [`FileId`].
+#[derive(Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the file at `path` and splits it into tokens, reporting every lexer error.
+    pub fn load(
+        &mut self,
+        path: impl Into<PathBuf>,
+        source: String,
+        diagnostics: &mut Diagnostics,
+    ) -> (FileId, TokenStream) {
+        let file = FileId(self.files.len());
+        let Lexed {
+            tokens,
+            errors,
+            line_starts,
+        } = lex(file, source.chars());
+        diagnostics.extend(errors);
+
+        self.files.push(SourceFile {
+            path: path.into(),
+            source,
+            line_starts,
+        });
+        (file, tokens)
+    }
+
+    /// Adds a file which is not part of the program, like the project configuration, so
+    /// diagnostics can point into it.
+    pub fn add(&mut self, path: impl Into<PathBuf>, source: String) -> FileId {
+        let file = FileId(self.files.len());
+        let line_starts = [0]
+            .into_iter()
+            .chain(
+                source
+                    .chars()
+                    .enumerate()
+                    .filter_map(|(index, char)| (char == '\n').then_some(index + 1)),
+            )
+            .collect();
+
+        self.files.push(SourceFile {
+            path: path.into(),
+            source,
+            line_starts,
+        });
+        file
+    }
+
+    /// The span of the bytes in `range` of `file`, like the ranges the TOML parser reports.
+    pub fn span(&self, file: FileId, range: Range<usize>) -> Span {
+        let source = &self.files[file.0].source;
+        let index = source[..range.start].chars().count();
+        Span::new(file, index, source[range].chars().count())
+    }
+
+    pub fn file(&self, file: FileId) -> Option<&SourceFile> {
+        self.files.get(file.0)
+    }
+
+    /// The file and location where `span` starts.
+    pub fn location(&self, span: Span) -> Option<(&Path, Location)> {
+        self.file(span.file)
+            .map(|file| (file.path(), file.location(span.index)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Location, SourceMap};
+    use crate::{diagnostic::Diagnostics, Spanned};
+    use std::path::Path;
+
+    #[test]
+    fn spans_resolve_to_lines_and_columns() {
+        let mut source_map = SourceMap::new();
+        let mut diagnostics = Diagnostics::new();
+        source_map.load("first.ss", "let a: int = 1;".to_string(), &mut diagnostics);
+        let (file, tokens) = source_map.load(
+            "second.ss",
+            "static x: int = 0;\r\n\n  x = 1;\n".to_string(),
+            &mut diagnostics,
+        );
+        assert!(diagnostics.is_empty());
+
+        let assigned = tokens.0[7].span();
+        assert_eq!(assigned.file, file);
+        assert_eq!(
+            source_map.location(assigned),
+            Some((Path::new("second.ss"), Location { line: 3, column: 3 }))
+        );
+        assert_eq!(source_map.file(file).unwrap().line(1), "static x: int = 0;");
+    }
+
+    #[test]
+    fn byte_ranges_become_spans() {
+        let mut source_map = SourceMap::new();
+        let source = "name = \"pâté\"\nfloat_scale = 7\n";
+        let file = source_map.add("stonescript.toml", source.to_string());
+
+        let start = source.find('7').unwrap();
+        let span = source_map.span(file, start..start + 1);
+        assert_eq!((span.index, span.width), (start - 2, 1));
+        assert_eq!(
+            source_map.location(span),
+            Some((
+                Path::new("stonescript.toml"),
+                Location {
+                    line: 2,
+                    column: 15
+                }
+            ))
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use super::{cursor::Cursor, ParseError, ParseResult, ParseToken, ToTokenTree, TokenTree};
+use crate::{Span, Spanned};
+use std::{fmt, iter::FusedIterator};
+
+/// A `///` comment, kept as a token so it can be attached to the declaration after it.
+///
+/// Other comments (`//`, `#` and `/* */`) are skipped while lexing.
+#[derive(Clone, PartialEq)]
+pub struct DocComment {
+    span: Span,
+    text: String,
+}
+
+impl DocComment {
+    /// The text after the `///`.
+    pub fn inner(&self) -> &str {
+        &self.text
+    }
+
+    pub fn into_inner(self) -> String {
+        self.text
+    }
+}
+
+impl Spanned for DocComment {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl ToTokenTree for DocComment {
+    fn to_token_tree(self) -> TokenTree {
+        TokenTree::DocComment(self)
+    }
+}
+
+fn skip_line<T: FusedIterator<Item = char>>(cursor: &mut Cursor<T>) -> String {
+    let mut text = String::new();
+    while let Some(next_char) = cursor.peek() {
+        if next_char == '\n' || next_char == '\r' {
+            break;
+        }
+        text.push(next_char);
+        cursor.consume();
+    }
+    text
+}
+
+impl<T: FusedIterator<Item = char>> ParseToken<T> for DocComment {
+    fn parse(start: char, mut cursor: Cursor<T>) -> ParseResult<Self> {
+        match (start, cursor.peek()) {
+            ('#', _) => {
+                skip_line(&mut cursor);
+                Err(ParseError::NotAToken)
+            }
+            ('/', Some('/')) => {
+                cursor.consume();
+                // `////` and longer are plain comments, like in Rust.
+                let is_doc = cursor.peek() == Some('/') && cursor.peek_ahead(1) != Some('/');
+                if is_doc {
+                    cursor.consume();
+                }
+
+                let text = skip_line(&mut cursor);
+                if is_doc {
+                    Ok(DocComment {
+                        span: cursor.into_span(),
+                        text,
+                    })
+                } else {
+                    Err(ParseError::NotAToken)
+                }
+            }
+            ('/', Some('*')) => {
+                cursor.consume();
+                let mut depth = 1;
+                while depth > 0 {
+                    let Some(next_char) = cursor.consume() else {
+                        return Err(ParseError::UnclosedComment(cursor.start_span(2)));
+                    };
+
+                    match (next_char, cursor.peek()) {
+                        ('/', Some('*')) => {
+                            cursor.consume();
+                            depth += 1;
+                        }
+                        ('*', Some('/')) => {
+                            cursor.consume();
+                            depth -= 1;
+                        }
+                        _ => (),
+                    }
+                }
+
+                Err(ParseError::NotAToken)
+            }
+            _ => Err(ParseError::InvalidStart(start, "comment")),
+        }
+    }
+}
+
+impl fmt::Debug for DocComment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "///{}", self.text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{hir::Statement, parse_str, ParseError, Spanned, TokenIter, TokenTree};
+
+    #[test]
+    fn comments_are_skipped() -> eyre::Result<()> {
+        let tokens = parse_str(
+            "# a comment
+            a // another one
+            /* a /* nested */ block
+            comment */ b #c
+            //// not a doc comment",
+        )?;
+
+        assert!(matches!(
+            tokens.0.as_slice(),
+            [TokenTree::Ident(a), TokenTree::Ident(b)] if a.inner() == "a" && b.inner() == "b"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn doc_comments_are_tokens() -> eyre::Result<()> {
+        let tokens = parse_str("/// Adds one.\r\nstatic function f(): void {}")?;
+
+        let [TokenTree::DocComment(doc), ..] = tokens.0.as_slice() else {
+            panic!("expected a doc comment, got {:?}", tokens);
+        };
+        assert_eq!(doc.inner(), " Adds one.");
+        assert_eq!(doc.span().width, 13);
+
+        // The parser looks past doc comments.
+        let statements: Vec<Statement> = TokenIter::from(&tokens).parse()?;
+        assert!(matches!(statements.as_slice(), [Statement::Function(_)]));
+        Ok(())
+    }
+
+    #[test]
+    fn unclosed_block_comment() {
+        let err = parse_str("a /* /* */").unwrap_err();
+        assert!(matches!(err, ParseError::UnclosedComment(span) if span.index == 2));
+    }
+}
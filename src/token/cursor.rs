@@ -1,14 +1,21 @@
 use super::{
-    group::Group, ident::Ident, literal::Literal, punct::Punct, ParseError, ParseResult,
-    ParseToken, TokenTree,
+    comment::DocComment, group::Group, ident::Ident, literal::Literal, punct::Punct, ParseError,
+    ParseResult, ParseToken, TokenTree,
 };
-use crate::Span;
+use crate::{FileId, Span};
 use std::{collections::VecDeque, iter::FusedIterator};
 
+type TokenParser<T> = fn(char, Cursor<T>) -> ParseResult<TokenTree>;
+
 struct CursorInner<'i, T: FusedIterator<Item = char> + 'i> {
     iterator: &'i mut T,
+    file: FileId,
     position: usize,
     buffer: VecDeque<char>,
+    /// Position of the first character of every line consumed so far.
+    line_starts: Vec<usize>,
+    /// Errors of tokens nested in groups, the group itself is still lexed.
+    errors: Vec<ParseError>,
 }
 
 pub struct Cursor<'a, T: FusedIterator<Item = char> + 'a> {
@@ -27,33 +34,22 @@ impl<'a, T: FusedIterator<Item = char> + 'a> Cursor<'a, T> {
         unsafe { &mut *self.inner }
     }
 
-    fn skip_comment(start: char, mut cursor: Cursor<T>) -> ParseResult<TokenTree> {
-        if start == '/' && cursor.peek() == Some('/') {
-            while let Some(char) = cursor.consume() {
-                if char == '\n' || char == '\r' {
-                    break;
-                }
-            }
-
-            Err(ParseError::NotAToken)
-        } else {
-            Err(ParseError::InvalidStart(start, "comment"))
-        }
-    }
-
-    const PARSERS: &'a [fn(char, Cursor<T>) -> ParseResult<TokenTree>] = &[
-        Self::skip_comment,
+    const PARSERS: &'a [TokenParser<T>] = &[
+        DocComment::parse_to_token_tree,
         Group::parse_to_token_tree,
         Literal::parse_to_token_tree,
         Punct::parse_to_token_tree,
         Ident::parse_to_token_tree,
     ];
 
-    pub fn run<R>(iterator: &'a mut T, mut closure: impl FnMut(Cursor<T>) -> R) -> R {
+    pub fn run<R>(file: FileId, iterator: &'a mut T, mut closure: impl FnMut(Cursor<T>) -> R) -> R {
         let mut inner = Box::new(CursorInner {
             iterator,
+            file,
             position: 0,
             buffer: VecDeque::new(),
+            line_starts: vec![0],
+            errors: vec![],
         });
 
         closure(Cursor {
@@ -75,7 +71,8 @@ impl<'a, T: FusedIterator<Item = char> + 'a> Cursor<'a, T> {
     }
 
     pub(super) fn expect_peek_ahead(&mut self, index: usize) -> ParseResult<char> {
-        self.peek_ahead(index).ok_or(ParseError::EarlyEof)
+        let end = self.end_of_input();
+        self.peek_ahead(index).ok_or(ParseError::EarlyEof(end))
     }
 
     pub(super) fn peek(&mut self) -> Option<char> {
@@ -89,22 +86,57 @@ impl<'a, T: FusedIterator<Item = char> + 'a> Cursor<'a, T> {
     pub(super) fn consume(&mut self) -> Option<char> {
         let inner = self.inner_mut();
         inner.position += 1;
-        if inner.buffer.is_empty() {
+        let char = if inner.buffer.is_empty() {
             inner.iterator.next()
         } else {
             inner.buffer.pop_front()
+        };
+
+        if char == Some('\n') {
+            inner.line_starts.push(inner.position);
         }
+        char
+    }
+
+    /// Where the lines consumed so far start.
+    pub(super) fn line_starts(&self) -> &[usize] {
+        &self.inner().line_starts
+    }
+
+    pub(super) fn report(&mut self, err: ParseError) {
+        self.inner_mut().errors.push(err)
+    }
+
+    pub(super) fn take_errors(&mut self) -> Vec<ParseError> {
+        std::mem::take(&mut self.inner_mut().errors)
     }
 
     pub(super) fn expect_consume(&mut self) -> ParseResult<char> {
-        self.consume().ok_or(ParseError::EarlyEof)
+        let end = self.end_of_input();
+        self.consume().ok_or(ParseError::EarlyEof(end))
+    }
+
+    /// The span of the first `width` characters of the current token.
+    pub(super) fn start_span(&self, width: usize) -> Span {
+        Span::new(self.inner().file, self.start_pos, width)
+    }
+
+    /// The span of `width` characters starting at `index`.
+    pub(super) fn span_at(&self, index: usize, width: usize) -> Span {
+        Span::new(self.inner().file, index, width)
+    }
+
+    /// The index of the next character.
+    pub(super) fn position(&self) -> usize {
+        self.inner().position
+    }
+
+    fn end_of_input(&self) -> Span {
+        Span::new(self.inner().file, self.inner().position, 0)
     }
 
     pub(super) fn into_span(self) -> Span {
-        Span {
-            index: self.start_pos,
-            width: self.inner().position - self.start_pos,
-        }
+        self.start_span(self.inner().position - self.start_pos)
     }
 
     pub(super) fn apply_parsers(&mut self) -> ParseResult<Option<TokenTree>> {
@@ -128,7 +160,7 @@ impl<'a, T: FusedIterator<Item = char> + 'a> Cursor<'a, T> {
         Err(ParseError::UnexpectedToken(
             start.to_string(),
             "token tree",
-            Span::new(start_pos, 1),
+            Span::new(self.inner().file, start_pos, 1),
         ))
     }
 }
@@ -64,29 +64,20 @@ macro_rules! define_delimiter {
                     }
                 }
 
-                impl<T: Parse> TryFrom<Group> for $variant<T> {
-                    type Error = SyntaxError;
-
-                    fn try_from(value: Group) -> SyntaxResult<Self> {
-                        if let Group {
-                            span,
-                            tokens,
-                            delimiter: Delimiter::$variant,
-                        } = value {
-                            Ok(Self {
-                                span,
-                                contents: TokenIter::from(&tokens).parse()?,
-                            })
-                        } else {
-                            Err(SyntaxError::UnexpectedToken(value.to_token_tree(), stringify!($variant)))
-                        }
-                    }
-                }
-
                 impl<T: Parse> Parse for $variant<T> {
                     fn parse(token_iter: &mut TokenIter) -> SyntaxResult<Self> {
-                        let group: Group = token_iter.parse()?;
-                        group.try_into()
+                        let group = match token_iter.expect_consume()? {
+                            TokenTree::Group(group) if group.delimiter == Delimiter::$variant => group,
+                            token => return Err(SyntaxError::UnexpectedToken(token, concat!("`", $open, "`"))),
+                        };
+
+                        // Running out of tokens inside the group is reported at its closing delimiter.
+                        let span = group.span;
+                        let close = Span::new(span.file, span.index + span.width - 1, 1);
+                        Ok(Self {
+                            span,
+                            contents: token_iter.parse_group(&group.tokens, close)?,
+                        })
                     }
                 }
 
@@ -157,7 +148,14 @@ impl<T: FusedIterator<Item = char>> ParseToken<T> for Group {
         let mut tokens = vec![];
 
         loop {
-            let next_char = cursor.expect_peek()?;
+            let next_char = match cursor.expect_peek() {
+                Ok(next_char) => next_char,
+                Err(ParseError::EarlyEof(end)) => {
+                    let open = cursor.start_span(1);
+                    return Err(ParseError::UnclosedDelimiter(start, open, end));
+                }
+                Err(other_err) => return Err(other_err),
+            };
             if next_char == closing_char {
                 break;
             }
@@ -167,8 +165,14 @@ impl<T: FusedIterator<Item = char>> ParseToken<T> for Group {
                 continue;
             }
 
-            if let Some(token) = cursor.apply_parsers()? {
-                tokens.push(token)
+            match cursor.apply_parsers() {
+                Ok(Some(token)) => tokens.push(token),
+                Ok(None) => (),
+                Err(err @ (ParseError::EarlyEof(_) | ParseError::UnclosedDelimiter(..))) => {
+                    return Err(err)
+                }
+                // Lexing continues after the invalid token, like it does outside of groups.
+                Err(err) => cursor.report(err),
             }
         }
 
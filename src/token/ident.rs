@@ -18,6 +18,11 @@ impl XID {
 
 impl Sealed for XID {}
 
+/// Identifiers start with an XID start character or `_`, like in Rust.
+fn is_ident_start(char: char) -> bool {
+    char == '_' || unicode_ident::is_xid_start(char)
+}
+
 impl FromStr for XID {
     type Err = InvalidXID;
 
@@ -25,11 +30,11 @@ impl FromStr for XID {
         let mut chars = s.chars();
 
         let first_char = chars.next().ok_or(InvalidXID)?;
-        if !unicode_ident::is_xid_start(first_char) {
+        if !is_ident_start(first_char) {
             return Err(InvalidXID);
         }
 
-        while let Some(next_char) = chars.next() {
+        for next_char in chars {
             if !unicode_ident::is_xid_continue(next_char) {
                 return Err(InvalidXID);
             }
@@ -82,7 +87,7 @@ impl ToTokenTree for Ident {
 
 impl<T: FusedIterator<Item = char>> ParseToken<T> for Ident {
     fn parse(start: char, mut cursor: Cursor<T>) -> ParseResult<Self> {
-        if !unicode_ident::is_xid_start(start) {
+        if !is_ident_start(start) {
             return Err(ParseError::InvalidStart(start, "ident"));
         }
 
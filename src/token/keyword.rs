@@ -19,7 +19,7 @@ macro_rules! define_keyword {
 
             impl Sealed for $keyword {}
             impl Token for $keyword {
-                const NAME: &'static str = $value;
+                const NAME: &'static str = concat!("`", $value, "`");
 
                 fn parse_token(token_tree: TokenTree) -> Option<Self> {
                     let value = Ident::parse_token(token_tree)?;
@@ -56,5 +56,13 @@ define_keyword!(
     If => "if",
     Else => "else",
     Unsafe => "unsafe",
-    Import => "import"
+    Import => "import",
+    Spread => "spread",
+    Break => "break",
+    Continue => "continue",
+    Struct => "struct",
+    Enum => "enum",
+    Match => "match",
+    As => "as",
+    Null => "null"
 );
@@ -4,6 +4,7 @@ use std::{fmt, iter::FusedIterator};
 
 #[derive(Clone, PartialEq)]
 pub enum LiteralType {
+    Bool(bool),
     Byte(i8),
     Short(i16),
     Int(i32),
@@ -16,6 +17,7 @@ pub enum LiteralType {
 impl fmt::Debug for LiteralType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
+            Self::Bool(value) => write!(f, "{}", value),
             Self::Byte(value) => write!(f, "{}b", value),
             Self::Short(value) => write!(f, "{}s", value),
             Self::Int(value) => write!(f, "{}i", value),
@@ -31,6 +33,9 @@ impl fmt::Debug for LiteralType {
 pub struct Literal {
     span: Span,
     value: LiteralType,
+    /// The text and suffix of a number one past the maximum of its type, like `2147483648`. It
+    /// holds the minimum of the type and is only in range when negated.
+    overflow: Option<(String, char)>,
 }
 
 impl Literal {
@@ -41,6 +46,18 @@ impl Literal {
     pub fn into_inner(self) -> LiteralType {
         self.value
     }
+
+    /// Fails for a number which is only in range when it is negated.
+    pub fn check_range(&self) -> ParseResult<()> {
+        match &self.overflow {
+            Some((text, suffix)) => Err(ParseError::LiteralOutOfRange(
+                text.clone(),
+                *suffix,
+                self.span,
+            )),
+            None => Ok(()),
+        }
+    }
 }
 
 impl Spanned for Literal {
@@ -55,90 +72,317 @@ impl ToTokenTree for Literal {
     }
 }
 
-macro_rules! number_or_error {
-    ($buffer:ident $span:ident; $variant:ident) => {
-        LiteralType::$variant(
-            $buffer.parse().map_err(move |_| {
-                ParseError::UnexpectedToken($buffer, stringify!($variant), $span)
-            })?,
-        )
-    };
+/// The type of the numbers a suffix selects.
+pub(super) fn suffix_type(suffix: char) -> &'static str {
+    match suffix {
+        'b' => "byte",
+        's' => "short",
+        'i' => "int",
+        'l' => "long",
+        'f' => "float",
+        'd' => "double",
+        _ => unreachable!("{:?} is not a number suffix", suffix),
+    }
 }
 
-macro_rules! parse_number {
-    ($start:ident, $cursor:ident; $($char:literal = $variant:ident),+) => {
-        let mut buffer = String::from($start);
+/// The indefinite article in front of `word`, like "an `int`".
+pub(super) fn article(word: &str) -> &'static str {
+    if word.starts_with(['a', 'e', 'i', 'o', 'u']) {
+        "an"
+    } else {
+        "a"
+    }
+}
 
-        loop {
-            let next_char = $cursor.expect_peek()?;
-            if next_char == '.' || next_char.is_ascii_digit() {
-                buffer.push(next_char);
-                $cursor.consume();
-                continue;
+/// The values of the type a suffix selects.
+pub(super) fn suffix_range(suffix: char) -> String {
+    match suffix {
+        'b' => format!("from {} to {}", i8::MIN, i8::MAX),
+        's' => format!("from {} to {}", i16::MIN, i16::MAX),
+        'i' => format!("from {} to {}", i32::MIN, i32::MAX),
+        'l' => format!("from {} to {}", i64::MIN, i64::MAX),
+        _ => "to finite values".to_string(),
+    }
+}
+
+fn consume_into<T: FusedIterator<Item = char>>(cursor: &mut Cursor<T>, text: &mut String) {
+    text.extend(cursor.consume())
+}
+
+/// Parses a number like `1_000`, `0xFF`, `0b1010`, `0o17`, `2.5` or `1e-3`, optionally followed
+/// by a suffix selecting its type.
+///
+/// Hex, octal and binary numbers can use every bit of their type, so `0xFFFFFFFF` is `-1`. The
+/// `b`, `d` and `f` suffixes are hex digits, so those numbers can only be `short`, `int` or
+/// `long`.
+///
+/// Numbers have no sign, `-` is lexed as punctuation and negated literals are folded when lowering
+/// to MIR. A decimal one past the maximum of its type is lexed as the minimum, parsing rejects it
+/// unless it is negated.
+fn parse_number<T: FusedIterator<Item = char>>(
+    start: char,
+    mut cursor: Cursor<T>,
+) -> ParseResult<Literal> {
+    let mut text = String::from(start);
+    let radix = match (start, cursor.peek(), cursor.peek_ahead(1)) {
+        ('0', Some('x'), _) => 16,
+        ('0', Some('o'), _) => 8,
+        // `0b` on its own is a zero byte.
+        ('0', Some('b'), Some('0' | '1' | '_')) => 2,
+        _ => 10,
+    };
+    let mut digits = if radix == 10 {
+        start.to_string()
+    } else {
+        consume_into(&mut cursor, &mut text);
+        String::new()
+    };
+
+    let mut is_float = false;
+    let mut has_exponent = false;
+    while let Some(next_char) = cursor.peek() {
+        if next_char == '_' {
+        } else if next_char.is_digit(radix) {
+            digits.push(next_char);
+        } else if radix == 10 && next_char == '.' && !is_float {
+            digits.push('.');
+            is_float = true;
+        } else if radix == 10 && matches!(next_char, 'e' | 'E') && !has_exponent {
+            let signed = matches!(cursor.peek_ahead(1), Some('+' | '-'));
+            let exponent = cursor.peek_ahead(1 + signed as usize);
+            if !exponent.is_some_and(|char| char.is_ascii_digit()) {
+                break;
             }
 
-            let span;
-            let value = match next_char {
-                $(
-                    $char => {
-                        $cursor.consume();
-                        span = $cursor.into_span();
-                        number_or_error!(buffer span; $variant)
+            digits.push('e');
+            consume_into(&mut cursor, &mut text);
+            if signed {
+                digits.extend(cursor.peek());
+                consume_into(&mut cursor, &mut text);
+            }
+            is_float = true;
+            has_exponent = true;
+            continue;
+        } else {
+            break;
+        }
+        consume_into(&mut cursor, &mut text);
+    }
+
+    let suffix = match cursor.peek() {
+        Some(suffix @ ('b' | 's' | 'i' | 'l' | 'f' | 'd')) if !suffix.is_digit(radix) => {
+            consume_into(&mut cursor, &mut text);
+            Some(suffix)
+        }
+        _ => None,
+    };
+
+    // Letters or digits right after the number, like in `12px` or `0b102`, make it invalid.
+    let mut valid = !digits.is_empty();
+    while cursor.peek().is_some_and(unicode_ident::is_xid_continue) {
+        consume_into(&mut cursor, &mut text);
+        valid = false;
+    }
+
+    let span = cursor.into_span();
+    let floating = has_exponent || matches!(suffix, Some('f' | 'd'));
+    let suffix = suffix.unwrap_or(if is_float { 'f' } else { 'i' });
+    if !valid || (is_float || radix != 10) && matches!(suffix, 'f' | 'd') != is_float {
+        return Err(ParseError::InvalidNumber(text, span, floating));
+    }
+
+    let out_of_range = || ParseError::LiteralOutOfRange(text.clone(), suffix, span);
+    let mut overflow = None;
+    macro_rules! integer {
+        ($variant:ident, $signed:ty, $unsigned:ty) => {
+            LiteralType::$variant(if radix == 10 {
+                match digits.parse::<$signed>() {
+                    Ok(value) => value,
+                    Err(_) if digits.parse::<$unsigned>() == Ok(<$signed>::MIN.unsigned_abs()) => {
+                        overflow = Some((text.clone(), suffix));
+                        <$signed>::MIN
                     }
-                )+
-                _ => {
-                    span = $cursor.into_span();
-                    if buffer.contains('.') {
-                        number_or_error!(buffer span; Float)
-                    } else {
-                        number_or_error!(buffer span; Int)
+                    Err(_) => return Err(out_of_range()),
+                }
+            } else {
+                <$unsigned>::from_str_radix(&digits, radix).map_err(|_| out_of_range())? as $signed
+            })
+        };
+    }
+    macro_rules! float {
+        ($variant:ident, $float:ty) => {
+            LiteralType::$variant(
+                digits
+                    .parse::<$float>()
+                    .ok()
+                    .filter(|value| value.is_finite())
+                    .ok_or_else(out_of_range)?,
+            )
+        };
+    }
+
+    let value = match suffix {
+        'b' => integer!(Byte, i8, u8),
+        's' => integer!(Short, i16, u16),
+        'i' => integer!(Int, i32, u32),
+        'l' => integer!(Long, i64, u64),
+        'f' => float!(Float, f32),
+        _ => float!(Double, f64),
+    };
+
+    Ok(Literal {
+        span,
+        value,
+        overflow,
+    })
+}
+
+/// Parses `true` or `false`, they are literals instead of identifiers.
+fn parse_bool<T: FusedIterator<Item = char>>(start: char, cursor: &mut Cursor<T>) -> Option<bool> {
+    let (word, value) = match start {
+        't' => ("rue", true),
+        'f' => ("alse", false),
+        _ => return None,
+    };
+
+    let is_word = word
+        .chars()
+        .enumerate()
+        .all(|(index, char)| cursor.peek_ahead(index) == Some(char));
+    let ends = !cursor
+        .peek_ahead(word.len())
+        .is_some_and(unicode_ident::is_xid_continue);
+    if !(is_word && ends) {
+        return None;
+    }
+
+    for _ in 0..word.len() {
+        cursor.consume();
+    }
+    Some(value)
+}
+
+/// Parses the rest of an escape sequence after the `\`, returning the escaped character.
+fn parse_escape<T: FusedIterator<Item = char>>(
+    cursor: &mut Cursor<T>,
+    start: usize,
+) -> ParseResult<char> {
+    Ok(match cursor.expect_consume()? {
+        'n' => '\n',
+        'r' => '\r',
+        't' => '\t',
+        '0' => '\0',
+        '\\' => '\\',
+        '"' => '"',
+        'u' => {
+            let mut sequence = String::from("\\u");
+            let mut digits = String::new();
+            if cursor.peek() == Some('{') {
+                sequence.push('{');
+                cursor.consume();
+                while let Some(next_char) = cursor.peek() {
+                    if next_char == '"' {
+                        break;
+                    }
+                    sequence.push(next_char);
+                    cursor.consume();
+                    if next_char == '}' {
+                        break;
                     }
+                    digits.push(next_char);
                 }
-            };
+            }
 
-            return Ok(Literal {
-                span,
-                value,
-            });
+            // At most 6 hex digits, naming a unicode scalar value.
+            let char = (sequence.ends_with('}') && (1..=6).contains(&digits.len()))
+                .then(|| u32::from_str_radix(&digits, 16).ok())
+                .flatten()
+                .and_then(char::from_u32);
+            match char {
+                Some(char) => char,
+                None => {
+                    let span = cursor.span_at(start, sequence.chars().count());
+                    return Err(ParseError::InvalidEscape(sequence, span));
+                }
+            }
         }
-    }
+        other => {
+            let span = cursor.span_at(start, 2);
+            return Err(ParseError::InvalidEscape(format!("\\{}", other), span));
+        }
+    })
 }
 
 impl<T: FusedIterator<Item = char>> ParseToken<T> for Literal {
     fn parse(start: char, mut cursor: Cursor<T>) -> ParseResult<Self> {
         if start == '"' {
             let mut buffer = String::new();
-            let mut escaped = false;
+            // The string is read to its end even after an invalid escape, so lexing continues
+            // after it.
+            let mut error = None;
 
             loop {
-                let next_char = cursor.expect_consume()?;
+                let position = cursor.position();
+                match cursor.expect_consume()? {
+                    '\\' => match parse_escape(&mut cursor, position) {
+                        Ok(char) => buffer.push(char),
+                        Err(err) => {
+                            error.get_or_insert(err);
+                        }
+                    },
+                    '"' => break,
+                    next_char => buffer.push(next_char),
+                }
+            }
+
+            if let Some(err) = error {
+                return Err(err);
+            }
+            Ok(Literal {
+                span: cursor.into_span(),
+                value: LiteralType::String(buffer),
+                overflow: None,
+            })
+        } else if start == 'r' && matches!(cursor.peek(), Some('"' | '#')) {
+            // Raw strings are closed by a `"` followed by as many `#` as they were opened with.
+            let mut hashes = 0;
+            while cursor.peek_ahead(hashes) == Some('#') {
+                hashes += 1;
+            }
+            if cursor.peek_ahead(hashes) != Some('"') {
+                return Err(ParseError::InvalidStart(start, "literal"));
+            }
+            for _ in 0..=hashes {
+                cursor.consume();
+            }
 
-                if escaped {
-                    buffer.push(next_char);
-                    escaped = false;
-                } else if next_char == '\\' {
-                    escaped = true;
-                } else if next_char == '"' {
+            let mut buffer = String::new();
+            loop {
+                let next_char = cursor.expect_consume()?;
+                if next_char == '"'
+                    && (0..hashes).all(|index| cursor.peek_ahead(index) == Some('#'))
+                {
+                    for _ in 0..hashes {
+                        cursor.consume();
+                    }
                     break;
-                } else {
-                    buffer.push(next_char);
                 }
+                buffer.push(next_char);
             }
 
             Ok(Literal {
                 span: cursor.into_span(),
                 value: LiteralType::String(buffer),
+                overflow: None,
+            })
+        } else if start.is_ascii_digit() {
+            parse_number(start, cursor)
+        } else if let Some(value) = parse_bool(start, &mut cursor) {
+            Ok(Literal {
+                span: cursor.into_span(),
+                value: LiteralType::Bool(value),
+                overflow: None,
             })
-        } else if start == '-' || start.is_ascii_digit() {
-            parse_number!(
-                start, cursor;
-                'b' = Byte,
-                's' = Short,
-                'i' = Int,
-                'l' = Long,
-                'f' = Float,
-                'd' = Double
-            );
         } else {
             Err(ParseError::InvalidStart(start, "literal"))
         }
@@ -147,6 +391,229 @@ impl<T: FusedIterator<Item = char>> ParseToken<T> for Literal {
 
 impl fmt::Debug for Literal {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        self.value.fmt(f)
+        // The minimum it holds is only written after the `-` negating it.
+        if self.overflow.is_some() {
+            let value = format!("{:?}", self.value);
+            f.write_str(value.trim_start_matches('-'))
+        } else {
+            self.value.fmt(f)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LiteralType;
+    use crate::{
+        diagnostic::Diagnostic, parse_str, token::prelude::lex, FileId, ParseError, TokenTree,
+    };
+
+    fn string(input: &str) -> Result<String, ParseError> {
+        let tokens = parse_str(input)?;
+        match tokens.0.as_slice() {
+            [TokenTree::Literal(literal)] => match literal.inner() {
+                LiteralType::String(value) => Ok(value.clone()),
+                other => panic!("expected a string, got {:?}", other),
+            },
+            other => panic!("expected a single literal, got {:?}", other),
+        }
+    }
+
+    fn parse_literal(input: &str) -> Result<LiteralType, ParseError> {
+        match parse_str(input)?.0.as_slice() {
+            [TokenTree::Literal(literal)] => Ok(literal.inner().clone()),
+            other => panic!("expected a single literal, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn numbers() -> eyre::Result<()> {
+        for (input, expected) in [
+            ("1_000_000", LiteralType::Int(1_000_000)),
+            ("0xFF", LiteralType::Int(0xFF)),
+            ("0xffl", LiteralType::Long(0xFF)),
+            ("0xFFFFFFFF", LiteralType::Int(-1)),
+            ("0o17", LiteralType::Int(0o17)),
+            ("0b1010_0101s", LiteralType::Short(0b1010_0101)),
+            ("0b1111_1111b", LiteralType::Byte(-1)),
+            ("0b", LiteralType::Byte(0)),
+            ("127b", LiteralType::Byte(127)),
+            ("2.5", LiteralType::Float(2.5)),
+            ("1e-3", LiteralType::Float(1e-3)),
+            ("1.5E+3d", LiteralType::Double(1.5e3)),
+            ("3f", LiteralType::Float(3.0)),
+            ("9_223_372_036_854_775_807l", LiteralType::Long(i64::MAX)),
+        ] {
+            assert_eq!(parse_literal(input)?, expected, "{}", input);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn minus_is_not_part_of_numbers() -> eyre::Result<()> {
+        let tokens = parse_str("a-1")?;
+        assert!(matches!(
+            tokens.0.as_slice(),
+            [
+                TokenTree::Ident(_),
+                TokenTree::Punct(_),
+                TokenTree::Literal(_)
+            ]
+        ));
+        assert!(matches!(
+            parse_str("-")?.0.as_slice(),
+            [TokenTree::Punct(_)]
+        ));
+        assert!(matches!(
+            parse_str("1")?.0.as_slice(),
+            [TokenTree::Literal(_)]
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn booleans() -> eyre::Result<()> {
+        assert_eq!(parse_literal("true")?, LiteralType::Bool(true));
+        assert_eq!(parse_literal("false")?, LiteralType::Bool(false));
+        assert!(matches!(
+            parse_str("truest f fals")?.0.as_slice(),
+            [
+                TokenTree::Ident(_),
+                TokenTree::Ident(_),
+                TokenTree::Ident(_)
+            ]
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_numbers() {
+        for input in ["0x", "12px", "0b102", "1.5i", "1e3l", "0o7f", "1e"] {
+            assert!(
+                matches!(parse_literal(input), Err(ParseError::InvalidNumber(text, _, _)) if text == input),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn only_floating_numbers_get_the_float_note() {
+        for (input, noted) in [
+            ("0x", false),
+            ("12px", false),
+            ("1e3l", true),
+            ("0o7f", true),
+        ] {
+            let diagnostic = Diagnostic::from(parse_literal(input).unwrap_err());
+            assert_eq!(!diagnostic.notes.is_empty(), noted, "{}", input);
+        }
+    }
+
+    #[test]
+    fn out_of_range_labels_use_the_right_article() {
+        for (input, label) in [
+            ("2147483649", "does not fit in an `int`"),
+            ("129b", "does not fit in a `byte`"),
+        ] {
+            let diagnostic = Diagnostic::from(parse_literal(input).unwrap_err());
+            assert_eq!(diagnostic.primary.unwrap().message, label);
+        }
+    }
+
+    #[test]
+    fn out_of_range_numbers_name_their_suffix() {
+        for (input, suffix) in [
+            ("129b", 'b'),
+            ("0x1_0000s", 's'),
+            ("2147483649", 'i'),
+            ("0x1_0000_0000_0000_0000l", 'l'),
+            ("1e39", 'f'),
+            ("1e309d", 'd'),
+        ] {
+            match parse_literal(input) {
+                Err(err @ ParseError::LiteralOutOfRange(_, found, _)) => {
+                    assert_eq!(found, suffix, "{}", input);
+                    assert!(err.to_string().contains(&format!("(suffix `{}`)", suffix)));
+                }
+                other => panic!("expected {} to be out of range, got {:?}", input, other),
+            }
+        }
+    }
+
+    #[test]
+    fn minimum_magnitudes_are_only_in_range_negated() -> eyre::Result<()> {
+        for (input, expected) in [
+            ("128b", LiteralType::Byte(i8::MIN)),
+            ("32768s", LiteralType::Short(i16::MIN)),
+            ("2147483648", LiteralType::Int(i32::MIN)),
+            ("9223372036854775808l", LiteralType::Long(i64::MIN)),
+        ] {
+            let tokens = parse_str(input)?;
+            let [TokenTree::Literal(literal)] = tokens.0.as_slice() else {
+                panic!("expected a single literal for {}", input);
+            };
+            assert_eq!(literal.inner(), &expected, "{}", input);
+            assert!(matches!(
+                literal.check_range(),
+                Err(ParseError::LiteralOutOfRange(text, _, _)) if text == input
+            ));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn string_escapes() -> eyre::Result<()> {
+        assert_eq!(string(r#""a\nb\t\\\"\0""#)?, "a\nb\t\\\"\0");
+        assert_eq!(string(r#""\u{48}\u{1F600}""#)?, "H\u{1F600}");
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_escapes() {
+        for (input, sequence, index, width) in [
+            (r#""ab\q""#, r"\q", 3, 2),
+            (r#""\u{110000}""#, r"\u{110000}", 1, 10),
+            (r#""\u{}""#, r"\u{}", 1, 4),
+            (r#""\u41""#, r"\u", 1, 2),
+        ] {
+            match string(input) {
+                Err(ParseError::InvalidEscape(found, span)) => {
+                    assert_eq!(found, sequence);
+                    assert_eq!((span.index, span.width), (index, width));
+                }
+                other => panic!("expected an invalid escape in {}, got {:?}", input, other),
+            }
+        }
+    }
+
+    #[test]
+    fn lexing_continues_after_invalid_escapes() {
+        let lexed = lex(FileId::default(), r#"$say("\q" "\u{}") after"#.chars());
+        assert_eq!(lexed.errors.len(), 2);
+        assert!(matches!(
+            lexed.tokens.0.as_slice(),
+            [TokenTree::Punct(_), TokenTree::Ident(_), TokenTree::Group(group), TokenTree::Ident(after)]
+                if group.tokens().0.is_empty() && after.inner() == "after"
+        ));
+    }
+
+    #[test]
+    fn raw_strings() -> eyre::Result<()> {
+        assert_eq!(string(r#"r"\n""#)?, r"\n");
+        assert_eq!(string(r###"r#"{"text": "hi"}"#"###)?, r#"{"text": "hi"}"#);
+        assert_eq!(string(r###"r##"a "# b"##"###)?, r##"a "# b"##);
+        Ok(())
+    }
+
+    #[test]
+    fn raw_prefix_is_an_ident_otherwise() -> eyre::Result<()> {
+        let tokens = parse_str("r rx r#a")?;
+        assert!(matches!(
+            tokens.0.as_slice(),
+            [TokenTree::Ident(a), TokenTree::Ident(b), TokenTree::Ident(c)]
+                if a.inner() == "r" && b.inner() == "rx" && c.inner() == "r"
+        ));
+        Ok(())
     }
 }
@@ -1,5 +1,6 @@
 use self::cursor::Cursor;
 pub use self::{
+    comment::DocComment,
     group::{hir::*, Delimiter, Group},
     ident::{Ident, InvalidXID, XID},
     keyword::*,
@@ -10,6 +11,7 @@ pub use self::{
 use crate::{ParseError, Sealed, Spanned, TokenTree};
 use std::iter::FusedIterator;
 
+mod comment;
 mod cursor;
 mod group;
 mod ident;
@@ -36,6 +38,7 @@ where
 }
 
 pub trait Token: Sized + ToTokenTree + Sealed {
+    /// What errors expecting the token call it, its spelling for punctuation and keywords.
     const NAME: &'static str;
     fn parse_token(token_tree: TokenTree) -> Option<Self>;
 }
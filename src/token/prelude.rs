@@ -1,8 +1,10 @@
 use super::{
-    cursor::Cursor, Delimiter, Group, Ident, Literal, ParseResult, Punct, PunctToken, ToTokenTree,
+    cursor::Cursor,
+    literal::{article, suffix_range, suffix_type},
+    Delimiter, DocComment, Group, Ident, Literal, ParseResult, Punct, PunctToken, ToTokenTree,
     Token,
 };
-use crate::{Sealed, Span, Spanned};
+use crate::{diagnostic::Diagnostic, FileId, Sealed, Span, Spanned};
 use std::{
     fmt::{self, Write},
     iter::FusedIterator,
@@ -12,7 +14,18 @@ use thiserror::Error;
 #[derive(Debug, Error)]
 pub enum ParseError {
     #[error("Unexpected end of file")]
-    EarlyEof,
+    EarlyEof(Span),
+    #[error("Unclosed delimiter `{0}`")]
+    UnclosedDelimiter(char, Span, Span),
+    #[error("Unterminated block comment")]
+    UnclosedComment(Span),
+    #[error("Invalid escape sequence `{0}`")]
+    InvalidEscape(String, Span),
+    /// A malformed number, and whether it was written like a `float` or `double`.
+    #[error("Invalid number `{0}`")]
+    InvalidNumber(String, Span, bool),
+    #[error("Number `{0}` is out of range for `{}` (suffix `{1}`)", suffix_type(*.1))]
+    LiteralOutOfRange(String, char, Span),
     #[error("Unexpected {0:?} while parsing {1}")]
     UnexpectedToken(String, &'static str, Span),
     #[error("Invalid starting character {0:?} for {1}")]
@@ -21,8 +34,58 @@ pub enum ParseError {
     NotAToken,
 }
 
+impl From<ParseError> for Diagnostic {
+    fn from(err: ParseError) -> Self {
+        let diagnostic = Diagnostic::error(&err);
+        match err {
+            ParseError::EarlyEof(span) => diagnostic.with_primary(span, "expected more input"),
+            ParseError::UnclosedDelimiter(_, open, end) => diagnostic
+                .with_primary(
+                    end,
+                    "expected the delimiter to be closed before the end of the file",
+                )
+                .with_secondary(open, "unclosed delimiter"),
+            ParseError::UnclosedComment(span) => diagnostic
+                .with_primary(span, "the comment starts here")
+                .with_note("block comments nest, every `/*` needs its own `*/`"),
+            ParseError::InvalidEscape(_, span) => diagnostic
+                .with_primary(span, "")
+                .with_help(
+                    "valid escapes are `\\n`, `\\r`, `\\t`, `\\0`, `\\\\`, `\\\"` and `\\u{...}`",
+                )
+                .with_help("raw strings like `r\"...\"` do not process escapes"),
+            ParseError::InvalidNumber(_, span, floating) => {
+                let diagnostic = diagnostic.with_primary(span, "").with_help(
+                    "numbers look like `1_000`, `0xFF`, `0o17`, `0b1010`, `2.5` or `1e-3`",
+                );
+                if floating {
+                    diagnostic.with_note("only decimal numbers can be `float`s or `double`s")
+                } else {
+                    diagnostic
+                }
+            }
+            ParseError::LiteralOutOfRange(_, suffix, span) => diagnostic
+                .with_primary(
+                    span,
+                    format!(
+                        "does not fit in {} `{}`",
+                        article(suffix_type(suffix)),
+                        suffix_type(suffix)
+                    ),
+                )
+                .with_note(format!(
+                    "`{}` numbers range {}",
+                    suffix_type(suffix),
+                    suffix_range(suffix)
+                )),
+            ParseError::UnexpectedToken(_, _, span) => diagnostic.with_primary(span, ""),
+            ParseError::InvalidStart(_, _) | ParseError::NotAToken => diagnostic,
+        }
+    }
+}
+
 macro_rules! define_token_tree {
-    ($($token:ident),+) => {
+    ($($token:ident => $name:literal),+) => {
         #[derive(Clone, PartialEq)]
         pub enum TokenTree {
             $($token($token),)+
@@ -47,7 +110,7 @@ macro_rules! define_token_tree {
         $(
             impl Sealed for $token {}
             impl Token for $token {
-                const NAME: &'static str = stringify!($token);
+                const NAME: &'static str = $name;
 
                 fn parse_token(token_tree: TokenTree) -> Option<Self> {
                     if let TokenTree::$token(value) = token_tree {
@@ -61,7 +124,13 @@ macro_rules! define_token_tree {
     }
 }
 
-define_token_tree!(Punct, Ident, Literal, Group);
+define_token_tree!(
+    Punct => "punctuation",
+    Ident => "an identifier",
+    Literal => "a literal",
+    Group => "a bracketed group",
+    DocComment => "a doc comment"
+);
 
 impl ToTokenTree for TokenTree {
     fn to_token_tree(self) -> TokenTree {
@@ -77,6 +146,12 @@ impl TokenStream {
     }
 }
 
+impl Default for TokenStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Spanned for TokenStream {
     fn span(&self) -> Span {
         self.0.span()
@@ -97,7 +172,7 @@ fn handle_next_char(next_char: Option<&TokenTree>, f: &mut fmt::Formatter) -> fm
                 Delimiter::Brace => f.write_char(' '),
             },
             TokenTree::Ident(_) => f.write_char(' '),
-            TokenTree::Literal(_) => f.write_char(' '),
+            TokenTree::Literal(_) | TokenTree::DocComment(_) => f.write_char(' '),
             TokenTree::Punct(punct) => match punct.inner() {
                 PunctToken::And
                 | PunctToken::Equals
@@ -130,6 +205,7 @@ impl fmt::Debug for TokenStream {
 
             match cur_char {
                 TokenTree::Literal(_) => handle_next_char(next_char, f)?,
+                TokenTree::DocComment(_) => f.write_char('\n')?,
                 TokenTree::Ident(ident) => {
                     if ident.inner() == "for" {
                         if let Some(TokenTree::Group(group)) = next_char {
@@ -162,13 +238,11 @@ impl fmt::Debug for TokenStream {
                     | PunctToken::Assign
                     | PunctToken::Colon
                     | PunctToken::Comma => f.write_char(' ')?,
-                    PunctToken::Semicolon => {
-                        if let Some(_) = next_char {
-                            if f.alternate() {
-                                f.write_char('\n')?
-                            } else {
-                                f.write_char(' ')?
-                            }
+                    PunctToken::Semicolon if next_char.is_some() => {
+                        if f.alternate() {
+                            f.write_char('\n')?
+                        } else {
+                            f.write_char(' ')?
                         }
                     }
                     _ => (),
@@ -184,9 +258,26 @@ pub fn parse_str(value: &str) -> ParseResult<TokenStream> {
     parse_iter(value.chars())
 }
 
-pub fn parse_iter<T: FusedIterator<Item = char>>(mut iterator: T) -> ParseResult<TokenStream> {
-    Cursor::run(&mut iterator, |mut cursor| {
+pub fn parse_iter<T: FusedIterator<Item = char>>(iterator: T) -> ParseResult<TokenStream> {
+    let Lexed { tokens, errors, .. } = lex(FileId::default(), iterator);
+    match errors.into_iter().next() {
+        Some(err) => Err(err),
+        None => Ok(tokens),
+    }
+}
+
+pub(crate) struct Lexed {
+    pub tokens: TokenStream,
+    pub errors: Vec<ParseError>,
+    /// Character index of the first character of every line.
+    pub line_starts: Vec<usize>,
+}
+
+/// Splits `iterator` into tokens, reporting every error instead of stopping at the first.
+pub(crate) fn lex<T: FusedIterator<Item = char>>(file: FileId, mut iterator: T) -> Lexed {
+    Cursor::run(file, &mut iterator, |mut cursor| {
         let mut tokens = vec![];
+        let mut errors = vec![];
 
         while let Some(next_char) = cursor.peek() {
             if next_char.is_whitespace() {
@@ -194,11 +285,20 @@ pub fn parse_iter<T: FusedIterator<Item = char>>(mut iterator: T) -> ParseResult
                 continue;
             }
 
-            if let Some(token) = cursor.apply_parsers()? {
-                tokens.push(token)
+            // The characters of an invalid token are skipped, lexing continues after them.
+            let token = cursor.apply_parsers();
+            errors.append(&mut cursor.take_errors());
+            match token {
+                Ok(Some(token)) => tokens.push(token),
+                Ok(None) => (),
+                Err(err) => errors.push(err),
             }
         }
 
-        Ok(tokens.into())
+        Lexed {
+            tokens: tokens.into(),
+            errors,
+            line_starts: cursor.line_starts().to_vec(),
+        }
     })
 }
@@ -66,7 +66,7 @@ macro_rules! define_punct {
 
                 impl Sealed for $variant {}
                 impl Token for $variant {
-                    const NAME: &'static str = stringify!($variant);
+                    const NAME: &'static str = concat!("`", $char1 $(, $char2)?, "`");
 
                     fn parse_token(token_tree: TokenTree) -> Option<Self> {
                         Punct::parse_token(token_tree)?.into()
@@ -104,6 +104,11 @@ define_punct!(
     GreaterThan => '>',
     GreaterThanEquals => '>' '=',
     Assign => '=',
+    PlusAssign => '+' '=',
+    MinusAssign => '-' '=',
+    StarAssign => '*' '=',
+    SlashAssign => '/' '=',
+    PercentAssign => '%' '=',
     Not => '!',
     NotEquals => '!' '=',
     Dot => '.',
@@ -117,6 +122,7 @@ define_punct!(
     Semicolon => ';',
     Comma => ',',
     Lambda => '-' '>',
+    FatArrow => '=' '>',
     MacroPrefix => '$'
 );
 
@@ -97,11 +97,7 @@ impl<T: Read> Iterator for CharReader<T> {
     type Item = char;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let Ok(value) = self.next() {
-            value
-        } else {
-            None
-        }
+        self.next().unwrap_or_default()
     }
 }
 
@@ -0,0 +1,22 @@
+use std::{path::Path, process::Command};
+
+#[test]
+fn example_compiles() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("example");
+    let target = Path::new(env!("CARGO_TARGET_TMPDIR")).join("example");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_stonescript"))
+        .arg("--root")
+        .arg(&root)
+        .arg("--target")
+        .arg(&target)
+        .output()
+        .unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(target.join("pack.mcmeta").exists());
+}